@@ -0,0 +1,76 @@
+use nalgebra::Vector3;
+
+use crate::world::BlockPos;
+
+/// An axis aligned box in world space.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Aabb {
+    pub min: Vector3<f32>,
+    pub max: Vector3<f32>,
+}
+
+impl Aabb {
+    pub fn new(min: Vector3<f32>, max: Vector3<f32>) -> Self {
+        Self { min, max }
+    }
+
+    /// The full cube a block takes up
+    pub fn block(pos: BlockPos) -> Self {
+        let min = Vector3::new(pos.x as f32, pos.y as f32, pos.z as f32);
+        Self::new(min, min + Vector3::new(1.0, 1.0, 1.0))
+    }
+
+    /// A box standing on `feet`, like an entity's
+    pub fn around_feet(feet: Vector3<f32>, width: f32, height: f32) -> Self {
+        let half = width / 2.0;
+        Self::new(
+            Vector3::new(feet.x - half, feet.y, feet.z - half),
+            Vector3::new(feet.x + half, feet.y + height, feet.z + half),
+        )
+    }
+
+    /// Touching faces don't count
+    pub fn intersects(&self, other: &Aabb) -> bool {
+        (0..3).all(|axis| self.min[axis] < other.max[axis] && self.max[axis] > other.min[axis])
+    }
+
+    pub fn offset(&self, by: Vector3<f32>) -> Self {
+        Self::new(self.min + by, self.max + by)
+    }
+
+    pub fn grow(&self, by: f32) -> Self {
+        let by = Vector3::new(by, by, by);
+        Self::new(self.min - by, self.max + by)
+    }
+
+    /// Stretched along `by`, covering everywhere the box passes through while moving
+    pub fn expand_towards(&self, by: Vector3<f32>) -> Self {
+        let (mut min, mut max) = (self.min, self.max);
+        for axis in 0..3 {
+            if by[axis] < 0.0 {
+                min[axis] += by[axis];
+            } else {
+                max[axis] += by[axis];
+            }
+        }
+        Self::new(min, max)
+    }
+
+    /// How far `moving` can go along `axis` before running into this box, starting from `offset`.
+    /// Vanilla's `calculateXOffset` and friends rolled into one, boxes that already overlap don't block.
+    pub fn clip_offset(&self, moving: &Aabb, axis: usize, offset: f32) -> f32 {
+        let overlaps_across = (0..3)
+            .filter(|&other| other != axis)
+            .all(|other| moving.max[other] > self.min[other] && moving.min[other] < self.max[other]);
+        if !overlaps_across {
+            return offset;
+        }
+        if offset > 0.0 && moving.max[axis] <= self.min[axis] {
+            offset.min(self.min[axis] - moving.max[axis])
+        } else if offset < 0.0 && moving.min[axis] >= self.max[axis] {
+            offset.max(self.max[axis] - moving.min[axis])
+        } else {
+            offset
+        }
+    }
+}
@@ -0,0 +1,224 @@
+use std::collections::HashMap;
+use std::io::Cursor;
+
+use image::{Rgba, RgbaImage};
+use include_dir::Dir;
+
+/// What `TextureAtlas::uv` hands back for names that aren't in the atlas
+pub const MISSING_TEXTURE: &str = "missing";
+
+/// A tile's corners in texture coordinates, v runs bottom to top like GL expects
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct UvRect {
+    pub u0: f32,
+    pub v0: f32,
+    pub u1: f32,
+    pub v1: f32,
+}
+
+/// Where a tile ended up in the atlas image, in pixels from the top left
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Tile {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+pub struct TextureAtlas {
+    pub image: RgbaImage,
+    pub tiles: HashMap<String, Tile>,
+    pub mip_levels: u32,
+}
+
+impl TextureAtlas {
+    pub fn tile(&self, name: &str) -> Option<Tile> {
+        self.tiles.get(name).copied()
+    }
+
+    pub fn uv(&self, name: &str) -> UvRect {
+        let tile = self.tile(name).or_else(|| self.tile(MISSING_TEXTURE)).expect("atlas has no missing texture");
+        let (width, height) = (self.image.width() as f32, self.image.height() as f32);
+        UvRect {
+            u0: tile.x as f32 / width,
+            v0: 1.0 - (tile.y + tile.height) as f32 / height,
+            u1: (tile.x + tile.width) as f32 / width,
+            v1: 1.0 - tile.y as f32 / height,
+        }
+    }
+}
+
+/// Packs textures into one image. Every tile is surrounded by a gutter of its own edge pixels,
+/// and both the gutter and the tile positions are multiples of `1 << mip_levels`, so none of the
+/// generated mip levels average texels from two different tiles together.
+pub struct AtlasBuilder {
+    padding: u32,
+    mip_levels: u32,
+    textures: Vec<(String, RgbaImage)>,
+}
+
+impl AtlasBuilder {
+    pub fn new() -> Self {
+        Self {
+            padding: 1,
+            mip_levels: 4,
+            textures: vec![(MISSING_TEXTURE.to_string(), missing_texture())],
+        }
+    }
+
+    /// Every PNG in the directory, named after the file without its extension
+    pub fn from_dir(dir: &Dir) -> Self {
+        let mut builder = Self::new();
+        for file in dir.files() {
+            let Some(name) = file.path().to_str().and_then(|path| path.strip_suffix(".png")) else {
+                continue;
+            };
+            let img = image::load(Cursor::new(file.contents()), image::ImageFormat::Png)
+                .expect("Failed to load texture image")
+                .to_rgba8();
+            builder = builder.add(name, img);
+        }
+        builder
+    }
+
+    pub fn padding(mut self, padding: u32) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    pub fn mip_levels(mut self, mip_levels: u32) -> Self {
+        self.mip_levels = mip_levels;
+        self
+    }
+
+    pub fn add(mut self, name: &str, image: RgbaImage) -> Self {
+        self.textures.retain(|(existing, _)| existing != name);
+        self.textures.push((name.to_string(), image));
+        self
+    }
+
+    pub fn build(mut self) -> TextureAtlas {
+        let align = 1 << self.mip_levels;
+        let gutter = if self.padding == 0 { 0 } else { self.padding.next_multiple_of(align) };
+        let slot = |img: &RgbaImage| (img.width().next_multiple_of(align) + gutter * 2, img.height().next_multiple_of(align) + gutter * 2);
+
+        // tallest first keeps the shelves tidy, names break ties so the layout never changes between runs
+        self.textures.sort_by(|(a_name, a), (b_name, b)| b.height().cmp(&a.height()).then(a_name.cmp(b_name)));
+
+        let area: u32 = self.textures.iter().map(|(_, img)| slot(img).0 * slot(img).1).sum();
+        let widest = self.textures.iter().map(|(_, img)| slot(img).0).max().unwrap_or(align);
+        let width = ((area as f32).sqrt().ceil() as u32).max(widest).next_power_of_two();
+
+        let mut positions = Vec::with_capacity(self.textures.len());
+        let (mut x, mut y, mut shelf_height) = (0, 0, 0);
+        for (_, img) in &self.textures {
+            let (slot_width, slot_height) = slot(img);
+            if x + slot_width > width {
+                x = 0;
+                y += shelf_height;
+                shelf_height = 0;
+            }
+            positions.push((x + gutter, y + gutter));
+            x += slot_width;
+            shelf_height = shelf_height.max(slot_height);
+        }
+        let height = (y + shelf_height).next_power_of_two();
+
+        let mut atlas = RgbaImage::new(width, height);
+        let mut tiles = HashMap::new();
+        for ((name, img), (tile_x, tile_y)) in self.textures.iter().zip(positions) {
+            // draw the tile over its whole gutter, clamping to the edge pixels outside of it
+            let gutter = gutter as i64;
+            for py in -gutter..img.height() as i64 + gutter {
+                for px in -gutter..img.width() as i64 + gutter {
+                    let sx = px.clamp(0, img.width() as i64 - 1) as u32;
+                    let sy = py.clamp(0, img.height() as i64 - 1) as u32;
+                    let (ax, ay) = ((tile_x as i64 + px) as u32, (tile_y as i64 + py) as u32);
+                    atlas.put_pixel(ax, ay, *img.get_pixel(sx, sy));
+                }
+            }
+            tiles.insert(name.clone(), Tile { x: tile_x, y: tile_y, width: img.width(), height: img.height() });
+        }
+
+        TextureAtlas { image: atlas, tiles, mip_levels: self.mip_levels }
+    }
+}
+
+impl Default for AtlasBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Vanilla's magenta and black checkerboard
+fn missing_texture() -> RgbaImage {
+    RgbaImage::from_fn(16, 16, |x, y| {
+        if (x < 8) == (y < 8) {
+            Rgba([248, 0, 248, 255])
+        } else {
+            Rgba([0, 0, 0, 255])
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tiles of a few different sizes, each a solid colour of its own
+    fn builder() -> AtlasBuilder {
+        let sizes = [(16, 16), (16, 16), (32, 32), (16, 32), (8, 8), (64, 16), (16, 16), (24, 40)];
+        sizes.iter().enumerate().fold(AtlasBuilder::new(), |builder, (i, &(width, height))| {
+            builder.add(&format!("tile{i}"), RgbaImage::from_pixel(width, height, Rgba([i as u8 * 30, 100, 200, 255])))
+        })
+    }
+
+    #[test]
+    fn tiles_and_their_padding_never_overlap() {
+        for (padding, mip_levels) in [(0, 0), (1, 0), (1, 4), (3, 2)] {
+            let atlas = builder().padding(padding).mip_levels(mip_levels).build();
+            let align = 1 << mip_levels;
+            let gutter = if padding == 0 { 0 } else { padding.next_multiple_of(align) };
+            // each tile with its gutter, as x0, y0, x1, y1
+            let slots: Vec<(i64, i64, i64, i64)> = atlas
+                .tiles
+                .values()
+                .map(|tile| {
+                    let (x, y, gutter) = (tile.x as i64, tile.y as i64, gutter as i64);
+                    (x - gutter, y - gutter, x + tile.width as i64 + gutter, y + tile.height as i64 + gutter)
+                })
+                .collect();
+            for (i, a) in slots.iter().enumerate() {
+                assert!(a.0 >= 0 && a.1 >= 0 && a.2 <= atlas.image.width() as i64 && a.3 <= atlas.image.height() as i64);
+                for b in &slots[i + 1..] {
+                    let overlaps = a.0 < b.2 && b.0 < a.2 && a.1 < b.3 && b.1 < a.3;
+                    assert!(!overlaps, "{a:?} and {b:?} overlap with padding {padding}");
+                }
+            }
+            for tile in atlas.tiles.values() {
+                assert_eq!((tile.x % align, tile.y % align), (0, 0));
+            }
+        }
+    }
+
+    #[test]
+    fn uv_covers_the_packed_tile() {
+        let atlas = builder().build();
+        let (width, height) = (atlas.image.width() as f32, atlas.image.height() as f32);
+        for (name, tile) in &atlas.tiles {
+            let uv = atlas.uv(name);
+            assert_eq!((uv.u0 * width, uv.u1 * width), (tile.x as f32, (tile.x + tile.width) as f32));
+            // v counts from the bottom
+            assert_eq!(((1.0 - uv.v1) * height, (1.0 - uv.v0) * height), (tile.y as f32, (tile.y + tile.height) as f32));
+        }
+        let tile = atlas.tile("tile5").unwrap();
+        assert_eq!(atlas.image.get_pixel(tile.x, tile.y), &Rgba([150, 100, 200, 255]));
+        assert_eq!(atlas.image.get_pixel(tile.x + tile.width - 1, tile.y + tile.height - 1), &Rgba([150, 100, 200, 255]));
+    }
+
+    #[test]
+    fn unknown_names_get_the_missing_texture() {
+        let atlas = builder().build();
+        assert_eq!(atlas.uv("nothing"), atlas.uv(MISSING_TEXTURE));
+    }
+}
@@ -0,0 +1,144 @@
+//! Runs the world for vanilla 1.8.9 clients to join: `nanocraft-server [world dir] [port]`.
+//! Type `save` to save, `list` to see who's on, or `stop` to save and quit. `op <name>` and
+//! `deop <name>` decide who can run commands from chat, kept in `ops.txt` in the world dir.
+//! Anything else goes through the same commands as chat, like `time set day`.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::exit;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use std::{env, thread};
+
+use nanocraft::command;
+use nanocraft::game::{Game, TICKS_PER_SECOND, UNLOAD_MARGIN};
+use nanocraft::net::server::{Server, MAX_PLAYERS, VIEW_DISTANCE};
+use nanocraft::net::DEFAULT_PORT;
+use nanocraft::storage::{load_game, save_world};
+
+const DEFAULT_WORLD_DIR: &str = "world";
+/// One name per line
+const OPS_FILE: &str = "ops.txt";
+const MOTD: &str = "A Nanocraft server";
+/// Every five minutes
+const AUTOSAVE_TICKS: u64 = 6000;
+/// How far behind the server can fall before it gives up on catching up
+const MAX_LAG: Duration = Duration::from_secs(2);
+
+fn main() {
+    let dir = env::args().nth(1).map(PathBuf::from).unwrap_or_else(|| PathBuf::from(DEFAULT_WORLD_DIR));
+    let port = match env::args().nth(2).map(|port| port.parse::<u16>()) {
+        None => DEFAULT_PORT,
+        Some(Ok(port)) => port,
+        Some(Err(_)) => {
+            eprintln!("Usage: nanocraft-server [world dir] [port]");
+            exit(2);
+        }
+    };
+
+    let mut game = match load_game(&dir) {
+        Ok(Some(game)) => {
+            println!("Loaded {} (seed {})", dir.display(), game.seed());
+            game
+        }
+        Ok(None) => {
+            let seed = SystemTime::now().duration_since(UNIX_EPOCH).map(|time| time.as_nanos() as i64).unwrap_or(0);
+            println!("Generating a new world in {} (seed {})", dir.display(), seed);
+            Game::new(seed)
+        }
+        Err(err) => {
+            eprintln!("Couldn't load {}: {}", dir.display(), err);
+            exit(1);
+        }
+    };
+    save(&mut game, &dir);
+
+    let mut server = match Server::bind(("0.0.0.0", port), MOTD) {
+        Ok(server) => server,
+        Err(err) => {
+            eprintln!("Couldn't listen on port {}: {}", port, err);
+            exit(1);
+        }
+    };
+    println!("Listening on {}", server.addr());
+    // nobody's an op until the file says so
+    for name in fs::read_to_string(dir.join(OPS_FILE)).unwrap_or_default().lines().map(str::trim).filter(|name| !name.is_empty()) {
+        server.op(name);
+    }
+
+    // picked up between ticks
+    let commands = command::stdin_lines();
+
+    let tick_length = Duration::from_secs(1) / TICKS_PER_SECOND;
+    let mut next_tick = Instant::now();
+    loop {
+        while let Ok(command) = commands.try_recv() {
+            match command.split_whitespace().collect::<Vec<_>>().as_slice() {
+                ["stop"] => {
+                    server.shutdown("Server closed");
+                    save(&mut game, &dir);
+                    return;
+                }
+                ["save"] => save(&mut game, &dir),
+                ["list"] => {
+                    let names = server.player_names();
+                    println!("{} of {} players online: {}", names.len(), MAX_PLAYERS, names.join(", "));
+                }
+                ["op", name] => {
+                    match server.op(name) {
+                        true => println!("Made {} an op", name),
+                        false => println!("{} is already an op", name),
+                    }
+                    save_ops(&server, &dir);
+                }
+                ["deop", name] => {
+                    match server.deop(name) {
+                        true => println!("{} is no longer an op", name),
+                        false => println!("{} isn't an op", name),
+                    }
+                    save_ops(&server, &dir);
+                }
+                [] => {}
+                _ => match command::run(&mut game, command.trim()) {
+                    Ok(message) => {
+                        println!("{}", message);
+                        server.send_time(&game);
+                    }
+                    Err(message) => println!("{}", message),
+                },
+            }
+        }
+
+        game.tick_world();
+        server.tick(&mut game);
+        if let Err(err) = game.unload_outside(&server.player_chunks(), VIEW_DISTANCE + UNLOAD_MARGIN) {
+            eprintln!("Couldn't save chunks to {}: {}", dir.display(), err);
+        }
+        if game.ticks % AUTOSAVE_TICKS == 0 {
+            save(&mut game, &dir);
+        }
+
+        next_tick += tick_length;
+        let now = Instant::now();
+        if next_tick > now {
+            thread::sleep(next_tick - now);
+        } else if now - next_tick > MAX_LAG {
+            println!("Can't keep up, skipping {} ticks", ((now - next_tick).as_secs_f32() * TICKS_PER_SECOND as f32) as u32);
+            next_tick = now;
+        }
+    }
+}
+
+fn save_ops(server: &Server, dir: &Path) {
+    let mut ops = server.ops().join("\n");
+    ops.push('\n');
+    if let Err(err) = fs::create_dir_all(dir).and_then(|()| fs::write(dir.join(OPS_FILE), ops)) {
+        eprintln!("Couldn't save the ops to {}: {}", dir.display(), err);
+    }
+}
+
+fn save(game: &mut Game, dir: &Path) {
+    match save_world(game, dir) {
+        Ok(saved) => println!("Saved {} chunks to {}", saved, dir.display()),
+        Err(err) => eprintln!("Couldn't save to {}: {}", dir.display(), err),
+    }
+}
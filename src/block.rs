@@ -0,0 +1,309 @@
+use std::collections::HashMap;
+
+use crate::world::BlockState;
+
+pub const STONE: BlockState = BlockState::new(1, 0);
+pub const GRASS: BlockState = BlockState::new(2, 0);
+pub const DIRT: BlockState = BlockState::new(3, 0);
+pub const COBBLESTONE: BlockState = BlockState::new(4, 0);
+pub const OAK_PLANKS: BlockState = BlockState::new(5, 0);
+pub const BEDROCK: BlockState = BlockState::new(7, 0);
+pub const FLOWING_WATER: BlockState = BlockState::new(8, 0);
+pub const WATER: BlockState = BlockState::new(9, 0);
+pub const FLOWING_LAVA: BlockState = BlockState::new(10, 0);
+pub const LAVA: BlockState = BlockState::new(11, 0);
+pub const SAND: BlockState = BlockState::new(12, 0);
+pub const GRAVEL: BlockState = BlockState::new(13, 0);
+pub const GOLD_ORE: BlockState = BlockState::new(14, 0);
+pub const IRON_ORE: BlockState = BlockState::new(15, 0);
+pub const COAL_ORE: BlockState = BlockState::new(16, 0);
+pub const OAK_LOG: BlockState = BlockState::new(17, 0);
+pub const SPRUCE_LOG: BlockState = BlockState::new(17, 1);
+pub const BIRCH_LOG: BlockState = BlockState::new(17, 2);
+pub const OAK_LEAVES: BlockState = BlockState::new(18, 0);
+pub const SPRUCE_LEAVES: BlockState = BlockState::new(18, 1);
+pub const BIRCH_LEAVES: BlockState = BlockState::new(18, 2);
+pub const GLASS: BlockState = BlockState::new(20, 0);
+pub const LAPIS_ORE: BlockState = BlockState::new(21, 0);
+pub const SANDSTONE: BlockState = BlockState::new(24, 0);
+pub const TALL_GRASS: BlockState = BlockState::new(31, 1);
+pub const FERN: BlockState = BlockState::new(31, 2);
+pub const DEAD_BUSH: BlockState = BlockState::new(32, 0);
+pub const DANDELION: BlockState = BlockState::new(37, 0);
+pub const POPPY: BlockState = BlockState::new(38, 0);
+pub const IRON_BLOCK: BlockState = BlockState::new(42, 0);
+pub const TORCH: BlockState = BlockState::new(50, 0);
+pub const DIAMOND_ORE: BlockState = BlockState::new(56, 0);
+pub const REDSTONE_ORE: BlockState = BlockState::new(73, 0);
+pub const CACTUS: BlockState = BlockState::new(81, 0);
+pub const GLOWSTONE: BlockState = BlockState::new(89, 0);
+
+/// Block faces in the same order as 1.8.9's EnumFacing, so the index matches the protocol's face byte.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Face {
+    Down,
+    Up,
+    North,
+    South,
+    West,
+    East,
+}
+
+impl Face {
+    pub const ALL: [Face; 6] = [Face::Down, Face::Up, Face::North, Face::South, Face::West, Face::East];
+
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    pub fn from_index(index: usize) -> Option<Face> {
+        Face::ALL.get(index).copied()
+    }
+
+    /// Unit step towards the neighbour this face looks at, north is -z and west is -x
+    pub fn offset(self) -> (i32, i32, i32) {
+        match self {
+            Face::Down => (0, -1, 0),
+            Face::Up => (0, 1, 0),
+            Face::North => (0, 0, -1),
+            Face::South => (0, 0, 1),
+            Face::West => (-1, 0, 0),
+            Face::East => (1, 0, 0),
+        }
+    }
+
+    pub fn opposite(self) -> Face {
+        match self {
+            Face::Down => Face::Up,
+            Face::Up => Face::Down,
+            Face::North => Face::South,
+            Face::South => Face::North,
+            Face::West => Face::East,
+            Face::East => Face::West,
+        }
+    }
+}
+
+/// Texture names for each face, indexed by `Face::index`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FaceTextures(pub [&'static str; 6]);
+
+impl FaceTextures {
+    pub fn all(name: &'static str) -> Self {
+        Self([name; 6])
+    }
+
+    /// Logs, grass and the like, different top and bottom with the same texture around the sides
+    pub fn column(top: &'static str, side: &'static str, bottom: &'static str) -> Self {
+        Self([bottom, top, side, side, side, side])
+    }
+
+    pub fn get(&self, face: Face) -> &'static str {
+        self.0[face.index()]
+    }
+}
+
+/// Which biome colour a face gets multiplied by, the textures for these are grey
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Tint {
+    #[default]
+    None,
+    Grass,
+    Foliage,
+}
+
+#[derive(Clone, Debug)]
+pub struct BlockDef {
+    pub name: &'static str,
+    /// Whether entities collide with it
+    pub solid: bool,
+    /// Whether the faces of blocks behind it can be seen
+    pub transparent: bool,
+    /// Water and lava, which can't be targeted or collided with
+    pub liquid: bool,
+    pub light_emission: u8,
+    /// How much light is lost going through it on top of the usual 1 a block, 15 stops it outright
+    pub light_opacity: u8,
+    /// Negative means unbreakable, like bedrock
+    pub hardness: f32,
+    pub textures: FaceTextures,
+    /// Indexed by `Face::index`
+    pub tint: [Tint; 6],
+}
+
+impl BlockDef {
+    pub fn new(name: &'static str, hardness: f32, textures: FaceTextures) -> Self {
+        Self {
+            name,
+            solid: true,
+            transparent: false,
+            liquid: false,
+            light_emission: 0,
+            light_opacity: 15,
+            hardness,
+            textures,
+            tint: [Tint::None; 6],
+        }
+    }
+
+    pub fn transparent(mut self) -> Self {
+        self.transparent = true;
+        self.light_opacity = 0;
+        self
+    }
+
+    /// Plants, liquids and such that you walk through, these are always see-through too
+    pub fn non_solid(mut self) -> Self {
+        self.solid = false;
+        self.transparent()
+    }
+
+    pub fn liquid(mut self) -> Self {
+        self.liquid = true;
+        self.non_solid()
+    }
+
+    pub fn tinted(mut self, tint: Tint) -> Self {
+        self.tint = [tint; 6];
+        self
+    }
+
+    pub fn tinted_top(mut self, tint: Tint) -> Self {
+        self.tint[Face::Up.index()] = tint;
+        self
+    }
+
+    pub fn light(mut self, level: u8) -> Self {
+        self.light_emission = level.min(15);
+        self
+    }
+
+    /// For see-through blocks that still dim light, like leaves and water
+    pub fn opacity(mut self, opacity: u8) -> Self {
+        self.light_opacity = opacity.min(15);
+        self
+    }
+}
+
+/// Maps `id:meta` pairs to their definitions. A meta without its own entry falls back to meta 0,
+/// since most metadata (log axis, crop age...) doesn't change what the block is.
+pub struct BlockRegistry {
+    blocks: HashMap<BlockState, BlockDef>,
+    air: BlockDef,
+}
+
+impl Default for BlockRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BlockRegistry {
+    pub fn new() -> Self {
+        Self {
+            blocks: HashMap::new(),
+            air: BlockDef::new("air", 0.0, FaceTextures::all("")).non_solid(),
+        }
+    }
+
+    pub fn register(&mut self, id: u16, meta: u8, def: BlockDef) {
+        self.blocks.insert(BlockState::new(id, meta), def);
+    }
+
+    pub fn get(&self, state: BlockState) -> Option<&BlockDef> {
+        if state.is_air() {
+            return Some(&self.air);
+        }
+        self.blocks.get(&state).or_else(|| self.blocks.get(&BlockState::new(state.id(), 0)))
+    }
+
+    /// Like `get` but unknown blocks come back as air instead of `None`
+    pub fn def(&self, state: BlockState) -> &BlockDef {
+        self.get(state).unwrap_or(&self.air)
+    }
+
+    pub fn is_solid(&self, state: BlockState) -> bool {
+        self.def(state).solid
+    }
+
+    pub fn is_transparent(&self, state: BlockState) -> bool {
+        self.def(state).transparent
+    }
+
+    /// Unknown blocks stop light, the same as `height_map` in saves treats them
+    pub fn light_opacity(&self, state: BlockState) -> u8 {
+        self.get(state).map_or(15, |def| def.light_opacity)
+    }
+
+    pub fn light_emission(&self, state: BlockState) -> u8 {
+        self.def(state).light_emission
+    }
+
+    /// Whether the crosshair can pick it, anything but air and liquids
+    pub fn is_targetable(&self, state: BlockState) -> bool {
+        !state.is_air() && !self.def(state).liquid
+    }
+
+    pub fn by_name(&self, name: &str) -> Option<BlockState> {
+        if name == "air" {
+            return Some(BlockState::AIR);
+        }
+        self.blocks.iter().filter(|(_, def)| def.name == name).map(|(state, _)| *state).min_by_key(|state| state.0)
+    }
+
+    /// The blocks the game knows about, with 1.8.9's ids and texture names
+    pub fn vanilla() -> Self {
+        let mut registry = Self::new();
+        let r = &mut registry;
+
+        r.register(1, 0, BlockDef::new("stone", 1.5, FaceTextures::all("stone")));
+        r.register(1, 1, BlockDef::new("granite", 1.5, FaceTextures::all("stone_granite")));
+        r.register(1, 3, BlockDef::new("diorite", 1.5, FaceTextures::all("stone_diorite")));
+        r.register(1, 5, BlockDef::new("andesite", 1.5, FaceTextures::all("stone_andesite")));
+        r.register(2, 0, BlockDef::new("grass", 0.6, FaceTextures::column("grass_top", "grass_side", "dirt")).tinted_top(Tint::Grass));
+        r.register(3, 0, BlockDef::new("dirt", 0.5, FaceTextures::all("dirt")));
+        r.register(4, 0, BlockDef::new("cobblestone", 2.0, FaceTextures::all("cobblestone")));
+        r.register(5, 0, BlockDef::new("oak_planks", 2.0, FaceTextures::all("planks_oak")));
+        r.register(5, 1, BlockDef::new("spruce_planks", 2.0, FaceTextures::all("planks_spruce")));
+        r.register(5, 2, BlockDef::new("birch_planks", 2.0, FaceTextures::all("planks_birch")));
+        r.register(7, 0, BlockDef::new("bedrock", -1.0, FaceTextures::all("bedrock")));
+        r.register(8, 0, BlockDef::new("flowing_water", 100.0, FaceTextures::all("water_flow")).liquid().opacity(3));
+        r.register(9, 0, BlockDef::new("water", 100.0, FaceTextures::all("water_still")).liquid().opacity(3));
+        r.register(10, 0, BlockDef::new("flowing_lava", 100.0, FaceTextures::all("lava_flow")).liquid().light(15));
+        r.register(11, 0, BlockDef::new("lava", 100.0, FaceTextures::all("lava_still")).liquid().light(15));
+        r.register(12, 0, BlockDef::new("sand", 0.5, FaceTextures::all("sand")));
+        r.register(12, 1, BlockDef::new("red_sand", 0.5, FaceTextures::all("red_sand")));
+        r.register(13, 0, BlockDef::new("gravel", 0.6, FaceTextures::all("gravel")));
+        r.register(14, 0, BlockDef::new("gold_ore", 3.0, FaceTextures::all("gold_ore")));
+        r.register(15, 0, BlockDef::new("iron_ore", 3.0, FaceTextures::all("iron_ore")));
+        r.register(16, 0, BlockDef::new("coal_ore", 3.0, FaceTextures::all("coal_ore")));
+        r.register(17, 0, BlockDef::new("oak_log", 2.0, FaceTextures::column("log_oak_top", "log_oak", "log_oak_top")));
+        r.register(17, 1, BlockDef::new("spruce_log", 2.0, FaceTextures::column("log_spruce_top", "log_spruce", "log_spruce_top")));
+        r.register(17, 2, BlockDef::new("birch_log", 2.0, FaceTextures::column("log_birch_top", "log_birch", "log_birch_top")));
+        r.register(18, 0, BlockDef::new("oak_leaves", 0.2, FaceTextures::all("leaves_oak")).transparent().opacity(1).tinted(Tint::Foliage));
+        r.register(18, 1, BlockDef::new("spruce_leaves", 0.2, FaceTextures::all("leaves_spruce")).transparent().opacity(1).tinted(Tint::Foliage));
+        r.register(18, 2, BlockDef::new("birch_leaves", 0.2, FaceTextures::all("leaves_birch")).transparent().opacity(1).tinted(Tint::Foliage));
+        r.register(20, 0, BlockDef::new("glass", 0.3, FaceTextures::all("glass")).transparent());
+        r.register(21, 0, BlockDef::new("lapis_ore", 3.0, FaceTextures::all("lapis_ore")));
+        r.register(24, 0, BlockDef::new("sandstone", 0.8, FaceTextures::column("sandstone_top", "sandstone_normal", "sandstone_bottom")));
+        r.register(31, 1, BlockDef::new("tall_grass", 0.0, FaceTextures::all("tallgrass")).non_solid().tinted(Tint::Grass));
+        r.register(31, 2, BlockDef::new("fern", 0.0, FaceTextures::all("fern")).non_solid().tinted(Tint::Grass));
+        r.register(32, 0, BlockDef::new("dead_bush", 0.0, FaceTextures::all("deadbush")).non_solid());
+        r.register(37, 0, BlockDef::new("dandelion", 0.0, FaceTextures::all("flower_dandelion")).non_solid());
+        r.register(38, 0, BlockDef::new("poppy", 0.0, FaceTextures::all("flower_rose")).non_solid());
+        r.register(41, 0, BlockDef::new("gold_block", 3.0, FaceTextures::all("gold_block")));
+        r.register(42, 0, BlockDef::new("iron_block", 5.0, FaceTextures::all("iron_block")));
+        r.register(50, 0, BlockDef::new("torch", 0.0, FaceTextures::all("torch_on")).non_solid().light(14));
+        r.register(56, 0, BlockDef::new("diamond_ore", 3.0, FaceTextures::all("diamond_ore")));
+        r.register(73, 0, BlockDef::new("redstone_ore", 3.0, FaceTextures::all("redstone_ore")));
+        r.register(74, 0, BlockDef::new("lit_redstone_ore", 3.0, FaceTextures::all("redstone_ore")).light(9));
+        r.register(78, 0, BlockDef::new("snow_layer", 0.1, FaceTextures::all("snow")).non_solid());
+        r.register(79, 0, BlockDef::new("ice", 0.5, FaceTextures::all("ice")).transparent().opacity(3));
+        r.register(80, 0, BlockDef::new("snow", 0.2, FaceTextures::all("snow")));
+        r.register(81, 0, BlockDef::new("cactus", 0.4, FaceTextures::column("cactus_top", "cactus_side", "cactus_bottom")).transparent());
+        r.register(82, 0, BlockDef::new("clay", 0.6, FaceTextures::all("clay")));
+        r.register(89, 0, BlockDef::new("glowstone", 0.3, FaceTextures::all("glowstone")).light(15));
+
+        registry
+    }
+}
@@ -19,13 +19,18 @@ impl Camera {
         }
     }
 
-    pub fn view_matrix(&self) -> Matrix4<f32> {
-        // Calculate the camera direction vector based on yaw and pitch
-        let direction = Vector3::new(
+    /// Unit vector the camera is looking along
+    pub fn direction(&self) -> Vector3<f32> {
+        Vector3::new(
             self.yaw.to_radians().cos() * self.pitch.to_radians().cos(),
             self.pitch.to_radians().sin(),
             self.yaw.to_radians().sin() * self.pitch.to_radians().cos(),
-        );
+        )
+    }
+
+    pub fn view_matrix(&self) -> Matrix4<f32> {
+        // Calculate the camera direction vector based on yaw and pitch
+        let direction = self.direction();
 
         // Calculate the right and adjusted up vectors
         let right = self.up.cross(&direction).normalize();
@@ -50,25 +55,4 @@ impl Camera {
             self.pitch = -89.0_f32;
         }
     }
-
-    pub fn process_keyboard(&mut self, direction: Direction, velo: f32, delta_time: f32) {
-        let velocity = velo * delta_time;
-        let direction_vector = Vector3::new(
-            self.yaw.to_radians().cos() * self.pitch.to_radians().cos(),
-            0.0,
-            self.yaw.to_radians().sin() * self.pitch.to_radians().cos(),
-        ).normalize();
-
-        let right_vector = self.up.cross(&direction_vector).normalize();
-
-        match direction {
-            Direction::X => self.position += right_vector * velocity,
-            Direction::Z => self.position -= direction_vector * velocity,
-        } // we need mouse yea
-    }
 }
-
-pub enum Direction {
-    X,
-    Z
-}
\ No newline at end of file
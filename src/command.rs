@@ -0,0 +1,90 @@
+//! Commands typed into the server console, the client's terminal or chat, minus the slash.
+
+use std::io::{self, BufRead};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+
+use crate::game::Game;
+use crate::level::DAY_LENGTH;
+
+const TIME_USAGE: &str = "Usage: time set <day|night|ticks>, time add <ticks> or time query <daytime|gametime|day>";
+
+/// Runs `line` against the game. The message says what it did, or why it couldn't.
+pub fn run(game: &mut Game, line: &str) -> Result<String, String> {
+    let args: Vec<&str> = line.split_whitespace().collect();
+    match args.as_slice() {
+        ["time", args @ ..] => time(game, args),
+        [] => Err("No command given".to_string()),
+        [name, ..] => Err(format!("Unknown command {:?}", name)),
+    }
+}
+
+/// Lines typed into the terminal, for running between ticks. Stdin blocks, so it gets read on
+/// its own thread.
+pub fn stdin_lines() -> Receiver<String> {
+    let (sender, lines) = mpsc::channel();
+    thread::spawn(move || {
+        for line in io::stdin().lock().lines().map_while(Result::ok) {
+            if sender.send(line).is_err() {
+                break;
+            }
+        }
+    });
+    lines
+}
+
+/// Vanilla's `/time`, day and night being the same ticks it uses
+fn time(game: &mut Game, args: &[&str]) -> Result<String, String> {
+    match args {
+        ["set", value] => {
+            game.day_time = match *value {
+                "day" => 1000,
+                "night" => 13000,
+                ticks => parse_ticks(ticks)?,
+            };
+            Ok(format!("Set the time to {}", game.day_time))
+        }
+        ["add", ticks] => {
+            let ticks = parse_ticks(ticks)?;
+            // saves and the protocol keep the time as a long
+            game.day_time = game.day_time.saturating_add(ticks).min(i64::MAX as u64);
+            Ok(format!("Added {} to the time", ticks))
+        }
+        ["query", "daytime"] => Ok(format!("The time is {}", game.day_time % DAY_LENGTH)),
+        ["query", "gametime"] => Ok(format!("The time is {}", game.ticks)),
+        ["query", "day"] => Ok(format!("The time is {}", game.day_time / DAY_LENGTH)),
+        _ => Err(TIME_USAGE.to_string()),
+    }
+}
+
+/// Anything from 0 up to an int like vanilla takes
+fn parse_ticks(value: &str) -> Result<u64, String> {
+    match value.parse::<i32>() {
+        Ok(ticks) if ticks >= 0 => Ok(ticks as u64),
+        Ok(_) => Err(format!("{} is negative, time only goes forward", value)),
+        Err(_) => Err(format!("{:?} isn't a whole number of ticks up to {}", value, i32::MAX)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn time_stays_in_range() {
+        let mut game = Game::new(1);
+        assert!(run(&mut game, "time set 9223372036854775808").is_err());
+        assert!(run(&mut game, "time set 2147483648").is_err());
+        assert!(run(&mut game, "time add -5").is_err());
+        assert_eq!(game.day_time, 0);
+
+        run(&mut game, "time set 2147483647").unwrap();
+        assert_eq!(game.day_time, i32::MAX as u64);
+        run(&mut game, "time add 10").unwrap();
+        assert_eq!(game.day_time, i32::MAX as u64 + 10);
+
+        game.day_time = i64::MAX as u64 - 1;
+        run(&mut game, "time add 2147483647").unwrap();
+        assert_eq!(game.day_time, i64::MAX as u64);
+    }
+}
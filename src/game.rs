@@ -0,0 +1,326 @@
+use std::io;
+
+use nalgebra::Vector3;
+
+use crate::aabb::Aabb;
+use crate::block::BlockRegistry;
+use crate::level::{self, GameRules, Weather, DO_DAYLIGHT_CYCLE};
+use crate::light;
+use crate::player::{Player, PlayerInput};
+use crate::storage::ChunkStorage;
+use crate::world::{BlockPos, BlockState, ChunkPos, World};
+use crate::worldgen::decorator::PendingDecorations;
+use crate::worldgen::random::JavaRandom;
+use crate::worldgen::TerrainGenerator;
+
+/// Vanilla's tick rate, the simulation only ever advances in steps of this
+pub const TICKS_PER_SECOND: u32 = 20;
+/// How many chunks around the origin get generated on startup
+pub const LOAD_RADIUS: i32 = 4;
+/// How far past where they're wanted chunks stay loaded, so going back and forth over a chunk
+/// border doesn't keep unloading and loading them
+pub const UNLOAD_MARGIN: i32 = 2;
+/// Every chunk around another, corners included
+const NEIGHBOURS: [(i32, i32); 8] = [(-1, -1), (0, -1), (1, -1), (-1, 0), (1, 0), (-1, 1), (0, 1), (1, 1)];
+/// Ticks a slow frame is allowed to catch up on, any more and the game just falls behind
+const MAX_TICKS_PER_FRAME: u32 = 10;
+
+/// Everything that gets simulated, with no window or GL anywhere near it.
+pub struct Game {
+    pub registry: BlockRegistry,
+    pub world: World,
+    pub generator: TerrainGenerator,
+    pub decorations: PendingDecorations,
+    /// Where chunks get loaded from instead of generated, and saved to. `None` until the world
+    /// has been saved somewhere.
+    pub storage: Option<ChunkStorage>,
+    pub player: Player,
+    /// Where players start out, feet in the middle of the block above the ground
+    pub spawn: Vector3<f32>,
+    /// Ticks run since the world was created, level.dat's `Time`
+    pub ticks: u64,
+    /// Where the sun is, also in ticks. Only moves while `doDaylightCycle` is on.
+    pub day_time: u64,
+    pub rules: GameRules,
+    pub weather: Weather,
+    random: JavaRandom,
+}
+
+impl Game {
+    /// Generates the chunks around the origin and stands the player on the ground in the middle
+    pub fn new(seed: i64) -> Self {
+        Self::with_storage(seed, None, PendingDecorations::new())
+    }
+
+    /// Carries on with a saved world, the chunks around the origin come out of `storage` where it
+    /// has them and `decorations` are what was still waiting on chunks that weren't generated yet
+    pub fn with_storage(seed: i64, storage: Option<ChunkStorage>, decorations: PendingDecorations) -> Self {
+        let registry = BlockRegistry::vanilla();
+        let generator = TerrainGenerator::new(seed);
+        let spawn = Vector3::new(0.5, generator.height_at(0, 0) as f32 + 1.0, 0.5);
+        let mut game = Self {
+            registry,
+            world: World::new(),
+            generator,
+            decorations,
+            storage,
+            player: Player::new(spawn),
+            spawn,
+            ticks: 0,
+            day_time: 0,
+            rules: GameRules::default(),
+            weather: Weather::default(),
+            random: JavaRandom::new(seed),
+        };
+        game.generate_around(ChunkPos::new(0, 0), LOAD_RADIUS);
+        game
+    }
+
+    /// Loads or generates whatever isn't loaded yet within `radius` chunks of `center`. Returns
+    /// the chunks that were already loaded but got decorations spilled into them.
+    pub fn generate_around(&mut self, center: ChunkPos, radius: i32) -> Vec<ChunkPos> {
+        let mut changed = Vec::new();
+        for x in center.x - radius..=center.x + radius {
+            for z in center.z - radius..=center.z + radius {
+                let pos = ChunkPos::new(x, z);
+                if !self.world.is_loaded(pos) && !self.load_saved(pos) {
+                    // saved neighbours come in first so what spills into them goes straight in,
+                    // rather than waiting in memory for a load that might not come before the next save
+                    for (dx, dz) in NEIGHBOURS {
+                        let neighbour = ChunkPos::new(x + dx, z + dz);
+                        if !self.world.is_loaded(neighbour) {
+                            self.load_saved(neighbour);
+                        }
+                    }
+                    let seed = self.seed();
+                    let spilled = self.decorations.add_chunk(&mut self.world, seed, self.generator.generate_chunk(pos));
+                    light::light_chunk(&mut self.world, &self.registry, pos);
+                    for block in spilled {
+                        light::block_changed(&mut self.world, &self.registry, block);
+                        if !changed.contains(&block.chunk()) {
+                            changed.push(block.chunk());
+                        }
+                    }
+                }
+            }
+        }
+        changed
+    }
+
+    /// Drops the chunks more than `radius` away from every one of `centers`, saving the changed
+    /// ones first. With no storage to save them to, changed chunks stay loaded rather than being lost.
+    pub fn unload_outside(&mut self, centers: &[ChunkPos], radius: i32) -> io::Result<()> {
+        let far: Vec<ChunkPos> = self.world.chunks()
+            .map(|chunk| chunk.pos)
+            .filter(|pos| !centers.iter().any(|center| (pos.x - center.x).abs() <= radius && (pos.z - center.z).abs() <= radius))
+            .collect();
+        for pos in far {
+            let chunk = self.world.chunk_mut(pos).unwrap();
+            if chunk.modified {
+                let Some(storage) = &mut self.storage else {
+                    continue;
+                };
+                storage.save_chunk(chunk, self.ticks)?;
+                chunk.modified = false;
+            }
+            self.world.unload_chunk(pos);
+        }
+        Ok(())
+    }
+
+    /// Puts the chunk in the world if the save has it. One that can't be read counts as never
+    /// saved and gets generated again, like vanilla does.
+    fn load_saved(&mut self, pos: ChunkPos) -> bool {
+        let Some(storage) = &mut self.storage else {
+            return false;
+        };
+        let chunk = match storage.load_chunk(pos) {
+            Ok(Some(chunk)) => chunk,
+            Ok(None) => return false,
+            Err(err) => {
+                eprintln!("Couldn't load chunk {},{}: {}", pos.x, pos.z, err);
+                return false;
+            }
+        };
+        let lit = chunk.light_populated;
+        if self.decorations.add_saved_chunk(&mut self.world, chunk) || !lit {
+            light::light_chunk(&mut self.world, &self.registry, pos);
+        } else {
+            // saves don't have the heights the way we count them
+            light::update_heights(self.world.chunk_mut(pos).unwrap(), &self.registry);
+        }
+        true
+    }
+
+    /// The closest chunk within `radius` of `center` that isn't loaded yet, for filling in the
+    /// world around a player a chunk at a time
+    pub fn nearest_missing_chunk(&self, center: ChunkPos, radius: i32) -> Option<ChunkPos> {
+        (-radius..=radius)
+            .flat_map(|dx| (-radius..=radius).map(move |dz| ChunkPos::new(center.x + dx, center.z + dz)))
+            .filter(|&pos| !self.world.is_loaded(pos))
+            .min_by_key(|pos| (pos.x - center.x).pow(2) + (pos.z - center.z).pow(2))
+    }
+
+    pub fn seed(&self) -> i64 {
+        self.generator.seed
+    }
+
+    pub fn tick(&mut self, input: &PlayerInput) {
+        self.player.tick(&self.world, &self.registry, input);
+        self.tick_world();
+    }
+
+    /// Everything in a tick but the single player, for the server whose players move themselves
+    pub fn tick_world(&mut self) {
+        self.weather.tick(&mut self.random);
+        if self.rules.get_bool(DO_DAYLIGHT_CYCLE) {
+            self.day_time += 1;
+        }
+        self.ticks += 1;
+    }
+
+    /// Where the sun is `partial_tick` into the next tick, see `level::celestial_angle`
+    pub fn celestial_angle(&self, partial_tick: f32) -> f32 {
+        // a stopped clock doesn't creep forward between ticks either
+        let partial_tick = if self.rules.get_bool(DO_DAYLIGHT_CYCLE) { partial_tick } else { 0.0 };
+        level::celestial_angle(self.day_time, partial_tick)
+    }
+
+    /// Returns false for air, unbreakable blocks like bedrock and unloaded chunks
+    pub fn break_block(&mut self, pos: BlockPos) -> bool {
+        let state = self.world.get_block(pos);
+        !state.is_air() && self.registry.def(state).hardness >= 0.0 && self.set_block(pos, BlockState::AIR)
+    }
+
+    /// Only goes into air or liquid, and never where the player is standing
+    pub fn place_block(&mut self, pos: BlockPos, state: BlockState) -> bool {
+        let player = self.player.bounding_box();
+        self.place_block_avoiding(pos, state, &[player])
+    }
+
+    /// `place_block` for when someone else decides who's in the way, like the server's players
+    pub fn place_block_avoiding(&mut self, pos: BlockPos, state: BlockState, entities: &[Aabb]) -> bool {
+        let replaceable = !self.registry.is_targetable(self.world.get_block(pos));
+        let blocked = self.registry.is_solid(state) && entities.iter().any(|entity| Aabb::block(pos).intersects(entity));
+        replaceable && !blocked && self.set_block(pos, state)
+    }
+
+    /// `World::set_block` that keeps the light up to date
+    pub fn set_block(&mut self, pos: BlockPos, state: BlockState) -> bool {
+        let changed = self.world.set_block(pos, state);
+        if changed {
+            light::block_changed(&mut self.world, &self.registry, pos);
+        }
+        changed
+    }
+}
+
+/// Turns frame times into whole ticks, keeping what's left over as the partial tick renderers
+/// interpolate with.
+pub struct TickTimer {
+    tick_length: f32,
+    elapsed: f32,
+}
+
+impl TickTimer {
+    pub fn new(ticks_per_second: u32) -> Self {
+        Self {
+            tick_length: 1.0 / ticks_per_second as f32,
+            elapsed: 0.0,
+        }
+    }
+
+    /// Adds a frame's worth of seconds and returns how many ticks are now due
+    pub fn advance(&mut self, seconds: f32) -> u32 {
+        self.elapsed += seconds;
+        let ticks = (self.elapsed / self.tick_length) as u32;
+        self.elapsed -= ticks as f32 * self.tick_length;
+        ticks.min(MAX_TICKS_PER_FRAME)
+    }
+
+    /// How far into the next tick we are, 0..1
+    pub fn partial_tick(&self) -> f32 {
+        (self.elapsed / self.tick_length).clamp(0.0, 1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::GLASS;
+
+    #[test]
+    fn timer_turns_frames_into_whole_ticks() {
+        // 16 a second so the tick length and the frames below add up exactly in binary
+        let mut timer = TickTimer::new(16);
+        assert_eq!(timer.advance(0.0625), 1);
+        assert_eq!(timer.partial_tick(), 0.0);
+
+        assert_eq!(timer.advance(0.03125), 0);
+        assert_eq!(timer.partial_tick(), 0.5);
+        assert_eq!(timer.advance(0.03125), 1);
+
+        // two and a half ticks, the half carries over
+        assert_eq!(timer.advance(0.15625), 2);
+        assert_eq!(timer.partial_tick(), 0.5);
+        assert_eq!(timer.advance(0.03125), 1);
+        assert_eq!(timer.partial_tick(), 0.0);
+    }
+
+    #[test]
+    fn timer_gives_up_on_long_frames() {
+        let mut timer = TickTimer::new(TICKS_PER_SECOND);
+        assert_eq!(timer.advance(5.0), MAX_TICKS_PER_FRAME);
+        // the rest of the frame is dropped rather than caught up on afterwards
+        assert_eq!(timer.advance(0.0), 0);
+    }
+
+    #[test]
+    fn far_chunks_unload_and_come_back_as_they_were() {
+        let far = BlockPos::new(200, 100, 0);
+        let mut game = Game::new(3);
+        game.generate_around(far.chunk(), 0);
+        game.set_block(far, GLASS);
+        // nowhere to save it yet, so it stays
+        game.unload_outside(&[ChunkPos::new(0, 0)], LOAD_RADIUS).unwrap();
+        assert!(game.world.is_loaded(far.chunk()));
+
+        let dir = std::env::temp_dir().join(format!("nanocraft-unload-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        game.storage = Some(ChunkStorage::new(&dir));
+        game.unload_outside(&[ChunkPos::new(0, 0)], LOAD_RADIUS).unwrap();
+        assert!(!game.world.is_loaded(far.chunk()));
+        assert_eq!(game.world.chunks().count(), (2 * LOAD_RADIUS as usize + 1).pow(2));
+
+        game.generate_around(far.chunk(), 0);
+        assert_eq!(game.world.get_block(far), GLASS);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn ticks_move_the_clock_and_the_player() {
+        let mut game = Game::new(1);
+        let start = game.spawn + Vector3::new(0.0, 5.0, 0.0);
+        game.player.position = start;
+        let mut timer = TickTimer::new(TICKS_PER_SECOND);
+        let mut ticks = 0;
+        // three seconds of 40 ms frames
+        for _ in 0..75 {
+            for _ in 0..timer.advance(0.04) {
+                game.tick(&PlayerInput::default());
+                ticks += 1;
+            }
+        }
+        assert!((59..=60).contains(&ticks));
+        assert_eq!((game.ticks, game.day_time), (ticks, ticks));
+        // dropped from above spawn and landed, on whatever grew there
+        assert!(game.player.on_ground);
+        assert_eq!((game.player.position.x, game.player.position.z), (start.x, start.z));
+        assert!(game.player.position.y < start.y);
+
+        game.rules.set(DO_DAYLIGHT_CYCLE, "false");
+        game.tick(&PlayerInput::default());
+        assert_eq!((game.ticks, game.day_time), (ticks + 1, ticks));
+        assert_eq!(game.celestial_angle(0.9), game.celestial_angle(0.0));
+    }
+}
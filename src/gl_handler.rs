@@ -1,4 +1,4 @@
-use glfw::Window;
+use glfw::{Glfw, Window, WindowMode};
 
 pub unsafe fn check_errors(check_point: &str) {
     let error = gl::GetError();
@@ -19,6 +19,63 @@ pub unsafe fn check_errors(check_point: &str) {
     }
 }
 
-pub fn framebuffer_size_callback(window: &mut Window, width: i32, height: i32) {
-    unsafe { gl::Viewport(0, 0, width, height) }
+/// Switches the window between windowed and fullscreen, putting it back exactly where it was
+#[derive(Default)]
+pub struct Fullscreen {
+    /// The window's position and size from before it went fullscreen, in screen coordinates
+    windowed: Option<(i32, i32, i32, i32)>,
+}
+
+impl Fullscreen {
+    pub fn toggle(&mut self, glfw: &mut Glfw, window: &mut Window) {
+        if self.windowed.is_some() {
+            self.restore(glfw, window);
+            return;
+        }
+
+        let (x, y) = window.get_pos();
+        let (width, height) = window.get_size();
+        let (center_x, center_y) = (x + width / 2, y + height / 2);
+        let entered = glfw.with_connected_monitors(|_, monitors| {
+            // whichever monitor the window is on, not always the primary one
+            let monitor = monitors.iter()
+                .find(|monitor| contains(monitor.get_workarea(), center_x, center_y))
+                .or(monitors.first())?;
+            let mode = monitor.get_video_mode()?;
+            window.set_monitor(WindowMode::FullScreen(monitor), 0, 0, mode.width, mode.height, Some(mode.refresh_rate));
+            Some(())
+        });
+        if entered.is_some() {
+            self.windowed = Some((x, y, width, height));
+        }
+    }
+
+    /// GLFW takes the window out of fullscreen by itself when its monitor is unplugged, this
+    /// notices and puts the window back where it was
+    pub fn update(&mut self, glfw: &mut Glfw, window: &mut Window) {
+        if self.windowed.is_some() && window.with_window_mode(|mode| matches!(mode, WindowMode::Windowed)) {
+            self.restore(glfw, window);
+        }
+    }
+
+    fn restore(&mut self, glfw: &mut Glfw, window: &mut Window) {
+        let Some((x, y, width, height)) = self.windowed.take() else {
+            return;
+        };
+        // if the monitor it was on has gone since, it goes in the middle of the primary one instead
+        let (x, y) = glfw.with_connected_monitors(|_, monitors| {
+            match monitors.first() {
+                Some(primary) if !monitors.iter().any(|monitor| contains(monitor.get_workarea(), x + width / 2, y + height / 2)) => {
+                    let (area_x, area_y, area_width, area_height) = primary.get_workarea();
+                    (area_x + ((area_width - width) / 2).max(0), area_y + ((area_height - height) / 2).max(0))
+                }
+                _ => (x, y),
+            }
+        });
+        window.set_monitor(WindowMode::Windowed, x, y, width as u32, height as u32, None);
+    }
+}
+
+fn contains((x, y, width, height): (i32, i32, i32, i32), point_x: i32, point_y: i32) -> bool {
+    (x..x + width).contains(&point_x) && (y..y + height).contains(&point_y)
 }
\ No newline at end of file
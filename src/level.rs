@@ -0,0 +1,134 @@
+//! World state that isn't blocks: game rules, the weather and the time of day, kept the way
+//! level.dat stores them.
+
+use std::collections::BTreeMap;
+use std::f32::consts::PI;
+
+use crate::worldgen::random::JavaRandom;
+
+pub const DO_DAYLIGHT_CYCLE: &str = "doDaylightCycle";
+/// Ticks from one sunrise to the next, 20 minutes
+pub const DAY_LENGTH: u64 = 24000;
+/// The moon goes from full to new and back over this many days
+pub const MOON_PHASES: u64 = 8;
+
+/// 1.8.9's rules and their defaults. They're all strings in level.dat, even the numbers.
+const DEFAULT_RULES: [(&str, &str); 15] = [
+    ("commandBlockOutput", "true"),
+    (DO_DAYLIGHT_CYCLE, "true"),
+    ("doEntityDrops", "true"),
+    ("doFireTick", "true"),
+    ("doMobLoot", "true"),
+    ("doMobSpawning", "true"),
+    ("doTileDrops", "true"),
+    ("keepInventory", "false"),
+    ("logAdminCommands", "true"),
+    ("mobGriefing", "true"),
+    ("naturalRegeneration", "true"),
+    ("randomTickSpeed", "3"),
+    ("reducedDebugInfo", "false"),
+    ("sendCommandFeedback", "true"),
+    ("showDeathMessages", "true"),
+];
+
+/// Named string values like `/gamerule` sets. Rules we don't use are still kept so they survive
+/// a load and save.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GameRules(BTreeMap<String, String>);
+
+impl GameRules {
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.0.get(name).map(String::as_str)
+    }
+
+    /// Anything but `true` is off, vanilla reads them the same way
+    pub fn get_bool(&self, name: &str) -> bool {
+        self.get(name) == Some("true")
+    }
+
+    pub fn set(&mut self, name: &str, value: &str) {
+        self.0.insert(name.to_string(), value.to_string());
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.0.iter().map(|(name, value)| (name.as_str(), value.as_str()))
+    }
+}
+
+impl Default for GameRules {
+    fn default() -> Self {
+        GameRules(DEFAULT_RULES.iter().map(|&(name, value)| (name.to_string(), value.to_string())).collect())
+    }
+}
+
+/// Rain and thunder each flip when their timer runs out, then wait a random while to flip back.
+/// `/weather clear` sets `clear_weather_time`, which holds both off until it runs out.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Weather {
+    pub raining: bool,
+    /// Ticks until `raining` flips
+    pub rain_time: i32,
+    pub thundering: bool,
+    pub thunder_time: i32,
+    pub clear_weather_time: i32,
+}
+
+impl Weather {
+    /// Same timings as vanilla's `WorldServer.updateWeather`
+    pub fn tick(&mut self, random: &mut JavaRandom) {
+        if self.clear_weather_time > 0 {
+            self.clear_weather_time -= 1;
+            self.thunder_time = if self.thundering { 0 } else { 1 };
+            self.rain_time = if self.raining { 0 } else { 1 };
+            self.thundering = false;
+            self.raining = false;
+            return;
+        }
+
+        if self.thunder_time <= 0 {
+            self.thunder_time = if self.thundering { random.next_int_bounded(12000) + 3600 } else { random.next_int_bounded(168000) + 12000 };
+        } else {
+            self.thunder_time -= 1;
+            if self.thunder_time <= 0 {
+                self.thundering = !self.thundering;
+            }
+        }
+
+        if self.rain_time <= 0 {
+            self.rain_time = if self.raining { random.next_int_bounded(12000) + 12000 } else { random.next_int_bounded(168000) + 12000 };
+        } else {
+            self.rain_time -= 1;
+            if self.rain_time <= 0 {
+                self.raining = !self.raining;
+            }
+        }
+    }
+}
+
+/// Where the sun is as a fraction of a turn, 0 at noon and 0.5 at midnight. Like vanilla it's
+/// eased so days and nights last a bit longer than sunrise and sunset. Day time 0 is sunrise.
+pub fn celestial_angle(day_time: u64, partial_tick: f32) -> f32 {
+    let linear = ((day_time % DAY_LENGTH) as f32 + partial_tick) / DAY_LENGTH as f32 - 0.25;
+    let linear = linear.rem_euclid(1.0);
+    let eased = 1.0 - ((linear * PI).cos() + 1.0) / 2.0;
+    linear + (eased - linear) / 3.0
+}
+
+/// 0 is full, 4 is new
+pub fn moon_phase(day_time: u64) -> u32 {
+    (day_time / DAY_LENGTH % MOON_PHASES) as u32
+}
+
+/// How much of the sun's light reaches the ground, 0.2 at night up to 1 at noon, dimmer in rain
+/// and thunder. Sky light gets multiplied by this.
+pub fn sun_brightness(celestial_angle: f32, weather: &Weather) -> f32 {
+    let darkness = (1.0 - ((celestial_angle * PI * 2.0).cos() * 2.0 + 0.2)).clamp(0.0, 1.0);
+    let mut brightness = 1.0 - darkness;
+    if weather.raining {
+        brightness *= 1.0 - 5.0 / 16.0;
+    }
+    if weather.thundering {
+        brightness *= 1.0 - 5.0 / 16.0;
+    }
+    brightness * 0.8 + 0.2
+}
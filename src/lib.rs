@@ -0,0 +1,26 @@
+//! Everything that runs without a window: the world, generation and the simulation. The client
+//! and the server binaries are both built on top of this.
+
+pub mod aabb;
+pub mod atlas;
+pub mod block;
+pub mod command;
+pub mod cube;
+pub mod game;
+pub mod level;
+pub mod light;
+pub mod mesh;
+pub mod nbt;
+pub mod net;
+pub mod player;
+pub mod raycast;
+pub mod storage;
+pub mod world;
+pub mod worldgen;
+
+use std::io;
+
+/// The error for anything read from a file or the network that doesn't make sense
+pub(crate) fn invalid(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
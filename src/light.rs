@@ -0,0 +1,242 @@
+//! Sky light and block light, worked out with breadth first flood fills the way vanilla does it.
+//!
+//! Sky light is 15 from the top of the world down to each column's height, the first block that
+//! dims light going through it. Below that, and for block light starting from whatever emits it,
+//! light spreads to the six neighbours losing 1 per block plus the opacity of the block it goes
+//! into. Taking light away is a flood fill too: everything that was lit by what's gone gets
+//! cleared, then light spreads back in from the edges of the cleared area.
+
+use std::collections::VecDeque;
+
+use crate::block::BlockRegistry;
+use crate::world::{BlockPos, Chunk, ChunkPos, LightType, SectionLight, World, CHUNK_HEIGHT, CHUNK_WIDTH, SECTION_COUNT, SECTION_HEIGHT};
+
+const NEIGHBOURS: [(i32, i32, i32); 6] = [(1, 0, 0), (-1, 0, 0), (0, 1, 0), (0, -1, 0), (0, 0, 1), (0, 0, -1)];
+/// The chunks sharing a side with another
+const SIDES: [(i32, i32); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+
+/// Lights a chunk that was just put in the world, spreading light both ways between it and
+/// the loaded chunks around it. Anything it had before is thrown away.
+pub fn light_chunk(world: &mut World, registry: &BlockRegistry, pos: ChunkPos) {
+    let Some(chunk) = world.chunk_mut(pos) else {
+        return;
+    };
+    update_heights(chunk, registry);
+
+    let lowest = (0..CHUNK_WIDTH * CHUNK_WIDTH).map(|i| chunk.height(i & 15, i >> 4)).min().unwrap();
+    let highest = (0..CHUNK_WIDTH * CHUNK_WIDTH).map(|i| chunk.height(i & 15, i >> 4)).max().unwrap();
+    for section_y in 0..SECTION_COUNT {
+        let bottom = section_y * SECTION_HEIGHT;
+        *chunk.section_light_mut(LightType::Block, section_y) = SectionLight::Uniform(0);
+        *chunk.section_light_mut(LightType::Sky, section_y) = if bottom >= highest {
+            SectionLight::Uniform(15)
+        } else {
+            SectionLight::Uniform(0)
+        };
+        if bottom < highest && bottom + SECTION_HEIGHT > lowest {
+            for z in 0..CHUNK_WIDTH {
+                for x in 0..CHUNK_WIDTH {
+                    for y in chunk.height(x, z).max(bottom)..bottom + SECTION_HEIGHT {
+                        chunk.set_light(LightType::Sky, x, y, z, 15);
+                    }
+                }
+            }
+        }
+    }
+
+    let origin = pos.origin();
+    let mut block = VecDeque::new();
+    let mut emitters = Vec::new();
+    for (section_y, section) in chunk.sections() {
+        for (i, &state) in section.blocks().iter().enumerate() {
+            let emission = registry.light_emission(state);
+            if emission > 0 {
+                emitters.push((i & 15, section_y * SECTION_HEIGHT + (i >> 8), i >> 4 & 15, emission));
+            }
+        }
+    }
+    for (x, y, z, emission) in emitters {
+        chunk.set_light(LightType::Block, x, y, z, emission);
+        block.push_back(BlockPos::new(origin.x + x as i32, y as i32, origin.z + z as i32));
+    }
+    chunk.light_populated = true;
+
+    // open sky next to a lower column spreads sideways, and the top of each column down into it
+    let mut sky = VecDeque::new();
+    for z in 0..CHUNK_WIDTH as i32 {
+        for x in 0..CHUNK_WIDTH as i32 {
+            let column = BlockPos::new(origin.x + x, 0, origin.z + z);
+            let height = column_height_at(world, column).unwrap();
+            let tallest_neighbour = SIDES.iter()
+                .filter_map(|&(dx, dz)| column_height_at(world, BlockPos::new(column.x + dx, 0, column.z + dz)))
+                .max()
+                .unwrap_or(0);
+            for y in height..tallest_neighbour.max(height + 1).min(CHUNK_HEIGHT) {
+                sky.push_back(BlockPos::new(column.x, y as i32, column.z));
+            }
+        }
+    }
+
+    // and whatever light is already along the edges of the neighbours spreads into this one
+    for (dx, dz) in SIDES {
+        if !world.is_loaded(ChunkPos::new(pos.x + dx, pos.z + dz)) {
+            continue;
+        }
+        for i in 0..CHUNK_WIDTH as i32 {
+            let (x, z) = match (dx, dz) {
+                (1, _) => (CHUNK_WIDTH as i32, i),
+                (-1, _) => (-1, i),
+                (_, 1) => (i, CHUNK_WIDTH as i32),
+                _ => (i, -1),
+            };
+            let inside = BlockPos::new(origin.x + x - dx, 0, origin.z + z - dz);
+            let height = column_height_at(world, inside).unwrap();
+            for y in 0..CHUNK_HEIGHT as i32 {
+                let outside = BlockPos::new(origin.x + x, y, origin.z + z);
+                // the inside is at full sky light from its height up, nothing to add there
+                if (y as usize) < height && world.get_light(LightType::Sky, outside) > 1 {
+                    sky.push_back(outside);
+                }
+                if world.get_light(LightType::Block, outside) > 1 {
+                    block.push_back(outside);
+                }
+            }
+        }
+    }
+
+    spread(world, registry, LightType::Sky, sky);
+    spread(world, registry, LightType::Block, block);
+}
+
+/// Fixes up the light around `pos` after the block there was placed or broken
+pub fn block_changed(world: &mut World, registry: &BlockRegistry, pos: BlockPos) {
+    if pos.y < 0 || pos.y >= CHUNK_HEIGHT as i32 {
+        return;
+    }
+    let Some(chunk) = world.chunk_mut(pos.chunk()) else {
+        return;
+    };
+    let (x, _, z) = pos.local();
+    let old_height = chunk.height(x, z);
+    let new_height = column_height(chunk, registry, x, z);
+    chunk.set_height(x, z, new_height);
+
+    for light in LightType::ALL {
+        let mut changed = vec![pos];
+        if light == LightType::Sky {
+            // the part of the column that just got or lost a clear view of the sky
+            changed.extend((old_height.min(new_height)..old_height.max(new_height))
+                .filter(|&y| y as i32 != pos.y)
+                .map(|y| BlockPos::new(pos.x, y as i32, pos.z)));
+        }
+
+        let mut removed = VecDeque::new();
+        let mut relight = VecDeque::new();
+        for cell in changed {
+            let old = world.get_light(light, cell);
+            let own = source_level(world, registry, light, cell);
+            world.set_light(light, cell, own);
+            if old > own {
+                removed.push_back((cell, old));
+            }
+            if own > 0 {
+                relight.push_back(cell);
+            }
+        }
+        relight.extend(unspread(world, registry, light, removed));
+        // light from around flows into wherever the block let it through
+        relight.extend(neighbours(world, pos).filter(|&neighbour| world.get_light(light, neighbour) > 1));
+        spread(world, registry, light, relight);
+    }
+}
+
+/// Sets every column's height from its blocks
+pub fn update_heights(chunk: &mut Chunk, registry: &BlockRegistry) {
+    for z in 0..CHUNK_WIDTH {
+        for x in 0..CHUNK_WIDTH {
+            let height = column_height(chunk, registry, x, z);
+            chunk.set_height(x, z, height);
+        }
+    }
+}
+
+/// The y above the highest block in the column with any opacity
+fn column_height(chunk: &Chunk, registry: &BlockRegistry, x: usize, z: usize) -> usize {
+    let top = chunk.sections().last().map_or(0, |(section_y, _)| (section_y + 1) * SECTION_HEIGHT);
+    (0..top).rev()
+        .find(|&y| registry.light_opacity(chunk.get_block(x, y, z)) > 0)
+        .map_or(0, |y| y + 1)
+}
+
+/// `None` in unloaded chunks
+fn column_height_at(world: &World, pos: BlockPos) -> Option<usize> {
+    let (x, _, z) = pos.local();
+    world.chunk(pos.chunk()).map(|chunk| chunk.height(x, z))
+}
+
+/// The light a block has no matter what's around it: full sky light above its column's height,
+/// or what it gives off itself
+fn source_level(world: &World, registry: &BlockRegistry, light: LightType, pos: BlockPos) -> u8 {
+    match light {
+        LightType::Sky => match column_height_at(world, pos) {
+            Some(height) if pos.y as usize >= height => 15,
+            _ => 0,
+        },
+        LightType::Block => registry.light_emission(world.get_block(pos)),
+    }
+}
+
+/// The neighbours of `pos` that are inside the world and in loaded chunks
+fn neighbours(world: &World, pos: BlockPos) -> impl Iterator<Item = BlockPos> + '_ {
+    NEIGHBOURS.iter()
+        .map(move |&(dx, dy, dz)| BlockPos::new(pos.x + dx, pos.y + dy, pos.z + dz))
+        .filter(|neighbour| (0..CHUNK_HEIGHT as i32).contains(&neighbour.y) && world.is_loaded(neighbour.chunk()))
+}
+
+/// Spreads light out from every block in `queue` for as far as it reaches
+fn spread(world: &mut World, registry: &BlockRegistry, light: LightType, mut queue: VecDeque<BlockPos>) {
+    let mut next = Vec::with_capacity(NEIGHBOURS.len());
+    while let Some(pos) = queue.pop_front() {
+        let level = world.get_light(light, pos);
+        if level <= 1 {
+            continue;
+        }
+        next.extend(neighbours(world, pos));
+        for neighbour in next.drain(..) {
+            let reached = level.saturating_sub(registry.light_opacity(world.get_block(neighbour)).max(1));
+            if reached > world.get_light(light, neighbour) {
+                world.set_light(light, neighbour, reached);
+                queue.push_back(neighbour);
+            }
+        }
+    }
+}
+
+/// Takes away the light each block in `queue` had, along with everything that could have been lit
+/// by it. Returns the blocks around the cleared area whose light should spread back into it.
+fn unspread(world: &mut World, registry: &BlockRegistry, light: LightType, mut queue: VecDeque<(BlockPos, u8)>) -> VecDeque<BlockPos> {
+    let mut relight = VecDeque::new();
+    let mut next = Vec::with_capacity(NEIGHBOURS.len());
+    while let Some((pos, level)) = queue.pop_front() {
+        next.extend(neighbours(world, pos));
+        for neighbour in next.drain(..) {
+            let neighbour_level = world.get_light(light, neighbour);
+            if neighbour_level == 0 {
+                continue;
+            }
+            if neighbour_level < level {
+                // might have been lit from here, start it over from its own light
+                let own = source_level(world, registry, light, neighbour);
+                world.set_light(light, neighbour, own);
+                queue.push_back((neighbour, neighbour_level));
+                if own > 0 {
+                    relight.push_back(neighbour);
+                }
+            } else {
+                // lit from somewhere else, so it lights the cleared area back up
+                relight.push_back(neighbour);
+            }
+        }
+    }
+    relight
+}
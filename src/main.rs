@@ -4,34 +4,62 @@ extern crate glfw;
 extern crate image;
 extern crate include_dir;
 
+use std::env;
 use std::ffi::CString;
 use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use std::process::exit;
 use std::ptr;
 use std::str;
-use std::time::Instant;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
 
 use gl::types::*;
-use glfw::{Action, Context, Key, WindowMode};
+use glfw::{Action, Context, Key};
 use glfw::WindowEvent::MouseButton;
 use image::GenericImageView;
 use include_dir::{Dir, include_dir};
-use nalgebra::{Matrix4, Perspective3, Translation3, Vector3};
 
-use crate::cube::VERTICES;
-use crate::gl_handler::{check_errors, framebuffer_size_callback};
+use nanocraft::atlas::{AtlasBuilder, TextureAtlas};
+use nanocraft::block::{COBBLESTONE, DIRT, GLASS, GRASS, IRON_BLOCK, OAK_LOG, OAK_PLANKS, SAND, STONE};
+use nanocraft::command;
+use nanocraft::game::{Game, TickTimer, TICKS_PER_SECOND, UNLOAD_MARGIN};
+use nanocraft::level::{moon_phase, sun_brightness};
+use nanocraft::mesh::MeshMode;
+use nanocraft::net::parse_address;
+use nanocraft::net::status::{ping, ping_legacy};
+use nanocraft::player::PlayerInput;
+use nanocraft::raycast::{raycast, REACH};
+use nanocraft::storage::{load_game, save_game, ChunkStorage};
+use nanocraft::world::BlockPos;
+use nanocraft::worldgen::decorator::PendingDecorations;
+
+use crate::gl_handler::{check_errors, Fullscreen};
+use crate::renderer::{ChunkRenderer, SelectionOutline};
+use crate::sky::{Fog, SkyColors, SkyRenderer};
 
 // use ogl33::{GL_COLOR_BUFFER_BIT, GL_DEPTH_BUFFER_BIT, glClear, glVertex3f}; a
 
-mod gl_handler;
 mod camera;
-mod cube;
+mod gl_handler;
+mod renderer;
+mod sky;
 
 const WINDOW_TITLE: &str = "Nanocraft";
+const DEFAULT_WORLD_DIR: &str = "world";
+/// Degrees the FOV changes by a key press
+const FOV_STEP: f32 = 5.0;
 static RESOURCES_DIR: Dir = include_dir!("resources");
 
 // https://github.com/rust-tutorials/learn-opengl/blob/main/examples/000-basic-window.rs
 
 fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("ping") {
+        ping_command(&args[1..]);
+        return;
+    }
+    let world_dir = PathBuf::from(args.first().map(String::as_str).unwrap_or(DEFAULT_WORLD_DIR));
+
     use glfw::fail_on_errors;
     let mut glfw = glfw::init(fail_on_errors!()).unwrap();
 
@@ -50,6 +78,7 @@ fn main() {
     window.set_mouse_button_polling(true);
     window.set_cursor_pos_polling(true);
     window.set_key_polling(true);
+    window.set_framebuffer_size_polling(true);
     window.set_cursor_mode(glfw::CursorMode::Disabled);
     set_window_icon(&mut window, "icon.png");
     glfw.set_swap_interval(glfw::SwapInterval::None);
@@ -63,55 +92,8 @@ fn main() {
     let fragment_shader = compile_shader(FRAGMENT_SHADER_SOURCE, gl::FRAGMENT_SHADER);
     let shader_program = link_program(vertex_shader, fragment_shader);
 
-    let mut camera = camera::Camera::new(Vector3::new(0.0, 0.0, 0.0), 0.0, 0.0, 1.0);
-
-    // Set up vertex buffer and array objects
-    let (mut vao, mut vbo_vertices, mut vbo_colors) = (0, 0, 0);
 
     unsafe {
-        gl::GenVertexArrays(1, &mut vao);
-        gl::GenBuffers(1, &mut vbo_vertices);
-
-        // Bind VAO
-        gl::BindVertexArray(vao);
-
-        // Bind vertex buffer
-        gl::BindBuffer(gl::ARRAY_BUFFER, vbo_vertices);
-        gl::BufferData(
-            gl::ARRAY_BUFFER,
-            (VERTICES.len() * std::mem::size_of::<f32>()) as isize,
-            VERTICES.as_ptr() as *const _,
-            gl::STATIC_DRAW,
-        );
-
-        // Vertex attribute for positions
-        gl::VertexAttribPointer(
-            0,
-            3,
-            gl::FLOAT,
-            gl::FALSE,
-            5 * std::mem::size_of::<f32>() as i32,
-            std::ptr::null(),
-        );
-        gl::EnableVertexAttribArray(0);
-
-        // Vertex attribute for texture coordinates
-        gl::VertexAttribPointer(
-            1,
-            2,
-            gl::FLOAT,
-            gl::FALSE,
-            5 * std::mem::size_of::<f32>() as i32,
-            (3 * std::mem::size_of::<f32>()) as *const _,
-        );
-        gl::EnableVertexAttribArray(1);
-
-        // Unbind VAO
-        gl::BindVertexArray(0);
-    }
-
-    unsafe {
-        window.set_framebuffer_size_callback(framebuffer_size_callback);
         // Enable depth testing
         gl::Enable(gl::DEPTH_TEST);
         gl::DepthFunc(gl::LEQUAL);
@@ -119,60 +101,46 @@ fn main() {
 
 
 
-    let iron_block = load_texture("iron_block.png");
-    let dirt_block = load_texture("dirt.png");
-    let icon_block = load_texture("icon.png");
+    let atlas = AtlasBuilder::from_dir(&RESOURCES_DIR).build();
+    let atlas_texture = load_atlas(&atlas);
 
 
 
     let mut x: i8 = 0;
-    let mut y: i8 = 0;
     let mut z: i8 = 0;
-    let mut fly_speed: f32 = 1.0;
+    let mut input = PlayerInput::default();
 
     let mut last_x = 400.0;
     let mut last_y = 300.0;
     let mut first_mouse = true;
 
-    let mut is_fullscreen = false; // Track the fullscreen state
-    let mut previousX = 0;
-    let mut previousY = 0;
-    let mut previousW = 0;
-    let mut previousH = 0;
+    let mut fullscreen = Fullscreen::default();
 
     let mut last_update = Instant::now();
     let mut last_frame = Instant::now();
     let mut frames = 0;
 
-    struct Cube {
-        position: Vector3<f32>,
-        color: Vector3<f32>,
-        texture: u32,
-    }
+    let mut game = load_or_create(&world_dir);
+    let mut timer = TickTimer::new(TICKS_PER_SECOND);
+    let mut camera = camera::Camera::new(game.player.eye_position(1.0), game.player.yaw, game.player.pitch, 1.0);
 
-    let cubes = vec![
-        Cube {
-            position: Vector3::new(1.5, 0.0, -7.0),
-            color: Vector3::new(1.0, 0.0, 0.0),
-            texture: iron_block,
-        },
-        Cube {
-            position: Vector3::new(8.0, 0.0, -7.0),
-            color: Vector3::new(0.0, 1.0, 0.0),
-            texture: dirt_block,
-        },
-        Cube {
-            position: Vector3::new(8.0, 10.0, -7.0),
-            color: Vector3::new(0.0, 1.0, 1.0),
-            texture: iron_block,
-        },
-        Cube {
-            position: Vector3::new(-5.0, 0.0, 7.0),
-            color: Vector3::new(0.5, 0.5, 0.0),
-            texture: icon_block,
-        },
-        // Add more cubes as needed
-    ];
+    let mut chunk_renderer = ChunkRenderer::new();
+    let (width, height) = window.get_framebuffer_size();
+    chunk_renderer.projection.resize(width, height);
+    chunk_renderer.mesh_mode = MeshMode::Greedy;
+    // nothing's meshed yet, the light it has now is what it'll be meshed with
+    game.world.track_light_changes = true;
+
+    let outline_program = link_program(
+        compile_shader(OUTLINE_VERTEX_SHADER_SOURCE, gl::VERTEX_SHADER),
+        compile_shader(OUTLINE_FRAGMENT_SHADER_SOURCE, gl::FRAGMENT_SHADER),
+    );
+    let selection_outline = SelectionOutline::new();
+    let sky_renderer = SkyRenderer::new();
+    // there's no chat box to type commands into yet
+    let commands = command::stdin_lines();
+    let hotbar = [STONE, COBBLESTONE, DIRT, GRASS, OAK_PLANKS, OAK_LOG, GLASS, SAND, IRON_BLOCK];
+    let mut held_block = hotbar[0];
 
 
 
@@ -192,19 +160,51 @@ fn main() {
 
         let delta = duration_since;
 
-        camera.process_keyboard(camera::Direction::X, x as f32 * fly_speed, delta); // works
-        camera.process_keyboard(camera::Direction::Z, z as f32 * fly_speed, delta);
-        camera.position.y += (y as f32 * fly_speed) * delta;
-
-
+        input.forward = -z as f32;
+        input.strafe = x as f32;
+        game.player.yaw = camera.yaw;
+        game.player.pitch = camera.pitch;
+        for _ in 0..timer.advance(delta) {
+            game.tick(&input);
+            input.jump_pressed = false;
+        }
+        camera.position = game.player.eye_position(timer.partial_tick());
 
-        unsafe {
-            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+        while let Ok(line) = commands.try_recv() {
+            match command::run(&mut game, line.trim().trim_start_matches('/')) {
+                Ok(message) => println!("{}", message),
+                Err(message) => eprintln!("{}", message),
+            }
         }
 
+        // fill in the world around the player a chunk a frame, closest first
+        let eye = BlockPos::new(camera.position.x.floor() as i32, camera.position.y.floor() as i32, camera.position.z.floor() as i32);
+        if let Some(pos) = game.nearest_missing_chunk(eye.chunk(), chunk_renderer.render_distance()) {
+            let spilled = game.generate_around(pos, 0);
+            chunk_renderer.chunks_changed(&spilled);
+        }
+        if let Err(err) = game.unload_outside(&[eye.chunk()], chunk_renderer.render_distance() + UNLOAD_MARGIN) {
+            eprintln!("Couldn't save chunks to {}: {}", world_dir.display(), err);
+        }
+        chunk_renderer.light_changed(&mut game.world, &game.registry, &atlas);
+        chunk_renderer.update(&game.world, &game.registry, &atlas, eye.chunk());
 
         // Calculate the view matrix using look_at_rh
         let view = camera.view_matrix();
+        let selection = raycast(&game.world, &game.registry, camera.position, camera.direction(), REACH);
+        chunk_renderer.projection.update(game.player.fov_multiplier(), delta);
+        let projection = chunk_renderer.projection_matrix();
+        let celestial_angle = game.celestial_angle(timer.partial_tick());
+        let sky_colors = SkyColors::new(celestial_angle, &game.weather, chunk_renderer.render_distance());
+        let fog = Fog::new(&sky_colors, chunk_renderer.view_distance(), game.world.get_block(eye));
+
+        unsafe {
+            gl::ClearColor(fog.color.x, fog.color.y, fog.color.z, 1.0);
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+            if !fog.hides_sky {
+                sky_renderer.draw(&view, &projection, &sky_colors, celestial_angle, moon_phase(game.day_time));
+            }
+        }
 
         // Use shader program
         unsafe {
@@ -214,33 +214,29 @@ fn main() {
             let view_location = gl::GetUniformLocation(shader_program, CString::new("view").unwrap().as_ptr());
             gl::UniformMatrix4fv(view_location, 1, gl::FALSE, view.as_ptr());
 
-            // Set the model matrix (unchanged)
-            let translation = Translation3::new(1.5, 0.0, -7.0);
-            let model: Matrix4<f32> = Matrix4::<f32>::identity() * translation.to_homogeneous();
-            let model_location = gl::GetUniformLocation(shader_program, CString::new("model").unwrap().as_ptr());
-            gl::UniformMatrix4fv(model_location, 1, gl::FALSE, model.as_ptr());
-
-            // Set the projection matrix (unchanged)
-            let projection = Perspective3::new(800.0 / 600.0, 45.0f32.to_radians(), 0.1, 100.0).to_homogeneous();
+            // Set the projection matrix
             let projection_location = gl::GetUniformLocation(shader_program, CString::new("projection").unwrap().as_ptr());
             gl::UniformMatrix4fv(projection_location, 1, gl::FALSE, projection.as_ptr());
 
-            for cube in &cubes {
-                gl::ActiveTexture(gl::TEXTURE0);
-                gl::BindTexture(gl::TEXTURE_2D, cube.texture);
-                let texture_location = gl::GetUniformLocation(shader_program, CString::new("texture1").unwrap().as_ptr());
-                gl::Uniform1i(texture_location, 0);
-
-                // Set the model matrix uniform
-                let translation = Translation3::new(cube.position.x, cube.position.y, cube.position.z);
-                let model: Matrix4<f32> = Matrix4::<f32>::identity() * translation.to_homogeneous();
-                let model_location = gl::GetUniformLocation(shader_program, CString::new("model").unwrap().as_ptr());
-                gl::UniformMatrix4fv(model_location, 1, gl::FALSE, model.as_ptr());
-
-
-                // Bind VAO and draw
-                gl::BindVertexArray(vao);
-                gl::DrawArrays(gl::TRIANGLES, 0, 36);
+            gl::ActiveTexture(gl::TEXTURE0);
+            gl::BindTexture(gl::TEXTURE_2D, atlas_texture);
+            let texture_location = gl::GetUniformLocation(shader_program, CString::new("texture1").unwrap().as_ptr());
+            gl::Uniform1i(texture_location, 0);
+
+            let daylight_location = gl::GetUniformLocation(shader_program, CString::new("daylight").unwrap().as_ptr());
+            gl::Uniform1f(daylight_location, sun_brightness(celestial_angle, &game.weather));
+            gl::Uniform3fv(gl::GetUniformLocation(shader_program, CString::new("fogColor").unwrap().as_ptr()), 1, fog.color.as_ptr());
+            gl::Uniform1f(gl::GetUniformLocation(shader_program, CString::new("fogStart").unwrap().as_ptr()), fog.start);
+            gl::Uniform1f(gl::GetUniformLocation(shader_program, CString::new("fogEnd").unwrap().as_ptr()), fog.end);
+
+            // each section sets its own model matrix
+            chunk_renderer.draw(gl::GetUniformLocation(shader_program, CString::new("model").unwrap().as_ptr()));
+
+            if let Some(hit) = selection {
+                gl::UseProgram(outline_program);
+                gl::UniformMatrix4fv(gl::GetUniformLocation(outline_program, CString::new("view").unwrap().as_ptr()), 1, gl::FALSE, view.as_ptr());
+                gl::UniformMatrix4fv(gl::GetUniformLocation(outline_program, CString::new("projection").unwrap().as_ptr()), 1, gl::FALSE, projection.as_ptr());
+                selection_outline.draw(gl::GetUniformLocation(outline_program, CString::new("model").unwrap().as_ptr()), hit.block);
             }
 
             // Check for errors
@@ -253,6 +249,7 @@ fn main() {
 
         // Poll for and process events
         glfw.poll_events();
+        fullscreen.update(&mut glfw, &mut window);
 
         for (_, event) in glfw::flush_messages(&events) {
             match event {
@@ -265,29 +262,48 @@ fn main() {
                         (Key::A, Action::Release) | (Key::D, Action::Press) => x -= 1,
                         (Key::W, Action::Press) | (Key::S, Action::Release) => z -= 1,
                         (Key::W, Action::Release) | (Key::S, Action::Press) =>  z += 1,
-                        (Key::Space, Action::Press) | (Key::LeftShift, Action::Release) => y += 1,
-                        (Key::Space, Action::Release) | (Key::LeftShift, Action::Press) => y -= 1,
-                        (Key::G, Action::Press) => unsafe {
-                            if is_fullscreen {
-                                window.set_monitor(WindowMode::Windowed, previousX, previousY, previousW as u32, previousH as u32, Option::from(0u32));
-                            } else {
-                                glfw.with_primary_monitor(|_, mut primary_monitor| {
-                                    if let Some(monitor) = primary_monitor {
-                                        let mode = monitor.get_video_mode().expect("Failed to get video mode");
-                                        (previousX, previousY) = window.get_pos();
-                                        (previousW, previousH) = window.get_size();
-
-                                        window.set_monitor(WindowMode::FullScreen(monitor), 0, 0, mode.width, mode.height, Option::from(mode.refresh_rate));
-                                    }
-                                });
-                            }
-                            is_fullscreen = !is_fullscreen;
-
+                        (Key::Space, Action::Press) => {
+                            input.jump = true;
+                            input.jump_pressed = true;
                         }
+                        (Key::Space, Action::Release) => input.jump = false,
+                        (Key::LeftShift, Action::Press) => input.sneak = true,
+                        (Key::LeftShift, Action::Release) => input.sneak = false,
+                        (Key::LeftControl, Action::Press) => input.sprint = true,
+                        (Key::LeftControl, Action::Release) => input.sprint = false,
+                        (Key::Num1 | Key::Num2 | Key::Num3 | Key::Num4 | Key::Num5 | Key::Num6 | Key::Num7 | Key::Num8 | Key::Num9, Action::Press) => {
+                            held_block = hotbar[key as usize - Key::Num1 as usize];
+                        }
+                        (Key::M, Action::Press) => {
+                            chunk_renderer.mesh_mode = match chunk_renderer.mesh_mode {
+                                MeshMode::Naive => MeshMode::Greedy,
+                                MeshMode::Greedy => MeshMode::Naive,
+                            };
+                            chunk_renderer.rebuild_all();
+                        }
+                        (Key::L, Action::Press) => {
+                            chunk_renderer.smooth_lighting = chunk_renderer.smooth_lighting.next();
+                            chunk_renderer.rebuild_all();
+                        }
+                        // further out, or back in with shift like vanilla
+                        (Key::F, Action::Press) => {
+                            let step = if input.sneak { -1 } else { 1 };
+                            chunk_renderer.set_render_distance(chunk_renderer.render_distance() + step);
+                        }
+                        (Key::V, Action::Press) => {
+                            let step = if input.sneak { -FOV_STEP } else { FOV_STEP };
+                            chunk_renderer.projection.set_fov(chunk_renderer.projection.fov() + step);
+                        }
+                        (Key::G, Action::Press) => fullscreen.toggle(&mut glfw, &mut window),
                         _ => {}
                     }
                 }
 
+                glfw::WindowEvent::FramebufferSize(width, height) => {
+                    unsafe { gl::Viewport(0, 0, width, height) }
+                    chunk_renderer.projection.resize(width, height);
+                }
+
                 glfw::WindowEvent::CursorPos(xpos, ypos) => {
                     if first_mouse {
                         last_x = xpos;
@@ -315,8 +331,20 @@ fn main() {
                 }
 
                 MouseButton(glfw::MouseButtonLeft, Action::Press, _) => {
-                    let (x, y) = window.get_cursor_pos();
-                    println!("Clicked at x: {}, y: {}", x, y)
+                    if let Some(hit) = selection {
+                        if game.break_block(hit.block) {
+                            chunk_renderer.block_changed(&game.world, &game.registry, &atlas, hit.block);
+                            chunk_renderer.light_changed(&mut game.world, &game.registry, &atlas);
+                        }
+                    }
+                }
+                MouseButton(glfw::MouseButtonRight, Action::Press, _) => {
+                    if let Some(hit) = selection {
+                        if game.place_block(hit.adjacent(), held_block) {
+                            chunk_renderer.block_changed(&game.world, &game.registry, &atlas, hit.adjacent());
+                            chunk_renderer.light_changed(&mut game.world, &game.registry, &atlas);
+                        }
+                    }
                 }
                 _ => {}
             }
@@ -325,6 +353,28 @@ fn main() {
 
 
 
+    }
+
+    game.player.yaw = camera.yaw;
+    game.player.pitch = camera.pitch;
+    if let Err(err) = save_game(&mut game, &world_dir) {
+        eprintln!("Couldn't save to {}: {}", world_dir.display(), err);
+    }
+}
+
+/// Picks up where the last session in `dir` left off, or starts a new world with a random seed
+fn load_or_create(dir: &Path) -> Game {
+    match load_game(dir) {
+        Ok(Some(game)) => game,
+        Ok(None) => {
+            // new worlds save here too, so chunks can be unloaded before the first save
+            let seed = SystemTime::now().duration_since(UNIX_EPOCH).map(|time| time.as_nanos() as i64).unwrap_or(0);
+            Game::with_storage(seed, Some(ChunkStorage::new(dir)), PendingDecorations::new())
+        }
+        Err(err) => {
+            eprintln!("Couldn't load {}: {}", dir.display(), err);
+            exit(1);
+        }
     }
 }
 
@@ -358,8 +408,17 @@ const VERTEX_SHADER_SOURCE: &str = r#"
     #version 330 core
     layout (location = 0) in vec3 aPos;
     layout (location = 1) in vec2 aTexCoords;
+    layout (location = 2) in vec4 aTile;
+    layout (location = 3) in vec3 aTint;
+    layout (location = 4) in vec2 aLight;
+    layout (location = 5) in float aShade;
 
     out vec2 TexCoords;
+    out vec4 Tile;
+    out vec3 Tint;
+    out vec2 Light;
+    out float Shade;
+    out float Distance;
 
     uniform mat4 model;
     uniform mat4 view;
@@ -367,7 +426,13 @@ const VERTEX_SHADER_SOURCE: &str = r#"
 
     void main() {
         TexCoords = aTexCoords;
-        gl_Position = projection * view * model * vec4(aPos, 1.0);
+        Tile = aTile;
+        Tint = aTint;
+        Light = aLight;
+        Shade = aShade;
+        vec4 position = view * model * vec4(aPos, 1.0);
+        Distance = length(position.xyz);
+        gl_Position = projection * position;
     }
 "#;
 
@@ -375,13 +440,64 @@ const FRAGMENT_SHADER_SOURCE: &str = r#"
     #version 330 core
 
     in vec2 TexCoords;
+    in vec4 Tile;
+    in vec3 Tint;
+    in vec2 Light;
+    in float Shade;
+    in float Distance;
 
     uniform sampler2D texture1;
+    // how much of the sky's light there is at this time of day
+    uniform float daylight;
+    uniform vec3 fogColor;
+    uniform float fogStart;
+    uniform float fogEnd;
+
+    out vec4 FragColor;
+
+    // vanilla's light level to brightness curve, never quite black
+    float brightness(float level) {
+        float dark = 1.0 - level;
+        return mix(0.05, 1.0, (1.0 - dark) / (dark * 3.0 + 1.0));
+    }
+
+    void main() {
+        // wrap inside the atlas tile, the gradients come from the unwrapped coordinates
+        // so the mip level doesn't jump where the texture repeats
+        vec2 size = Tile.zw - Tile.xy;
+        vec2 uv = Tile.xy + fract(TexCoords) * size;
+        vec4 color = textureGrad(texture1, uv, dFdx(TexCoords) * size, dFdy(TexCoords) * size);
+        // cut out the see-through bits of leaves and plants
+        if (color.a < 0.5) {
+            discard;
+        }
+        float light = max(brightness(Light.x) * daylight, brightness(Light.y)) * Shade;
+        // measured from the eye rather than along the view, so turning doesn't move it
+        float fog = clamp((Distance - fogStart) / (fogEnd - fogStart), 0.0, 1.0);
+        FragColor = vec4(mix(color.rgb * Tint * light, fogColor, fog), color.a);
+    }
+"#;
+
+const OUTLINE_VERTEX_SHADER_SOURCE: &str = r#"
+    #version 330 core
+    layout (location = 0) in vec3 aPos;
+
+    uniform mat4 model;
+    uniform mat4 view;
+    uniform mat4 projection;
+
+    void main() {
+        gl_Position = projection * view * model * vec4(aPos, 1.0);
+    }
+"#;
+
+const OUTLINE_FRAGMENT_SHADER_SOURCE: &str = r#"
+    #version 330 core
 
     out vec4 FragColor;
 
     void main() {
-        FragColor = texture(texture1, TexCoords);
+        FragColor = vec4(0.0, 0.0, 0.0, 1.0);
     }
 "#;
 
@@ -411,12 +527,9 @@ fn link_program(vs: GLuint, fs: GLuint) -> GLuint {
     program
 }
 
-fn load_texture(file_path: &str) -> u32 {
-    let file =  RESOURCES_DIR.get_file(file_path).expect("Texture file not found in resources!");
-    let img = image::load(Cursor::new(file.contents()), image::ImageFormat::Png)
-        .expect("Failed to load icon image")
-        .flipv()
-        .to_rgba8();
+fn load_atlas(atlas: &TextureAtlas) -> u32 {
+    // GL wants the bottom row first, the UVs from the atlas already account for that
+    let img = image::imageops::flip_vertical(&atlas.image);
 
     let (width, height) = img.dimensions();
 
@@ -435,17 +548,55 @@ fn load_texture(file_path: &str) -> u32 {
             gl::UNSIGNED_BYTE,
             img.as_ptr() as *const _,
         );
+        // anything past the atlas' mip levels would start mixing tiles together
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAX_LEVEL, atlas.mip_levels as i32);
         gl::GenerateMipmap(gl::TEXTURE_2D);
 
-        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::REPEAT as i32);
-        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::REPEAT as i32);
-        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::NEAREST as i32);
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::NEAREST_MIPMAP_LINEAR as i32);
         gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::NEAREST as i32);
     }
 
     texture_id
 }
 
+/// `nanocraft ping <host[:port]> [--legacy]`, prints what the server list would show
+fn ping_command(args: &[String]) {
+    let legacy = args.iter().any(|arg| arg == "--legacy");
+    let Some(address) = args.iter().find(|arg| !arg.starts_with("--")) else {
+        eprintln!("Usage: nanocraft ping <host[:port]> [--legacy]");
+        exit(2);
+    };
+    let (host, port) = match parse_address(address) {
+        Ok(address) => address,
+        Err(err) => {
+            eprintln!("{}", err);
+            exit(2);
+        }
+    };
+
+    let result = if legacy { ping_legacy(host, port) } else { ping(host, port) };
+    match result {
+        Ok((status, latency)) => {
+            let host = if host.contains(':') { format!("[{}]", host) } else { host.to_string() };
+            println!("{}:{} - {} (protocol {})", host, port, status.version_name, status.protocol);
+            println!("{}", status.motd);
+            if status.sample.is_empty() {
+                println!("{}/{} players", status.online, status.max_players);
+            } else {
+                println!("{}/{} players: {}", status.online, status.max_players, status.sample.join(", "));
+            }
+            println!("Favicon: {}", if status.favicon.is_some() { "yes" } else { "no" });
+            println!("Latency: {} ms", latency.as_millis());
+        }
+        Err(err) => {
+            eprintln!("Couldn't ping {}:{}: {}", host, port, err);
+            exit(1);
+        }
+    }
+}
+
 fn set_window_icon(window: &mut glfw::Window, file_path: &str) {
     let icon_file = RESOURCES_DIR.get_file(file_path).expect("Icon file not found in resources");
     // Load the image using the image crate
@@ -0,0 +1,375 @@
+use crate::atlas::{TextureAtlas, UvRect};
+use crate::block::{BlockRegistry, Face, Tint};
+use crate::cube::VERTICES;
+use crate::world::{BlockPos, BlockState, ChunkPos, LightType, CHUNK_WIDTH, SECTION_HEIGHT, World};
+use crate::worldgen::biome::Biome;
+
+/// Position, texture coordinates that count in blocks, the atlas tile they wrap around in, the tint colour,
+/// sky light and block light as 0..1, then how much the face is shaded
+pub const FLOATS_PER_VERTEX: usize = 15;
+pub const VERTICES_PER_FACE: usize = 6;
+/// Which of a face's four corners make up its two triangles, `cube::VERTICES` splits along 0-2
+const TRIANGLES: [usize; 6] = [0, 1, 2, 3, 0, 2];
+/// The same corners split along 1-3 instead
+const FLIPPED_TRIANGLES: [usize; 6] = [1, 2, 3, 3, 0, 1];
+/// Each block next to a corner that's in the way takes this much of its light in maximum smooth lighting
+const OCCLUSION_STRENGTH: f32 = 0.2;
+/// `cube::VERTICES` only has position and texture coordinates
+const TEMPLATE_FLOATS_PER_VERTEX: usize = 5;
+const TEMPLATE_FLOATS_PER_FACE: usize = TEMPLATE_FLOATS_PER_VERTEX * VERTICES_PER_FACE;
+
+/// Vertices for one 16x16x16 section, positioned relative to the section's lowest corner.
+#[derive(Clone, Debug, Default)]
+pub struct SectionMesh {
+    pub vertices: Vec<f32>,
+}
+
+impl SectionMesh {
+    pub fn vertex_count(&self) -> usize {
+        self.vertices.len() / FLOATS_PER_VERTEX
+    }
+
+    pub fn face_count(&self) -> usize {
+        self.vertex_count() / VERTICES_PER_FACE
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.vertices.is_empty()
+    }
+}
+
+/// The two triangles `cube::VERTICES` uses for a face. The cube there spans -1..1 so
+/// callers still have to bring it down to a unit block.
+pub fn face_template(face: Face) -> &'static [f32] {
+    let slot = match face {
+        Face::North => 0,
+        Face::South => 1,
+        Face::Up => 2,
+        Face::Down => 3,
+        Face::West => 4,
+        Face::East => 5,
+    };
+    &VERTICES[slot * TEMPLATE_FLOATS_PER_FACE..(slot + 1) * TEMPLATE_FLOATS_PER_FACE]
+}
+
+/// Whether `face` of a block should be drawn given the block on the other side of it
+pub fn face_visible(registry: &BlockRegistry, block: BlockState, neighbour: BlockState) -> bool {
+    if neighbour.is_air() {
+        return true;
+    }
+    // glass next to glass or water next to water would just be a pile of inner faces
+    registry.is_transparent(neighbour) && neighbour.id() != block.id()
+}
+
+/// Which axes a face lies across as `(normal, u, v)`, with u and v being the axes
+/// its texture coordinates run along in `cube::VERTICES`
+fn face_axes(face: Face) -> (usize, usize, usize) {
+    match face {
+        Face::North | Face::South => (2, 0, 1),
+        Face::Up | Face::Down => (1, 0, 2),
+        Face::West | Face::East => (0, 2, 1),
+    }
+}
+
+/// Vanilla's fixed shading by direction, so the sides of a block don't all look the same
+fn face_shade(face: Face) -> f32 {
+    match face {
+        Face::Up => 1.0,
+        Face::Down => 0.5,
+        Face::North | Face::South => 0.8,
+        Face::West | Face::East => 0.6,
+    }
+}
+
+/// How faces get lit, like 1.8.9's smooth lighting option
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum SmoothLighting {
+    /// Every face takes the light of the block in front of it
+    Off,
+    /// Light at each corner is the average of the four blocks touching it
+    Minimum,
+    /// Averaged light, and corners tucked in against other blocks get darker too
+    #[default]
+    Maximum,
+}
+
+impl SmoothLighting {
+    pub fn next(self) -> Self {
+        match self {
+            SmoothLighting::Off => SmoothLighting::Minimum,
+            SmoothLighting::Minimum => SmoothLighting::Maximum,
+            SmoothLighting::Maximum => SmoothLighting::Off,
+        }
+    }
+}
+
+/// Light at one corner of a face: sky and block light summed over the four blocks around it,
+/// so 0..=60, and how many of the three that aren't straight in front of the face are in the way
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+struct CornerLight {
+    sky: u8,
+    block: u8,
+    occluders: u8,
+}
+
+impl CornerLight {
+    fn brightness(self) -> u32 {
+        (self.sky.max(self.block) as u32) * (4 - self.occluders as u32)
+    }
+}
+
+/// Light for the corners of the face of the block at `pos` that looks out at `face`, in the
+/// order `face_template` has them
+fn corner_lights(world: &World, registry: &BlockRegistry, pos: BlockPos, face: Face, smooth: SmoothLighting) -> [CornerLight; 4] {
+    let (dx, dy, dz) = face.offset();
+    let front = BlockPos::new(pos.x + dx, pos.y + dy, pos.z + dz);
+    let light = |pos: BlockPos| (world.get_light(LightType::Sky, pos), world.get_light(LightType::Block, pos));
+    let (sky, block) = light(front);
+    if smooth == SmoothLighting::Off {
+        return [CornerLight { sky: sky * 4, block: block * 4, occluders: 0 }; 4];
+    }
+
+    let (_, u_axis, v_axis) = face_axes(face);
+    let template = face_template(face);
+    std::array::from_fn(|i| {
+        let vertex = &template[i * TEMPLATE_FLOATS_PER_VERTEX..];
+        let step = |axis: usize, offset: &mut [i32; 3]| offset[axis] += if vertex[axis] > 0.0 { 1 } else { -1 };
+        let (mut along_u, mut along_v) = ([front.x, front.y, front.z], [front.x, front.y, front.z]);
+        step(u_axis, &mut along_u);
+        step(v_axis, &mut along_v);
+        let mut diagonal = along_u;
+        step(v_axis, &mut diagonal);
+        let [along_u, along_v, diagonal] = [along_u, along_v, diagonal].map(|[x, y, z]| BlockPos::new(x, y, z));
+
+        let occludes = |pos: BlockPos| !registry.is_transparent(world.get_block(pos));
+        let (u_blocked, v_blocked) = (occludes(along_u), occludes(along_v));
+        // with both sides in the way the diagonal can't be seen from the face at all
+        let diagonal_blocked = (u_blocked && v_blocked) || occludes(diagonal);
+        let diagonal_light = if u_blocked && v_blocked { light(along_u) } else { light(diagonal) };
+
+        // blocks in the way have no light of their own, so they count as the block in front
+        let mut corner = CornerLight { sky, block, occluders: 0 };
+        for (side_sky, side_block) in [light(along_u), light(along_v), diagonal_light] {
+            corner.sky += if side_sky == 0 { sky } else { side_sky };
+            corner.block += if side_block == 0 { block } else { side_block };
+        }
+        if smooth == SmoothLighting::Maximum {
+            corner.occluders = u_blocked as u8 + v_blocked as u8 + diagonal_blocked as u8;
+        }
+        corner
+    })
+}
+
+/// Appends a face covering `size` blocks starting at `corner`. Texture coordinates are
+/// scaled by the size too, the shader wraps them back into `tile` so the texture repeats.
+fn push_face(out: &mut Vec<f32>, face: Face, corner: [f32; 3], size: [f32; 3], tile: UvRect, color: u32, lights: [CornerLight; 4]) {
+    let [_, r, g, b] = color.to_be_bytes();
+    let (_, u_axis, v_axis) = face_axes(face);
+    let template = face_template(face);
+    // split along the darker diagonal, otherwise the light smears across the quad one way only
+    let brightness = lights.map(CornerLight::brightness);
+    let triangles = if brightness[0] + brightness[2] > brightness[1] + brightness[3] { FLIPPED_TRIANGLES } else { TRIANGLES };
+    for i in triangles {
+        let vertex = &template[i * TEMPLATE_FLOATS_PER_VERTEX..(i + 1) * TEMPLATE_FLOATS_PER_VERTEX];
+        let light = lights[i];
+        out.extend_from_slice(&[
+            corner[0] + (vertex[0] + 1.0) / 2.0 * size[0],
+            corner[1] + (vertex[1] + 1.0) / 2.0 * size[1],
+            corner[2] + (vertex[2] + 1.0) / 2.0 * size[2],
+            vertex[3] * size[u_axis],
+            vertex[4] * size[v_axis],
+            tile.u0,
+            tile.v0,
+            tile.u1,
+            tile.v1,
+            r as f32 / 255.0,
+            g as f32 / 255.0,
+            b as f32 / 255.0,
+            light.sky as f32 / 60.0,
+            light.block as f32 / 60.0,
+            face_shade(face) * (1.0 - OCCLUSION_STRENGTH * light.occluders as f32),
+        ]);
+    }
+}
+
+/// Texture, tint, the light at each corner, and whether the face may be merged with its neighbours
+type FaceKey = (&'static str, u32, [CornerLight; 4], bool);
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum MeshMode {
+    /// One quad for every visible block face
+    #[default]
+    Naive,
+    /// Merges neighbouring opaque faces with the same texture into bigger quads
+    Greedy,
+}
+
+/// Builds the mesh for section `section_y` of a chunk, only keeping faces that border air
+/// or transparent blocks. Blocks in unloaded neighbouring chunks count as air.
+pub fn mesh_section(world: &World, registry: &BlockRegistry, atlas: &TextureAtlas, chunk_pos: ChunkPos, section_y: usize, mode: MeshMode, smooth: SmoothLighting) -> SectionMesh {
+    let mut mesh = SectionMesh::default();
+    let Some(chunk) = world.chunk(chunk_pos) else {
+        return mesh;
+    };
+    let Some(section) = chunk.section(section_y) else {
+        return mesh;
+    };
+    let origin = chunk_pos.origin();
+    let base_y = (section_y * SECTION_HEIGHT) as i32;
+
+    for face in Face::ALL {
+        let (normal, u_axis, v_axis) = face_axes(face);
+        let (dx, dy, dz) = face.offset();
+
+        for layer in 0..SECTION_HEIGHT {
+            // every visible face in this slice
+            let mut mask: [[Option<FaceKey>; 16]; 16] = [[None; 16]; 16];
+            for (v, row) in mask.iter_mut().enumerate() {
+                for (u, cell) in row.iter_mut().enumerate() {
+                    let mut p = [0; 3];
+                    p[normal] = layer;
+                    p[u_axis] = u;
+                    p[v_axis] = v;
+                    let block = section.get(p[0], p[1], p[2]);
+                    if block.is_air() {
+                        continue;
+                    }
+
+                    let (nx, ny, nz) = (p[0] as i32 + dx, p[1] as i32 + dy, p[2] as i32 + dz);
+                    let neighbour = if (0..16).contains(&nx) && (0..16).contains(&ny) && (0..16).contains(&nz) {
+                        section.get(nx as usize, ny as usize, nz as usize)
+                    } else {
+                        world.get_block(BlockPos::new(origin.x + nx, base_y + ny, origin.z + nz))
+                    };
+                    if !face_visible(registry, block, neighbour) {
+                        continue;
+                    }
+
+                    let def = registry.def(block);
+                    let biome = Biome::from_id(chunk.biome(p[0], p[2])).unwrap_or(Biome::Plains);
+                    let color = match def.tint[face.index()] {
+                        Tint::None => 0xFFFFFF,
+                        Tint::Grass => biome.grass_color(),
+                        Tint::Foliage => biome.foliage_color(),
+                    };
+                    let block_pos = BlockPos::new(origin.x + p[0] as i32, base_y + p[1] as i32, origin.z + p[2] as i32);
+                    let lights = corner_lights(world, registry, block_pos, face, smooth);
+                    // a quad only has light at its corners, so only evenly lit faces can be merged
+                    let mergeable = mode == MeshMode::Greedy && !def.transparent && lights.iter().all(|light| *light == lights[0]);
+                    *cell = Some((def.textures.get(face), color, lights, mergeable));
+                }
+            }
+
+            for v in 0..CHUNK_WIDTH {
+                let mut u = 0;
+                while u < CHUNK_WIDTH {
+                    let Some((texture, color, lights, mergeable)) = mask[v][u] else {
+                        u += 1;
+                        continue;
+                    };
+
+                    let (mut width, mut height) = (1, 1);
+                    if mergeable {
+                        while u + width < CHUNK_WIDTH && mask[v][u + width] == mask[v][u] {
+                            width += 1;
+                        }
+                        while v + height < CHUNK_WIDTH && mask[v + height][u..u + width].iter().all(|cell| *cell == mask[v][u]) {
+                            height += 1;
+                        }
+                    }
+                    for row in &mut mask[v..v + height] {
+                        row[u..u + width].fill(None);
+                    }
+
+                    let mut corner = [0.0; 3];
+                    corner[normal] = layer as f32;
+                    corner[u_axis] = u as f32;
+                    corner[v_axis] = v as f32;
+                    let mut size = [1.0; 3];
+                    size[u_axis] = width as f32;
+                    size[v_axis] = height as f32;
+                    push_face(&mut mesh.vertices, face, corner, size, atlas.uv(texture), color, lights);
+
+                    u += width;
+                }
+            }
+        }
+    }
+
+    mesh
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::atlas::AtlasBuilder;
+    use crate::block::{DIRT, GLASS, STONE};
+    use crate::world::Chunk;
+
+    fn mesh(world: &World, mode: MeshMode) -> SectionMesh {
+        let atlas = AtlasBuilder::new().mip_levels(0).build();
+        mesh_section(world, &BlockRegistry::vanilla(), &atlas, ChunkPos::new(0, 0), 0, mode, SmoothLighting::Off)
+    }
+
+    /// One chunk at the origin with `blocks` in its bottom section
+    fn world_with(blocks: impl IntoIterator<Item = (usize, usize, usize, BlockState)>) -> World {
+        let mut chunk = Chunk::new(ChunkPos::new(0, 0));
+        for (x, y, z, state) in blocks {
+            chunk.set_block(x, y, z, state);
+        }
+        let mut world = World::new();
+        world.load_chunk(chunk);
+        world
+    }
+
+    #[test]
+    fn single_block_has_six_faces() {
+        let world = world_with([(4, 4, 4, STONE)]);
+        assert_eq!(mesh(&world, MeshMode::Naive).face_count(), 6);
+    }
+
+    #[test]
+    fn solid_section_only_keeps_its_outside() {
+        let blocks = (0..16 * 16 * 16).map(|i| (i & 15, i >> 8, i >> 4 & 15, STONE));
+        let world = world_with(blocks);
+        assert_eq!(mesh(&world, MeshMode::Naive).face_count(), 6 * 256);
+    }
+
+    #[test]
+    fn faces_between_opaque_blocks_are_culled() {
+        let world = world_with([(4, 4, 4, STONE), (5, 4, 4, STONE)]);
+        assert_eq!(mesh(&world, MeshMode::Naive).face_count(), 10);
+    }
+
+    #[test]
+    fn faces_against_transparent_blocks_are_kept() {
+        let world = world_with([(4, 4, 4, STONE), (5, 4, 4, GLASS)]);
+        // the stone shows through the glass, the glass doesn't against the stone
+        assert_eq!(mesh(&world, MeshMode::Naive).face_count(), 11);
+        let world = world_with([(4, 4, 4, GLASS), (5, 4, 4, GLASS)]);
+        assert_eq!(mesh(&world, MeshMode::Naive).face_count(), 10);
+    }
+
+    #[test]
+    fn flat_floor_is_one_quad_per_side() {
+        let floor = (0..16 * 16).map(|i| (i & 15, 4, i >> 4, STONE));
+        let world = world_with(floor);
+        assert_eq!(mesh(&world, MeshMode::Greedy).face_count(), 6);
+        assert_eq!(mesh(&world, MeshMode::Naive).face_count(), 2 * 256 + 4 * 16);
+    }
+
+    #[test]
+    fn greedy_never_has_more_faces_than_naive() {
+        // a bumpy mix of two textures and some glass, which greedy meshing leaves alone
+        let blocks = (0..16 * 16).flat_map(|i| {
+            let (x, z) = (i & 15, i >> 4);
+            let height = (x * 7 + z * 3) % 5 + (x / 4 + z / 4) % 3;
+            (0..=height).map(move |y| (x, y, z, if y == height && (x + z) % 3 == 0 { GLASS } else if y % 2 == 0 { STONE } else { DIRT }))
+        });
+        let world = world_with(blocks);
+        let naive = mesh(&world, MeshMode::Naive).face_count();
+        let greedy = mesh(&world, MeshMode::Greedy).face_count();
+        assert!(greedy <= naive, "greedy {greedy} naive {naive}");
+        assert!(greedy < naive);
+    }
+}
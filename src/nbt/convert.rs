@@ -0,0 +1,631 @@
+//! Serde support, so save data can be plain structs instead of compounds picked apart by hand.
+//!
+//! Structs and maps become compounds, sequences become lists, `None` fields are left out and
+//! unit variants are strings. Unsigned integers are stored in the signed tag of the same size,
+//! the way vanilla does it. Lists are lists of tags by default, use `byte_array` or `int_array`
+//! on a field to get the array tags instead.
+
+use std::fmt;
+use std::io;
+
+use serde::de::value::{BorrowedStrDeserializer, StrDeserializer};
+use serde::de::{self, DeserializeSeed, EnumAccess, IntoDeserializer, MapAccess, SeqAccess, VariantAccess, Visitor};
+use serde::ser::{self, Serialize};
+use serde::{forward_to_deserialize_any, Deserialize};
+
+use crate::nbt::{Compound, List, Tag, BYTE, INT};
+
+/// Newtype names `byte_array` and `int_array` mark their fields with
+const BYTE_ARRAY_MARKER: &str = "$nbt::ByteArray";
+const INT_ARRAY_MARKER: &str = "$nbt::IntArray";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Error(String);
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl ser::Error for Error {
+    fn custom<T: fmt::Display>(message: T) -> Self {
+        Error(message.to_string())
+    }
+}
+
+impl de::Error for Error {
+    fn custom<T: fmt::Display>(message: T) -> Self {
+        Error(message.to_string())
+    }
+}
+
+impl From<Error> for io::Error {
+    fn from(err: Error) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, err.0)
+    }
+}
+
+pub fn to_tag<T: Serialize + ?Sized>(value: &T) -> Result<Tag, Error> {
+    value.serialize(TagSerializer)?.ok_or_else(|| Error("there's no tag for None".to_string()))
+}
+
+pub fn from_tag<'de, T: Deserialize<'de>>(tag: &'de Tag) -> Result<T, Error> {
+    T::deserialize(TagDeserializer(tag))
+}
+
+/// `#[serde(with = "nbt::convert::byte_array")]` stores a sequence of bytes as a Byte Array tag
+pub mod byte_array {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<T: Serialize, S: Serializer>(value: &T, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_newtype_struct(super::BYTE_ARRAY_MARKER, value)
+    }
+
+    pub fn deserialize<'de, T: Deserialize<'de>, D: Deserializer<'de>>(deserializer: D) -> Result<T, D::Error> {
+        T::deserialize(deserializer)
+    }
+}
+
+/// `#[serde(with = "nbt::convert::int_array")]` stores a sequence of ints as an Int Array tag
+pub mod int_array {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<T: Serialize, S: Serializer>(value: &T, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_newtype_struct(super::INT_ARRAY_MARKER, value)
+    }
+
+    pub fn deserialize<'de, T: Deserialize<'de>, D: Deserializer<'de>>(deserializer: D) -> Result<T, D::Error> {
+        T::deserialize(deserializer)
+    }
+}
+
+/// Turns a list the serializer built back into the array tag a marker asked for
+fn into_array(tag: Tag, element: u8) -> Result<Tag, Error> {
+    let list = match tag {
+        Tag::List(list) if list.is_empty() || list.element() == element => list,
+        Tag::ByteArray(values) if element == BYTE => return Ok(Tag::ByteArray(values)),
+        tag => return Err(Error(format!("can't make an array out of {tag}"))),
+    };
+    Ok(match element {
+        BYTE => Tag::ByteArray(list.into_iter().map(|tag| if let Tag::Byte(value) = tag { value } else { unreachable!() }).collect()),
+        _ => Tag::IntArray(list.into_iter().map(|tag| if let Tag::Int(value) = tag { value } else { unreachable!() }).collect()),
+    })
+}
+
+/// Serializes to `None` for values that should be left out, which only compounds are allowed to do
+struct TagSerializer;
+
+impl ser::Serializer for TagSerializer {
+    type Ok = Option<Tag>;
+    type Error = Error;
+    type SerializeSeq = ListSerializer;
+    type SerializeTuple = ListSerializer;
+    type SerializeTupleStruct = ListSerializer;
+    type SerializeTupleVariant = VariantSerializer<ListSerializer>;
+    type SerializeMap = CompoundSerializer;
+    type SerializeStruct = CompoundSerializer;
+    type SerializeStructVariant = VariantSerializer<CompoundSerializer>;
+
+    fn serialize_bool(self, value: bool) -> Result<Self::Ok, Error> {
+        Ok(Some(Tag::Byte(value as i8)))
+    }
+
+    fn serialize_i8(self, value: i8) -> Result<Self::Ok, Error> {
+        Ok(Some(Tag::Byte(value)))
+    }
+
+    fn serialize_i16(self, value: i16) -> Result<Self::Ok, Error> {
+        Ok(Some(Tag::Short(value)))
+    }
+
+    fn serialize_i32(self, value: i32) -> Result<Self::Ok, Error> {
+        Ok(Some(Tag::Int(value)))
+    }
+
+    fn serialize_i64(self, value: i64) -> Result<Self::Ok, Error> {
+        Ok(Some(Tag::Long(value)))
+    }
+
+    fn serialize_u8(self, value: u8) -> Result<Self::Ok, Error> {
+        Ok(Some(Tag::Byte(value as i8)))
+    }
+
+    fn serialize_u16(self, value: u16) -> Result<Self::Ok, Error> {
+        Ok(Some(Tag::Short(value as i16)))
+    }
+
+    fn serialize_u32(self, value: u32) -> Result<Self::Ok, Error> {
+        Ok(Some(Tag::Int(value as i32)))
+    }
+
+    fn serialize_u64(self, value: u64) -> Result<Self::Ok, Error> {
+        Ok(Some(Tag::Long(value as i64)))
+    }
+
+    fn serialize_f32(self, value: f32) -> Result<Self::Ok, Error> {
+        Ok(Some(Tag::Float(value)))
+    }
+
+    fn serialize_f64(self, value: f64) -> Result<Self::Ok, Error> {
+        Ok(Some(Tag::Double(value)))
+    }
+
+    fn serialize_char(self, value: char) -> Result<Self::Ok, Error> {
+        Ok(Some(Tag::String(value.to_string())))
+    }
+
+    fn serialize_str(self, value: &str) -> Result<Self::Ok, Error> {
+        Ok(Some(Tag::String(value.to_string())))
+    }
+
+    fn serialize_bytes(self, value: &[u8]) -> Result<Self::Ok, Error> {
+        Ok(Some(Tag::ByteArray(value.iter().map(|&byte| byte as i8).collect())))
+    }
+
+    fn serialize_none(self) -> Result<Self::Ok, Error> {
+        Ok(None)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Self::Ok, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Self::Ok, Error> {
+        Ok(Some(Tag::Compound(Compound::new())))
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Self::Ok, Error> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(self, _name: &'static str, _index: u32, variant: &'static str) -> Result<Self::Ok, Error> {
+        Ok(Some(Tag::String(variant.to_string())))
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(self, name: &'static str, value: &T) -> Result<Self::Ok, Error> {
+        let tag = value.serialize(self)?;
+        match name {
+            BYTE_ARRAY_MARKER => tag.map(|tag| into_array(tag, BYTE)).transpose(),
+            INT_ARRAY_MARKER => tag.map(|tag| into_array(tag, INT)).transpose(),
+            _ => Ok(tag),
+        }
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(self, _name: &'static str, _index: u32, variant: &'static str, value: &T) -> Result<Self::Ok, Error> {
+        let mut compound = Compound::new();
+        if let Some(tag) = value.serialize(self)? {
+            compound.insert(variant, tag);
+        }
+        Ok(Some(Tag::Compound(compound)))
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<ListSerializer, Error> {
+        Ok(ListSerializer(List::new()))
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<ListSerializer, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, len: usize) -> Result<ListSerializer, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(self, _name: &'static str, _index: u32, variant: &'static str, _len: usize) -> Result<Self::SerializeTupleVariant, Error> {
+        Ok(VariantSerializer { variant, inner: ListSerializer(List::new()) })
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<CompoundSerializer, Error> {
+        Ok(CompoundSerializer { compound: Compound::new(), key: None })
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<CompoundSerializer, Error> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(self, _name: &'static str, _index: u32, variant: &'static str, _len: usize) -> Result<Self::SerializeStructVariant, Error> {
+        Ok(VariantSerializer { variant, inner: CompoundSerializer { compound: Compound::new(), key: None } })
+    }
+}
+
+struct ListSerializer(List);
+
+impl ListSerializer {
+    fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        let tag = value.serialize(TagSerializer)?.ok_or_else(|| Error("lists can't have None in them".to_string()))?;
+        let element = self.0.element();
+        self.0.push(tag).map_err(|tag| Error(format!("list of tag type {element} can't take {tag}")))
+    }
+}
+
+impl ser::SerializeSeq for ListSerializer {
+    type Ok = Option<Tag>;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Option<Tag>, Error> {
+        Ok(Some(Tag::List(self.0)))
+    }
+}
+
+impl ser::SerializeTuple for ListSerializer {
+    type Ok = Option<Tag>;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Option<Tag>, Error> {
+        Ok(Some(Tag::List(self.0)))
+    }
+}
+
+impl ser::SerializeTupleStruct for ListSerializer {
+    type Ok = Option<Tag>;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Option<Tag>, Error> {
+        Ok(Some(Tag::List(self.0)))
+    }
+}
+
+struct CompoundSerializer {
+    compound: Compound,
+    key: Option<String>,
+}
+
+impl ser::SerializeMap for CompoundSerializer {
+    type Ok = Option<Tag>;
+    type Error = Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), Error> {
+        // numbers are fine as names too, which covers maps keyed by ids
+        self.key = Some(match key.serialize(TagSerializer)? {
+            Some(Tag::String(key)) => key,
+            Some(tag) if tag.as_i64().is_some() => tag.as_i64().unwrap().to_string(),
+            _ => return Err(Error("compound names have to be strings or integers".to_string())),
+        });
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        let key = self.key.take().ok_or_else(|| Error("map value without a key".to_string()))?;
+        if let Some(tag) = value.serialize(TagSerializer)? {
+            self.compound.insert(key, tag);
+        }
+        Ok(())
+    }
+
+    fn end(self) -> Result<Option<Tag>, Error> {
+        Ok(Some(Tag::Compound(self.compound)))
+    }
+}
+
+impl ser::SerializeStruct for CompoundSerializer {
+    type Ok = Option<Tag>;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, name: &'static str, value: &T) -> Result<(), Error> {
+        if let Some(tag) = value.serialize(TagSerializer)? {
+            self.compound.insert(name, tag);
+        }
+        Ok(())
+    }
+
+    fn end(self) -> Result<Option<Tag>, Error> {
+        Ok(Some(Tag::Compound(self.compound)))
+    }
+}
+
+/// Wraps whatever a variant holds in a compound with just the variant's name in it
+struct VariantSerializer<S> {
+    variant: &'static str,
+    inner: S,
+}
+
+impl<S> VariantSerializer<S> {
+    fn wrap(variant: &str, tag: Option<Tag>) -> Option<Tag> {
+        let mut compound = Compound::new();
+        compound.insert(variant, tag?);
+        Some(Tag::Compound(compound))
+    }
+}
+
+impl ser::SerializeTupleVariant for VariantSerializer<ListSerializer> {
+    type Ok = Option<Tag>;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.inner.push(value)
+    }
+
+    fn end(self) -> Result<Option<Tag>, Error> {
+        Ok(Self::wrap(self.variant, ser::SerializeSeq::end(self.inner)?))
+    }
+}
+
+impl ser::SerializeStructVariant for VariantSerializer<CompoundSerializer> {
+    type Ok = Option<Tag>;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, name: &'static str, value: &T) -> Result<(), Error> {
+        ser::SerializeStruct::serialize_field(&mut self.inner, name, value)
+    }
+
+    fn end(self) -> Result<Option<Tag>, Error> {
+        Ok(Self::wrap(self.variant, ser::SerializeStruct::end(self.inner)?))
+    }
+}
+
+struct TagDeserializer<'de>(&'de Tag);
+
+impl<'de> de::Deserializer<'de> for TagDeserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.0 {
+            Tag::Byte(value) => visitor.visit_i8(*value),
+            Tag::Short(value) => visitor.visit_i16(*value),
+            Tag::Int(value) => visitor.visit_i32(*value),
+            Tag::Long(value) => visitor.visit_i64(*value),
+            Tag::Float(value) => visitor.visit_f32(*value),
+            Tag::Double(value) => visitor.visit_f64(*value),
+            Tag::ByteArray(values) => visitor.visit_seq(ArrayAccess(values.iter().map(|&value| Number::Byte(value)))),
+            Tag::String(value) => visitor.visit_borrowed_str(value),
+            Tag::List(list) => visitor.visit_seq(ListAccess(list.iter())),
+            Tag::Compound(compound) => visitor.visit_map(CompoundAccess { entries: compound.iter(), value: None }),
+            Tag::IntArray(values) => visitor.visit_seq(ArrayAccess(values.iter().map(|&value| Number::Int(value)))),
+        }
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.0 {
+            Tag::Byte(value) => visitor.visit_bool(*value != 0),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_u8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.0 {
+            Tag::Byte(value) => visitor.visit_u8(*value as u8),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_u16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.0 {
+            Tag::Short(value) => visitor.visit_u16(*value as u16),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_u32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.0 {
+            Tag::Int(value) => visitor.visit_u32(*value as u32),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_u64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.0 {
+            Tag::Long(value) => visitor.visit_u64(*value as u64),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        // anything that's there at all is Some, missing fields are None without asking us
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(self, _name: &'static str, _variants: &'static [&'static str], visitor: V) -> Result<V::Value, Error> {
+        match self.0 {
+            Tag::String(variant) => visitor.visit_enum(IntoDeserializer::<Error>::into_deserializer(variant.as_str())),
+            Tag::Compound(compound) if compound.len() == 1 => {
+                let (variant, tag) = compound.iter().next().unwrap();
+                visitor.visit_enum(VariantDeserializer { variant, tag })
+            }
+            tag => Err(Error(format!("expected a variant name or a compound with one tag in it, got {tag}"))),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        i8 i16 i32 i64 i128 u128 f32 f64 char str string bytes byte_buf seq tuple tuple_struct map
+        struct identifier ignored_any
+    }
+}
+
+struct ListAccess<'de>(std::slice::Iter<'de, Tag>);
+
+impl<'de> SeqAccess<'de> for ListAccess<'de> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>, Error> {
+        self.0.next().map(|tag| seed.deserialize(TagDeserializer(tag))).transpose()
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.0.len())
+    }
+}
+
+struct CompoundAccess<'de, I: Iterator<Item = (&'de str, &'de Tag)>> {
+    entries: I,
+    value: Option<&'de Tag>,
+}
+
+impl<'de, I: Iterator<Item = (&'de str, &'de Tag)>> MapAccess<'de> for CompoundAccess<'de, I> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>, Error> {
+        let Some((name, tag)) = self.entries.next() else {
+            return Ok(None);
+        };
+        self.value = Some(tag);
+        seed.deserialize(BorrowedStrDeserializer::new(name)).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        let tag = self.value.take().ok_or_else(|| Error("compound value asked for before its name".to_string()))?;
+        seed.deserialize(TagDeserializer(tag))
+    }
+}
+
+struct VariantDeserializer<'de> {
+    variant: &'de str,
+    tag: &'de Tag,
+}
+
+impl<'de> EnumAccess<'de> for VariantDeserializer<'de> {
+    type Error = Error;
+    type Variant = TagDeserializer<'de>;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self::Variant), Error> {
+        let variant: StrDeserializer<Error> = self.variant.into_deserializer();
+        Ok((seed.deserialize(variant)?, TagDeserializer(self.tag)))
+    }
+}
+
+impl<'de> VariantAccess<'de> for TagDeserializer<'de> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, Error> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value, Error> {
+        de::Deserializer::deserialize_seq(self, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(self, _fields: &'static [&'static str], visitor: V) -> Result<V::Value, Error> {
+        de::Deserializer::deserialize_map(self, visitor)
+    }
+}
+
+/// One element of an array tag, which can be read back as unsigned like the other tags
+enum Number {
+    Byte(i8),
+    Int(i32),
+}
+
+impl<'de> de::Deserializer<'de> for Number {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self {
+            Number::Byte(value) => visitor.visit_i8(value),
+            Number::Int(value) => visitor.visit_i32(value),
+        }
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self {
+            Number::Byte(value) => visitor.visit_bool(value != 0),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_u8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self {
+            Number::Byte(value) => visitor.visit_u8(value as u8),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_u32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self {
+            Number::Int(value) => visitor.visit_u32(value as u32),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        i8 i16 i32 i64 i128 u16 u64 u128 f32 f64 char str string bytes byte_buf option unit
+        unit_struct newtype_struct seq tuple tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+struct ArrayAccess<I: Iterator<Item = Number>>(I);
+
+impl<'de, I: Iterator<Item = Number>> SeqAccess<'de> for ArrayAccess<I> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>, Error> {
+        self.0.next().map(|number| seed.deserialize(number)).transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Serialize;
+
+    use super::*;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Arrays {
+        #[serde(with = "byte_array")]
+        bytes: Vec<u8>,
+        #[serde(with = "int_array")]
+        ints: Vec<i32>,
+        #[serde(with = "byte_array", default)]
+        maybe: Option<Vec<i8>>,
+        plain: Vec<i32>,
+    }
+
+    #[test]
+    fn array_fields_become_array_tags() {
+        let arrays = Arrays { bytes: vec![0, 200, 255], ints: vec![-1, 70000], maybe: Some(vec![-3]), plain: vec![4] };
+        let tag = to_tag(&arrays).unwrap();
+        let compound = tag.as_compound().unwrap();
+        // unsigned bytes are stored as their signed bits
+        assert_eq!(compound.get("bytes"), Some(&Tag::ByteArray(vec![0, -56, -1])));
+        assert_eq!(compound.get("ints"), Some(&Tag::IntArray(vec![-1, 70000])));
+        assert_eq!(compound.get("maybe"), Some(&Tag::ByteArray(vec![-3])));
+        assert_eq!(compound.get_list("plain").map(List::element), Some(INT));
+        assert_eq!(from_tag::<Arrays>(&tag).unwrap(), arrays);
+    }
+
+    #[test]
+    fn empty_and_missing_arrays() {
+        let arrays = Arrays { bytes: Vec::new(), ints: Vec::new(), maybe: None, plain: Vec::new() };
+        let tag = to_tag(&arrays).unwrap();
+        let compound = tag.as_compound().unwrap();
+        assert_eq!(compound.get("bytes"), Some(&Tag::ByteArray(Vec::new())));
+        assert_eq!(compound.get("ints"), Some(&Tag::IntArray(Vec::new())));
+        assert!(!compound.contains("maybe"));
+        assert_eq!(from_tag::<Arrays>(&tag).unwrap(), arrays);
+    }
+
+    #[test]
+    fn wrong_tags_are_errors() {
+        let tag = Tag::Compound(Compound::from_iter([("bytes", Tag::String("no".to_string()))]));
+        assert!(from_tag::<Arrays>(&tag).is_err());
+    }
+}
@@ -0,0 +1,352 @@
+use std::io::{self, BufRead, BufReader, ErrorKind, Read, Write};
+
+use flate2::read::{GzDecoder, ZlibDecoder};
+use flate2::write::{GzEncoder, ZlibEncoder};
+
+use crate::invalid;
+use crate::nbt::{Compound, List, Tag, BYTE, BYTE_ARRAY, COMPOUND, DOUBLE, END, FLOAT, INT, INT_ARRAY, LIST, LONG, SHORT, STRING};
+
+/// Vanilla gives up past this, and it keeps a hostile file from blowing the stack
+const MAX_DEPTH: usize = 512;
+
+/// How a stream of NBT is wrapped. level.dat and player files are gzip, region file chunks
+/// are usually zlib and network data isn't compressed at all.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    Gzip,
+    Zlib,
+}
+
+/// Reads one named tag, normally the root compound of a file
+pub fn read(input: &mut impl Read) -> io::Result<(String, Tag)> {
+    let id = read_u8(input)?;
+    if id == END {
+        return Err(invalid("root tag is End"));
+    }
+    let name = read_string(input)?;
+    Ok((name, read_payload(input, id, 0)?))
+}
+
+pub fn write(out: &mut impl Write, name: &str, tag: &Tag) -> io::Result<()> {
+    out.write_all(&[tag.id()])?;
+    write_string(out, name)?;
+    write_payload(out, tag)
+}
+
+/// Like `read`, but works out from the first bytes whether it's gzip, zlib or neither
+pub fn read_compressed(input: impl Read) -> io::Result<(String, Tag, Compression)> {
+    let mut input = BufReader::new(input);
+    let start = input.fill_buf()?;
+    let compression = match start {
+        [0x1F, 0x8B, ..] => Compression::Gzip,
+        // zlib's header is deflate with some window size, and a checksum that makes it divisible by 31
+        [cmf, flg, ..] if cmf & 0x0F == 8 && (*cmf as u16 * 256 + *flg as u16).is_multiple_of(31) => Compression::Zlib,
+        _ => Compression::None,
+    };
+    let (name, tag) = match compression {
+        Compression::None => read(&mut input)?,
+        Compression::Gzip => read(&mut GzDecoder::new(input))?,
+        Compression::Zlib => read(&mut ZlibDecoder::new(input))?,
+    };
+    Ok((name, tag, compression))
+}
+
+pub fn write_compressed(out: impl Write, name: &str, tag: &Tag, compression: Compression) -> io::Result<()> {
+    // encoding into memory first means one big write to the compressor instead of thousands of tiny ones
+    let mut data = Vec::new();
+    write(&mut data, name, tag)?;
+    match compression {
+        Compression::None => {
+            let mut out = out;
+            out.write_all(&data)
+        }
+        Compression::Gzip => {
+            let mut encoder = GzEncoder::new(out, flate2::Compression::default());
+            encoder.write_all(&data)?;
+            encoder.finish().map(drop)
+        }
+        Compression::Zlib => {
+            let mut encoder = ZlibEncoder::new(out, flate2::Compression::default());
+            encoder.write_all(&data)?;
+            encoder.finish().map(drop)
+        }
+    }
+}
+
+fn read_payload(input: &mut impl Read, id: u8, depth: usize) -> io::Result<Tag> {
+    if depth > MAX_DEPTH {
+        return Err(invalid(format!("tags nested more than {MAX_DEPTH} deep")));
+    }
+    Ok(match id {
+        BYTE => Tag::Byte(read_u8(input)? as i8),
+        SHORT => Tag::Short(i16::from_be_bytes(read_array(input)?)),
+        INT => Tag::Int(read_i32(input)?),
+        LONG => Tag::Long(i64::from_be_bytes(read_array(input)?)),
+        FLOAT => Tag::Float(f32::from_be_bytes(read_array(input)?)),
+        DOUBLE => Tag::Double(f64::from_be_bytes(read_array(input)?)),
+        BYTE_ARRAY => {
+            let length = read_length(input)?;
+            let mut bytes = Vec::new();
+            input.by_ref().take(length as u64).read_to_end(&mut bytes)?;
+            if bytes.len() != length {
+                return Err(ErrorKind::UnexpectedEof.into());
+            }
+            Tag::ByteArray(bytes.into_iter().map(|byte| byte as i8).collect())
+        }
+        STRING => Tag::String(read_string(input)?),
+        LIST => {
+            let element = read_u8(input)?;
+            let length = read_length(input)?;
+            if element == END && length > 0 {
+                return Err(invalid("list of End tags isn't empty"));
+            }
+            // lengths come from the file, so don't trust them with an allocation up front
+            let mut tags = Vec::with_capacity(length.min(1024));
+            for _ in 0..length {
+                tags.push(read_payload(input, element, depth + 1)?);
+            }
+            Tag::List(List::from_parts(element, tags))
+        }
+        COMPOUND => {
+            let mut compound = Compound::new();
+            loop {
+                let id = read_u8(input)?;
+                if id == END {
+                    break;
+                }
+                let name = read_string(input)?;
+                let tag = read_payload(input, id, depth + 1)?;
+                // duplicate names would get merged here and change the file, vanilla never writes them
+                compound.insert(name, tag);
+            }
+            Tag::Compound(compound)
+        }
+        INT_ARRAY => {
+            let length = read_length(input)?;
+            let mut values = Vec::with_capacity(length.min(1024));
+            for _ in 0..length {
+                values.push(read_i32(input)?);
+            }
+            Tag::IntArray(values)
+        }
+        id => return Err(invalid(format!("unknown tag type {id}"))),
+    })
+}
+
+fn write_payload(out: &mut impl Write, tag: &Tag) -> io::Result<()> {
+    match tag {
+        Tag::Byte(value) => out.write_all(&value.to_be_bytes()),
+        Tag::Short(value) => out.write_all(&value.to_be_bytes()),
+        Tag::Int(value) => out.write_all(&value.to_be_bytes()),
+        Tag::Long(value) => out.write_all(&value.to_be_bytes()),
+        Tag::Float(value) => out.write_all(&value.to_be_bytes()),
+        Tag::Double(value) => out.write_all(&value.to_be_bytes()),
+        Tag::ByteArray(values) => {
+            write_length(out, values.len())?;
+            out.write_all(&values.iter().map(|&value| value as u8).collect::<Vec<_>>())
+        }
+        Tag::String(value) => write_string(out, value),
+        Tag::List(list) => {
+            out.write_all(&[list.element()])?;
+            write_length(out, list.len())?;
+            list.iter().try_for_each(|tag| write_payload(out, tag))
+        }
+        Tag::Compound(compound) => {
+            for (name, tag) in compound.iter() {
+                out.write_all(&[tag.id()])?;
+                write_string(out, name)?;
+                write_payload(out, tag)?;
+            }
+            out.write_all(&[END])
+        }
+        Tag::IntArray(values) => {
+            write_length(out, values.len())?;
+            out.write_all(&values.iter().flat_map(|value| value.to_be_bytes()).collect::<Vec<_>>())
+        }
+    }
+}
+
+fn read_array<const N: usize>(input: &mut impl Read) -> io::Result<[u8; N]> {
+    let mut bytes = [0; N];
+    input.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn read_u8(input: &mut impl Read) -> io::Result<u8> {
+    Ok(read_array::<1>(input)?[0])
+}
+
+fn read_i32(input: &mut impl Read) -> io::Result<i32> {
+    Ok(i32::from_be_bytes(read_array(input)?))
+}
+
+fn read_length(input: &mut impl Read) -> io::Result<usize> {
+    let length = read_i32(input)?;
+    usize::try_from(length).map_err(|_| invalid(format!("negative length {length}")))
+}
+
+fn write_length(out: &mut impl Write, length: usize) -> io::Result<()> {
+    let length = i32::try_from(length).map_err(|_| invalid("too long for NBT"))?;
+    out.write_all(&length.to_be_bytes())
+}
+
+/// Strings are Java's modified UTF-8 with a u16 byte count in front
+fn read_string(input: &mut impl Read) -> io::Result<String> {
+    let length = u16::from_be_bytes(read_array(input)?) as usize;
+    let mut bytes = vec![0; length];
+    input.read_exact(&mut bytes)?;
+    decode_modified_utf8(&bytes)
+}
+
+fn write_string(out: &mut impl Write, value: &str) -> io::Result<()> {
+    let bytes = encode_modified_utf8(value);
+    let length = u16::try_from(bytes.len()).map_err(|_| invalid(format!("string of {} bytes is too long for NBT", bytes.len())))?;
+    out.write_all(&length.to_be_bytes())?;
+    out.write_all(&bytes)
+}
+
+/// UTF-8 except that NUL takes two bytes so there's never a zero byte, and anything outside the
+/// BMP goes as its two UTF-16 surrogates, three bytes each
+pub fn encode_modified_utf8(value: &str) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(value.len());
+    for unit in value.encode_utf16() {
+        match unit {
+            0x0001..=0x007F => bytes.push(unit as u8),
+            0x0000 | 0x0080..=0x07FF => bytes.extend([0xC0 | (unit >> 6) as u8, 0x80 | (unit & 0x3F) as u8]),
+            _ => bytes.extend([0xE0 | (unit >> 12) as u8, 0x80 | (unit >> 6 & 0x3F) as u8, 0x80 | (unit & 0x3F) as u8]),
+        }
+    }
+    bytes
+}
+
+pub fn decode_modified_utf8(bytes: &[u8]) -> io::Result<String> {
+    let mut units = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    let continuation = |i: usize| match bytes.get(i) {
+        Some(&byte) if byte & 0xC0 == 0x80 => Ok((byte & 0x3F) as u16),
+        _ => Err(invalid("truncated or malformed modified UTF-8")),
+    };
+    while i < bytes.len() {
+        let byte = bytes[i];
+        match byte {
+            0x01..=0x7F => {
+                units.push(byte as u16);
+                i += 1;
+            }
+            0xC0..=0xDF => {
+                units.push(((byte & 0x1F) as u16) << 6 | continuation(i + 1)?);
+                i += 2;
+            }
+            0xE0..=0xEF => {
+                units.push(((byte & 0x0F) as u16) << 12 | continuation(i + 1)? << 6 | continuation(i + 2)?);
+                i += 3;
+            }
+            _ => return Err(invalid(format!("byte {byte:#04x} can't start a modified UTF-8 character"))),
+        }
+    }
+    String::from_utf16(&units).map_err(|_| invalid("unpaired surrogate in string"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// One of every tag type, nested
+    fn every_tag() -> Tag {
+        let mut ints = List::new();
+        ints.push(1).unwrap();
+        ints.push(-2).unwrap();
+        let mut nested = Compound::new();
+        nested.insert("name", "nested");
+        Tag::Compound(Compound::from_iter([
+            ("byte", Tag::Byte(-8)),
+            ("short", Tag::Short(-1234)),
+            ("int", Tag::Int(123_456_789)),
+            ("long", Tag::Long(-9_000_000_000_000)),
+            ("float", Tag::Float(1.5)),
+            ("double", Tag::Double(-0.1)),
+            ("byte array", Tag::ByteArray(vec![0, -1, 127, -128])),
+            ("string", Tag::String("hello".to_string())),
+            ("list", Tag::List(ints)),
+            ("compound", Tag::Compound(nested)),
+            ("int array", Tag::IntArray(vec![i32::MIN, 0, i32::MAX])),
+        ]))
+    }
+
+    fn round_trip(tag: &Tag) -> Tag {
+        let mut data = Vec::new();
+        write(&mut data, "root", tag).unwrap();
+        let (name, read_back) = read(&mut &data[..]).unwrap();
+        assert_eq!(name, "root");
+        read_back
+    }
+
+    #[test]
+    fn every_tag_type_round_trips() {
+        let tag = every_tag();
+        assert_eq!(round_trip(&tag), tag);
+        let ids: Vec<u8> = tag.as_compound().unwrap().iter().map(|(_, tag)| tag.id()).collect();
+        assert_eq!(ids, [BYTE, SHORT, INT, LONG, FLOAT, DOUBLE, BYTE_ARRAY, STRING, LIST, COMPOUND, INT_ARRAY]);
+    }
+
+    #[test]
+    fn end_is_written_after_every_compound() {
+        let mut data = Vec::new();
+        write(&mut data, "", &Tag::Compound(Compound::new())).unwrap();
+        assert_eq!(data, [COMPOUND, 0, 0, END]);
+    }
+
+    #[test]
+    fn empty_lists_keep_their_element_type() {
+        for element in [END, BYTE, STRING, LIST, COMPOUND, INT_ARRAY] {
+            let tag = Tag::List(List::of(element));
+            assert_eq!(round_trip(&tag).as_list().unwrap().element(), element);
+        }
+    }
+
+    #[test]
+    fn strings_are_modified_utf8() {
+        // NUL is two bytes, and a non-BMP character is two three byte surrogates
+        assert_eq!(encode_modified_utf8("a\0b"), [b'a', 0xC0, 0x80, b'b']);
+        assert_eq!(encode_modified_utf8("😀"), [0xED, 0xA0, 0xBD, 0xED, 0xB8, 0x80]);
+        assert_eq!(encode_modified_utf8("é"), "é".as_bytes());
+        for text in ["", "plain", "a\0b", "😀 and ☃", "\u{10FFFF}"] {
+            assert_eq!(decode_modified_utf8(&encode_modified_utf8(text)).unwrap(), text);
+            assert_eq!(round_trip(&Tag::String(text.to_string())), Tag::String(text.to_string()));
+        }
+        // plain UTF-8 for a non-BMP character isn't modified UTF-8
+        assert!(decode_modified_utf8("😀".as_bytes()).is_err());
+        assert!(decode_modified_utf8(&[0]).is_err());
+    }
+
+    #[test]
+    fn compression_is_detected() {
+        let tag = every_tag();
+        for compression in [Compression::None, Compression::Gzip, Compression::Zlib] {
+            let mut data = Vec::new();
+            write_compressed(&mut data, "level", &tag, compression).unwrap();
+            assert_eq!(read_compressed(&data[..]).unwrap(), ("level".to_string(), tag.clone(), compression));
+        }
+    }
+
+    #[test]
+    fn broken_input_is_an_error() {
+        let mut data = Vec::new();
+        write(&mut data, "", &every_tag()).unwrap();
+        for length in 0..data.len() {
+            assert!(read(&mut &data[..length]).is_err());
+        }
+        assert!(read(&mut &[END][..]).is_err());
+        assert!(read(&mut &[12, 0, 0][..]).is_err());
+        // a list of 5 End tags
+        assert!(read(&mut &[LIST, 0, 0, END, 0, 0, 0, 5][..]).is_err());
+    }
+
+    #[test]
+    fn oversized_strings_fail_to_write() {
+        let tag = Tag::String("x".repeat(65536));
+        assert_eq!(write(&mut Vec::new(), "", &tag).unwrap_err().kind(), ErrorKind::InvalidData);
+        assert!(write(&mut Vec::new(), "", &Tag::String("x".repeat(65535))).is_ok());
+    }
+}
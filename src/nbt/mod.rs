@@ -0,0 +1,388 @@
+//! Named Binary Tag, the format every vanilla save file is built from and what item data goes
+//! over the wire in.
+
+use std::fmt;
+
+pub mod convert;
+pub mod io;
+
+pub use convert::{from_tag, to_tag, Error};
+pub use io::{read, read_compressed, write, write_compressed, Compression};
+
+pub const END: u8 = 0;
+pub const BYTE: u8 = 1;
+pub const SHORT: u8 = 2;
+pub const INT: u8 = 3;
+pub const LONG: u8 = 4;
+pub const FLOAT: u8 = 5;
+pub const DOUBLE: u8 = 6;
+pub const BYTE_ARRAY: u8 = 7;
+pub const STRING: u8 = 8;
+pub const LIST: u8 = 9;
+pub const COMPOUND: u8 = 10;
+pub const INT_ARRAY: u8 = 11;
+
+/// Any tag but End, which only ever shows up in the encoding.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Tag {
+    Byte(i8),
+    Short(i16),
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    ByteArray(Vec<i8>),
+    String(String),
+    List(List),
+    Compound(Compound),
+    IntArray(Vec<i32>),
+}
+
+impl Tag {
+    pub fn id(&self) -> u8 {
+        match self {
+            Tag::Byte(_) => BYTE,
+            Tag::Short(_) => SHORT,
+            Tag::Int(_) => INT,
+            Tag::Long(_) => LONG,
+            Tag::Float(_) => FLOAT,
+            Tag::Double(_) => DOUBLE,
+            Tag::ByteArray(_) => BYTE_ARRAY,
+            Tag::String(_) => STRING,
+            Tag::List(_) => LIST,
+            Tag::Compound(_) => COMPOUND,
+            Tag::IntArray(_) => INT_ARRAY,
+        }
+    }
+
+    /// Any of the integer tags widened, vanilla is loose about which size it writes flags with
+    pub fn as_i64(&self) -> Option<i64> {
+        match *self {
+            Tag::Byte(value) => Some(value as i64),
+            Tag::Short(value) => Some(value as i64),
+            Tag::Int(value) => Some(value as i64),
+            Tag::Long(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match *self {
+            Tag::Float(value) => Some(value as f64),
+            Tag::Double(value) => Some(value),
+            _ => self.as_i64().map(|value| value as f64),
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Tag::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_list(&self) -> Option<&List> {
+        match self {
+            Tag::List(list) => Some(list),
+            _ => None,
+        }
+    }
+
+    pub fn as_compound(&self) -> Option<&Compound> {
+        match self {
+            Tag::Compound(compound) => Some(compound),
+            _ => None,
+        }
+    }
+
+    pub fn as_compound_mut(&mut self) -> Option<&mut Compound> {
+        match self {
+            Tag::Compound(compound) => Some(compound),
+            _ => None,
+        }
+    }
+}
+
+impl From<Compound> for Tag {
+    fn from(compound: Compound) -> Self {
+        Tag::Compound(compound)
+    }
+}
+
+impl From<List> for Tag {
+    fn from(list: List) -> Self {
+        Tag::List(list)
+    }
+}
+
+impl From<&str> for Tag {
+    fn from(value: &str) -> Self {
+        Tag::String(value.to_string())
+    }
+}
+
+macro_rules! tag_from {
+    ($($type:ty => $variant:ident),*) => {
+        $(impl From<$type> for Tag {
+            fn from(value: $type) -> Self {
+                Tag::$variant(value)
+            }
+        })*
+    };
+}
+
+tag_from!(i8 => Byte, i16 => Short, i32 => Int, i64 => Long, f32 => Float, f64 => Double,
+    Vec<i8> => ByteArray, String => String, Vec<i32> => IntArray);
+
+impl From<bool> for Tag {
+    fn from(value: bool) -> Self {
+        Tag::Byte(value as i8)
+    }
+}
+
+/// Tags all of one type. The element type is kept even when it's empty, since vanilla files
+/// have empty lists of all sorts and they should come back out the same.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct List {
+    element: u8,
+    tags: Vec<Tag>,
+}
+
+impl List {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// An empty list that remembers it holds `element` tags
+    pub fn of(element: u8) -> Self {
+        Self { element, tags: Vec::new() }
+    }
+
+    /// Element type, End until something's been added to a list made with `new`
+    pub fn element(&self) -> u8 {
+        self.element
+    }
+
+    /// Hands the tag back if it isn't the same type as what's already in here
+    pub fn push(&mut self, tag: impl Into<Tag>) -> Result<(), Tag> {
+        let tag = tag.into();
+        if self.tags.is_empty() && self.element == END {
+            self.element = tag.id();
+        } else if tag.id() != self.element {
+            return Err(tag);
+        }
+        self.tags.push(tag);
+        Ok(())
+    }
+
+    pub fn get(&self, index: usize) -> Option<&Tag> {
+        self.tags.get(index)
+    }
+
+    pub fn len(&self) -> usize {
+        self.tags.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_empty()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Tag> {
+        self.tags.iter()
+    }
+
+    /// Unchecked, for the reader which already knows every tag has the element type
+    fn from_parts(element: u8, tags: Vec<Tag>) -> Self {
+        Self { element, tags }
+    }
+}
+
+impl<'a> IntoIterator for &'a List {
+    type Item = &'a Tag;
+    type IntoIter = std::slice::Iter<'a, Tag>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.tags.iter()
+    }
+}
+
+impl IntoIterator for List {
+    type Item = Tag;
+    type IntoIter = std::vec::IntoIter<Tag>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.tags.into_iter()
+    }
+}
+
+/// Named tags in the order they were read or inserted, so files get written back exactly the way
+/// they came in. Compounds are small enough that looking names up in a list is fine.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Compound {
+    entries: Vec<(String, Tag)>,
+}
+
+impl Compound {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Tag> {
+        self.entries.iter().find(|(key, _)| key == name).map(|(_, tag)| tag)
+    }
+
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut Tag> {
+        self.entries.iter_mut().find(|(key, _)| key == name).map(|(_, tag)| tag)
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+
+    /// Replaces an existing tag in place, otherwise adds it on the end. Returns what was there.
+    pub fn insert(&mut self, name: impl Into<String>, tag: impl Into<Tag>) -> Option<Tag> {
+        let (name, tag) = (name.into(), tag.into());
+        match self.get_mut(&name) {
+            Some(existing) => Some(std::mem::replace(existing, tag)),
+            None => {
+                self.entries.push((name, tag));
+                None
+            }
+        }
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<Tag> {
+        let index = self.entries.iter().position(|(key, _)| key == name)?;
+        Some(self.entries.remove(index).1)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Tag)> {
+        self.entries.iter().map(|(name, tag)| (name.as_str(), tag))
+    }
+
+    pub fn get_i8(&self, name: &str) -> Option<i8> {
+        match self.get(name)? {
+            Tag::Byte(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn get_bool(&self, name: &str) -> Option<bool> {
+        self.get(name)?.as_i64().map(|value| value != 0)
+    }
+
+    pub fn get_i16(&self, name: &str) -> Option<i16> {
+        match self.get(name)? {
+            Tag::Short(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn get_i32(&self, name: &str) -> Option<i32> {
+        match self.get(name)? {
+            Tag::Int(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn get_i64(&self, name: &str) -> Option<i64> {
+        match self.get(name)? {
+            Tag::Long(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn get_f32(&self, name: &str) -> Option<f32> {
+        match self.get(name)? {
+            Tag::Float(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn get_f64(&self, name: &str) -> Option<f64> {
+        match self.get(name)? {
+            Tag::Double(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn get_byte_array(&self, name: &str) -> Option<&[i8]> {
+        match self.get(name)? {
+            Tag::ByteArray(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn get_str(&self, name: &str) -> Option<&str> {
+        self.get(name)?.as_str()
+    }
+
+    pub fn get_list(&self, name: &str) -> Option<&List> {
+        self.get(name)?.as_list()
+    }
+
+    pub fn get_compound(&self, name: &str) -> Option<&Compound> {
+        self.get(name)?.as_compound()
+    }
+
+    pub fn get_compound_mut(&mut self, name: &str) -> Option<&mut Compound> {
+        self.get_mut(name)?.as_compound_mut()
+    }
+
+    pub fn get_int_array(&self, name: &str) -> Option<&[i32]> {
+        match self.get(name)? {
+            Tag::IntArray(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+impl<N: Into<String>, T: Into<Tag>> FromIterator<(N, T)> for Compound {
+    fn from_iter<I: IntoIterator<Item = (N, T)>>(iter: I) -> Self {
+        let mut compound = Compound::new();
+        for (name, tag) in iter {
+            compound.insert(name, tag);
+        }
+        compound
+    }
+}
+
+/// Roughly the SNBT `/give` takes, handy when debugging
+impl fmt::Display for Tag {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Tag::Byte(value) => write!(f, "{value}b"),
+            Tag::Short(value) => write!(f, "{value}s"),
+            Tag::Int(value) => write!(f, "{value}"),
+            Tag::Long(value) => write!(f, "{value}L"),
+            Tag::Float(value) => write!(f, "{value}f"),
+            Tag::Double(value) => write!(f, "{value}d"),
+            Tag::ByteArray(values) => write!(f, "[B;{}]", values.iter().map(|value| format!("{value}b")).collect::<Vec<_>>().join(",")),
+            Tag::String(value) => write!(f, "{value:?}"),
+            Tag::List(list) => write!(f, "[{}]", list.iter().map(Tag::to_string).collect::<Vec<_>>().join(",")),
+            Tag::Compound(compound) => write!(f, "{compound}"),
+            Tag::IntArray(values) => write!(f, "[I;{}]", values.iter().map(i32::to_string).collect::<Vec<_>>().join(",")),
+        }
+    }
+}
+
+impl fmt::Display for Compound {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{{")?;
+        for (i, (name, tag)) in self.iter().enumerate() {
+            if i > 0 {
+                write!(f, ",")?;
+            }
+            write!(f, "{name}:{tag}")?;
+        }
+        write!(f, "}}")
+    }
+}
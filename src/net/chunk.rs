@@ -0,0 +1,153 @@
+use std::io;
+
+use crate::invalid;
+use crate::net::codec::{Packet, PacketReader, PacketWriter};
+use crate::net::packet::play;
+use crate::world::{BlockState, Chunk, ChunkPos, LightType, SectionLight, World, CHUNK_WIDTH, SECTION_COUNT, SECTION_HEIGHT, SECTION_VOLUME};
+
+/// Block light and sky light are a nibble per block
+const LIGHT_BYTES: usize = SECTION_VOLUME / 2;
+const BIOME_BYTES: usize = CHUNK_WIDTH * CHUNK_WIDTH;
+
+/// How many bytes a column with the sections in `mask` takes up in Chunk Data and Map Chunk Bulk
+pub fn column_size(mask: u16, sky_light: bool, ground_up: bool) -> usize {
+    let sections = mask.count_ones() as usize;
+    let per_section = SECTION_VOLUME * 2 + LIGHT_BYTES + if sky_light { LIGHT_BYTES } else { 0 };
+    sections * per_section + if ground_up { BIOME_BYTES } else { 0 }
+}
+
+/// Reads one column into the world. Every section's blocks come first as little endian
+/// `id << 4 | meta`, then all their block light, sky light outside the nether and end, and the
+/// biomes last when the column is ground up continuous. A ground up column replaces the whole
+/// chunk, otherwise only the sections in `mask` get replaced.
+pub fn read_column(world: &mut World, pos: ChunkPos, mask: u16, sky_light: bool, ground_up: bool, data: &mut PacketReader) -> io::Result<()> {
+    let sections: Vec<usize> = (0..SECTION_COUNT).filter(|y| mask & 1 << y != 0).collect();
+    let blocks = data.bytes(sections.len() * SECTION_VOLUME * 2)?;
+    let block_light = data.bytes(sections.len() * LIGHT_BYTES)?;
+    let sky_light = if sky_light { Some(data.bytes(sections.len() * LIGHT_BYTES)?) } else { None };
+    let biomes = if ground_up { Some(data.bytes(BIOME_BYTES)?) } else { None };
+
+    if ground_up {
+        world.load_chunk(Chunk::new(pos));
+    }
+    // vanilla drops partial updates for chunks it doesn't have too
+    let Some(chunk) = world.chunk_mut(pos) else {
+        return Ok(());
+    };
+    for (section_y, blocks) in sections.iter().zip(blocks.chunks_exact(SECTION_VOLUME * 2)) {
+        for (i, state) in blocks.chunks_exact(2).enumerate() {
+            let (x, y, z) = (i & 15, i >> 8, i >> 4 & 15);
+            chunk.set_block(x, section_y * SECTION_HEIGHT + y, z, BlockState(u16::from_le_bytes([state[0], state[1]])));
+        }
+    }
+    for (i, &section_y) in sections.iter().enumerate() {
+        let range = i * LIGHT_BYTES..(i + 1) * LIGHT_BYTES;
+        *chunk.section_light_mut(LightType::Block, section_y) = SectionLight::from_bytes(&block_light[range.clone()]).unwrap();
+        // the nether and end have no sky, so no sky light either
+        *chunk.section_light_mut(LightType::Sky, section_y) = match sky_light {
+            Some(sky_light) => SectionLight::from_bytes(&sky_light[range]).unwrap(),
+            None => SectionLight::Uniform(0),
+        };
+    }
+    chunk.light_populated = true;
+    if let Some(biomes) = biomes {
+        chunk.biomes.copy_from_slice(biomes);
+    }
+    Ok(())
+}
+
+/// The body of a Chunk Data packet: position, ground up flag, section mask and the column itself.
+/// Ground up with no sections means the chunk should be unloaded, which is what the second value says.
+pub fn read_chunk_data(world: &mut World, sky_light: bool, packet: &mut PacketReader) -> io::Result<(ChunkPos, bool)> {
+    let pos = ChunkPos::new(packet.i32()?, packet.i32()?);
+    let ground_up = packet.bool()?;
+    let mask = packet.u16()?;
+    let size = packet.varint()?;
+    let size = usize::try_from(size).map_err(|_| invalid(format!("chunk data size {size} is negative")))?;
+    let mut data = PacketReader::new(packet.bytes(size)?);
+
+    if ground_up && mask == 0 {
+        world.unload_chunk(pos);
+        return Ok((pos, true));
+    }
+    read_column(world, pos, mask, sky_light, ground_up, &mut data)?;
+    Ok((pos, false))
+}
+
+/// The body of a Map Chunk Bulk packet, a batch of ground up columns with all their headers up front
+pub fn read_map_chunk_bulk(world: &mut World, packet: &mut PacketReader) -> io::Result<Vec<ChunkPos>> {
+    let sky_light = packet.bool()?;
+    let count = packet.varint()?;
+    let count = usize::try_from(count).map_err(|_| invalid(format!("chunk count {count} is negative")))?;
+
+    let mut headers = Vec::with_capacity(count.min(1024));
+    for _ in 0..count {
+        headers.push((ChunkPos::new(packet.i32()?, packet.i32()?), packet.u16()?));
+    }
+    for &(pos, mask) in &headers {
+        read_column(world, pos, mask, sky_light, true, packet)?;
+    }
+    Ok(headers.into_iter().map(|(pos, _)| pos).collect())
+}
+
+/// The other way round from `read_column`, every section that isn't empty and the biomes
+pub fn write_column(chunk: &Chunk, sky_light: bool) -> (u16, Vec<u8>) {
+    let mask = chunk.sections().fold(0u16, |mask, (y, _)| mask | 1 << y);
+    let mut data = Vec::with_capacity(column_size(mask, sky_light, true));
+    for (_, section) in chunk.sections() {
+        data.extend(section.blocks().iter().flat_map(|state| state.0.to_le_bytes()));
+    }
+    for (y, _) in chunk.sections() {
+        data.extend(chunk.section_light(LightType::Block, y).to_bytes());
+    }
+    if sky_light {
+        for (y, _) in chunk.sections() {
+            data.extend(chunk.section_light(LightType::Sky, y).to_bytes());
+        }
+    }
+    data.extend_from_slice(&chunk.biomes);
+    (mask, data)
+}
+
+/// A ground up Chunk Data packet with the whole column
+pub fn chunk_data_packet(chunk: &Chunk, sky_light: bool) -> Packet {
+    let (mask, data) = write_column(chunk, sky_light);
+    PacketWriter::new()
+        .i32(chunk.pos.x)
+        .i32(chunk.pos.z)
+        .bool(true)
+        .u16(mask)
+        .varint(data.len() as i32)
+        .bytes(&data)
+        .finish(play::clientbound::CHUNK_DATA)
+}
+
+/// Several ground up columns in one Map Chunk Bulk packet, all with sky light or all without
+pub fn map_chunk_bulk_packet(chunks: &[&Chunk], sky_light: bool) -> Packet {
+    let columns: Vec<(ChunkPos, u16, Vec<u8>)> = chunks.iter()
+        .map(|chunk| {
+            let (mask, data) = write_column(chunk, sky_light);
+            (chunk.pos, mask, data)
+        })
+        .collect();
+    let mut packet = PacketWriter::new().bool(sky_light).varint(columns.len() as i32);
+    for (pos, mask, _) in &columns {
+        packet = packet.i32(pos.x).i32(pos.z).u16(*mask);
+    }
+    for (_, _, data) in &columns {
+        packet = packet.bytes(data);
+    }
+    packet.finish(play::clientbound::MAP_CHUNK_BULK)
+}
+
+/// What vanilla sends to make a client drop a chunk, ground up with no sections and empty biomes
+pub fn unload_chunk_packet(pos: ChunkPos) -> Packet {
+    PacketWriter::new()
+        .i32(pos.x)
+        .i32(pos.z)
+        .bool(true)
+        .u16(0)
+        .varint(BIOME_BYTES as i32)
+        .bytes(&[0; BIOME_BYTES])
+        .finish(play::clientbound::CHUNK_DATA)
+}
@@ -0,0 +1,143 @@
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::TcpStream;
+
+use crate::invalid;
+use crate::net::chunk::{read_chunk_data, read_map_chunk_bulk};
+use crate::net::codec::{PacketWriter};
+use crate::net::connection::Connection;
+use crate::net::packet::{handshake, login, play};
+use crate::net::{NextState, PROTOCOL_VERSION};
+use crate::world::{BlockPos, BlockState, ChunkPos, World};
+
+/// What happened in play that whoever's driving the client might care about.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ClientEvent {
+    JoinGame { entity_id: i32, gamemode: u8, dimension: i8 },
+    /// The server moved us. Bits of `relative` say which fields are offsets rather than absolute,
+    /// x, y, z, yaw then pitch from the lowest bit up.
+    Teleport { x: f64, y: f64, z: f64, yaw: f32, pitch: f32, relative: u8 },
+    ChunksLoaded(Vec<ChunkPos>),
+    ChunkUnloaded(ChunkPos),
+    BlocksChanged(Vec<BlockPos>),
+    /// Raw chat JSON
+    Chat(String),
+    /// The connection is done after this, with the server's reason as chat JSON
+    Disconnected(String),
+    KeepAlive,
+    /// Anything we don't decode
+    Ignored(i32),
+}
+
+/// A logged in, offline mode connection to a 1.8.9 server.
+pub struct Client<R: Read, W: Write> {
+    connection: Connection<R, W>,
+    pub username: String,
+    pub uuid: String,
+    /// Only the overworld has sky light in chunk packets
+    pub dimension: i8,
+}
+
+impl Client<TcpStream, TcpStream> {
+    pub fn connect(host: &str, port: u16, username: &str) -> io::Result<Self> {
+        let connection = Connection::tcp(TcpStream::connect((host, port))?)?;
+        Self::login(connection, host, port, username)
+    }
+}
+
+impl<R: Read, W: Write> Client<R, W> {
+    /// Handshakes into the login state and logs in without authentication, following the
+    /// server's compression threshold if it sends one
+    pub fn login(mut connection: Connection<R, W>, host: &str, port: u16, username: &str) -> io::Result<Self> {
+        connection.send(&PacketWriter::new()
+            .varint(PROTOCOL_VERSION)
+            .string(host)
+            .u16(port)
+            .varint(NextState::Login as i32)
+            .finish(handshake::HANDSHAKE))?;
+        connection.send(&PacketWriter::new().string(username).finish(login::serverbound::LOGIN_START))?;
+
+        loop {
+            let packet = connection.receive()?;
+            let mut reader = packet.reader();
+            match packet.id {
+                login::clientbound::DISCONNECT => {
+                    return Err(io::Error::new(ErrorKind::ConnectionRefused, format!("kicked while logging in: {}", reader.string()?)));
+                }
+                login::clientbound::ENCRYPTION_REQUEST => {
+                    return Err(io::Error::new(ErrorKind::Unsupported, "server is in online mode, only offline mode is supported"));
+                }
+                login::clientbound::SET_COMPRESSION => connection.set_compression(reader.varint()?),
+                login::clientbound::LOGIN_SUCCESS => {
+                    let uuid = reader.string()?;
+                    let username = reader.string()?;
+                    return Ok(Self { connection, username, uuid, dimension: 0 });
+                }
+                id => return Err(invalid(format!("unexpected packet {id:#04x} while logging in"))),
+            }
+        }
+    }
+
+    /// Blocks until the next packet arrives and handles it. Keep alives get answered and chunk
+    /// and block updates go straight into `world`.
+    pub fn poll(&mut self, world: &mut World) -> io::Result<ClientEvent> {
+        let packet = self.connection.receive()?;
+        let mut reader = packet.reader();
+        Ok(match packet.id {
+            play::clientbound::KEEP_ALIVE => {
+                let id = reader.varint()?;
+                self.connection.send(&PacketWriter::new().varint(id).finish(play::serverbound::KEEP_ALIVE))?;
+                ClientEvent::KeepAlive
+            }
+            play::clientbound::JOIN_GAME => {
+                let entity_id = reader.i32()?;
+                let gamemode = reader.u8()?;
+                self.dimension = reader.i8()?;
+                ClientEvent::JoinGame { entity_id, gamemode, dimension: self.dimension }
+            }
+            play::clientbound::CHAT_MESSAGE => ClientEvent::Chat(reader.string()?),
+            play::clientbound::PLAYER_POSITION_AND_LOOK => ClientEvent::Teleport {
+                x: reader.f64()?,
+                y: reader.f64()?,
+                z: reader.f64()?,
+                yaw: reader.f32()?,
+                pitch: reader.f32()?,
+                relative: reader.u8()?,
+            },
+            play::clientbound::CHUNK_DATA => match read_chunk_data(world, self.dimension == 0, &mut reader)? {
+                (pos, true) => ClientEvent::ChunkUnloaded(pos),
+                (pos, false) => ClientEvent::ChunksLoaded(vec![pos]),
+            },
+            play::clientbound::MAP_CHUNK_BULK => ClientEvent::ChunksLoaded(read_map_chunk_bulk(world, &mut reader)?),
+            play::clientbound::BLOCK_CHANGE => {
+                let pos = reader.position()?;
+                world.set_block(pos, BlockState(reader.varint()? as u16));
+                ClientEvent::BlocksChanged(vec![pos])
+            }
+            play::clientbound::MULTI_BLOCK_CHANGE => {
+                let chunk = ChunkPos::new(reader.i32()?, reader.i32()?);
+                let count = reader.varint()?;
+                let mut changed = Vec::new();
+                for _ in 0..count {
+                    // horizontal position packed into a byte, x in the high nibble
+                    let xz = reader.u8()?;
+                    let y = reader.u8()?;
+                    let origin = chunk.origin();
+                    let pos = BlockPos::new(origin.x + (xz >> 4) as i32, y as i32, origin.z + (xz & 15) as i32);
+                    world.set_block(pos, BlockState(reader.varint()? as u16));
+                    changed.push(pos);
+                }
+                ClientEvent::BlocksChanged(changed)
+            }
+            play::clientbound::SET_COMPRESSION => {
+                self.connection.set_compression(reader.varint()?);
+                ClientEvent::Ignored(packet.id)
+            }
+            play::clientbound::DISCONNECT => ClientEvent::Disconnected(reader.string()?),
+            id => ClientEvent::Ignored(id),
+        })
+    }
+
+    pub fn send_chat(&mut self, message: &str) -> io::Result<()> {
+        self.connection.send(&PacketWriter::new().string(message).finish(play::serverbound::CHAT_MESSAGE))
+    }
+}
@@ -0,0 +1,337 @@
+use std::io::{self, ErrorKind, Read, Write};
+
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+
+use crate::invalid;
+use crate::nbt::{self, Tag};
+use crate::world::BlockPos;
+
+/// Vanilla refuses anything bigger than this
+const MAX_PACKET_LENGTH: usize = 1 << 21;
+const MAX_STRING_LENGTH: usize = 32767;
+
+/// One packet's id and body, after framing and compression have been taken off.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Packet {
+    pub id: i32,
+    pub data: Vec<u8>,
+}
+
+impl Packet {
+    pub fn new(id: i32, data: Vec<u8>) -> Self {
+        Self { id, data }
+    }
+
+    pub fn reader(&self) -> PacketReader<'_> {
+        PacketReader::new(&self.data)
+    }
+}
+
+pub fn read_varint(input: &mut impl Read) -> io::Result<i32> {
+    let mut value = 0u32;
+    for i in 0..5 {
+        let mut byte = [0];
+        input.read_exact(&mut byte)?;
+        value |= ((byte[0] & 0x7F) as u32) << (7 * i);
+        if byte[0] & 0x80 == 0 {
+            return Ok(value as i32);
+        }
+    }
+    Err(invalid("VarInt is longer than 5 bytes"))
+}
+
+pub fn write_varint(out: &mut Vec<u8>, value: i32) {
+    let mut value = value as u32;
+    loop {
+        if value & !0x7F == 0 {
+            out.push(value as u8);
+            return;
+        }
+        out.push((value & 0x7F) as u8 | 0x80);
+        value >>= 7;
+    }
+}
+
+pub fn varint_len(value: i32) -> usize {
+    let bits = 32 - (value as u32).leading_zeros();
+    (bits.max(1) as usize).div_ceil(7)
+}
+
+/// Reads one length prefixed frame. Once compression is on, every frame also carries its
+/// uncompressed length, with 0 meaning it went out as is because it was under the threshold.
+pub fn read_packet(input: &mut impl Read, compression: Option<usize>) -> io::Result<Packet> {
+    let length = read_varint(input)?;
+    if length < 0 || length as usize > MAX_PACKET_LENGTH {
+        return Err(invalid(format!("packet length {length} out of range")));
+    }
+    let mut frame = vec![0; length as usize];
+    input.read_exact(&mut frame)?;
+
+    let body = match compression {
+        None => frame,
+        Some(_) => {
+            let mut frame = &frame[..];
+            let data_length = read_varint(&mut frame)?;
+            if data_length == 0 {
+                frame.to_vec()
+            } else {
+                if data_length < 0 || data_length as usize > MAX_PACKET_LENGTH {
+                    return Err(invalid(format!("uncompressed length {data_length} out of range")));
+                }
+                let mut body = Vec::with_capacity(data_length as usize);
+                // a byte past what it claims is enough to tell it lied, don't inflate a zip bomb any further
+                ZlibDecoder::new(frame).take(data_length as u64 + 1).read_to_end(&mut body)?;
+                if body.len() != data_length as usize {
+                    return Err(invalid(format!("packet inflated to {} bytes instead of {data_length}", body.len())));
+                }
+                body
+            }
+        }
+    };
+
+    let mut body = &body[..];
+    let id = read_varint(&mut body)?;
+    Ok(Packet::new(id, body.to_vec()))
+}
+
+pub fn write_packet(out: &mut impl Write, packet: &Packet, compression: Option<usize>) -> io::Result<()> {
+    let mut body = Vec::with_capacity(packet.data.len() + 5);
+    write_varint(&mut body, packet.id);
+    body.extend_from_slice(&packet.data);
+
+    let mut frame = Vec::with_capacity(body.len() + 10);
+    match compression {
+        None => {
+            write_varint(&mut frame, body.len() as i32);
+            frame.extend_from_slice(&body);
+        }
+        Some(threshold) if body.len() < threshold => {
+            write_varint(&mut frame, body.len() as i32 + 1);
+            frame.push(0);
+            frame.extend_from_slice(&body);
+        }
+        Some(_) => {
+            let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(&body)?;
+            let compressed = encoder.finish()?;
+            write_varint(&mut frame, (varint_len(body.len() as i32) + compressed.len()) as i32);
+            write_varint(&mut frame, body.len() as i32);
+            frame.extend_from_slice(&compressed);
+        }
+    }
+    out.write_all(&frame)
+}
+
+/// Reads the big endian fields of a packet body, running off the end is an `UnexpectedEof` error.
+pub struct PacketReader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> PacketReader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn bytes(&mut self, count: usize) -> io::Result<&'a [u8]> {
+        if count > self.data.len() {
+            return Err(io::Error::new(ErrorKind::UnexpectedEof, format!("wanted {count} more bytes, packet only has {}", self.data.len())));
+        }
+        let (bytes, rest) = self.data.split_at(count);
+        self.data = rest;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> io::Result<[u8; N]> {
+        Ok(self.bytes(N)?.try_into().unwrap())
+    }
+
+    pub fn u8(&mut self) -> io::Result<u8> {
+        Ok(self.array::<1>()?[0])
+    }
+
+    pub fn i8(&mut self) -> io::Result<i8> {
+        Ok(self.u8()? as i8)
+    }
+
+    pub fn bool(&mut self) -> io::Result<bool> {
+        Ok(self.u8()? != 0)
+    }
+
+    pub fn u16(&mut self) -> io::Result<u16> {
+        Ok(u16::from_be_bytes(self.array()?))
+    }
+
+    pub fn i16(&mut self) -> io::Result<i16> {
+        Ok(i16::from_be_bytes(self.array()?))
+    }
+
+    pub fn i32(&mut self) -> io::Result<i32> {
+        Ok(i32::from_be_bytes(self.array()?))
+    }
+
+    pub fn i64(&mut self) -> io::Result<i64> {
+        Ok(i64::from_be_bytes(self.array()?))
+    }
+
+    pub fn f32(&mut self) -> io::Result<f32> {
+        Ok(f32::from_be_bytes(self.array()?))
+    }
+
+    pub fn f64(&mut self) -> io::Result<f64> {
+        Ok(f64::from_be_bytes(self.array()?))
+    }
+
+    pub fn varint(&mut self) -> io::Result<i32> {
+        read_varint(&mut self.data)
+    }
+
+    pub fn string(&mut self) -> io::Result<String> {
+        let length = self.varint()?;
+        if length < 0 || length as usize > MAX_STRING_LENGTH * 4 {
+            return Err(invalid(format!("string length {length} out of range")));
+        }
+        String::from_utf8(self.bytes(length as usize)?.to_vec()).map_err(|_| invalid("string isn't valid UTF-8"))
+    }
+
+    pub fn position(&mut self) -> io::Result<BlockPos> {
+        let packed = self.i64()?;
+        // x and z are 26 bit and y 12 bit two's complement, shifting left then right sign extends them
+        Ok(BlockPos::new((packed >> 38) as i32, (packed << 26 >> 52) as i32, (packed << 38 >> 38) as i32))
+    }
+
+    /// Item data in slots, an uncompressed named tag or a lone End byte when there isn't any
+    pub fn nbt(&mut self) -> io::Result<Option<Tag>> {
+        if self.data.first() == Some(&nbt::END) {
+            self.data = &self.data[1..];
+            return Ok(None);
+        }
+        nbt::read(&mut self.data).map(|(_, tag)| Some(tag))
+    }
+}
+
+/// Builds a packet body field by field.
+#[derive(Default)]
+pub struct PacketWriter {
+    data: Vec<u8>,
+}
+
+impl PacketWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn finish(self, id: i32) -> Packet {
+        Packet::new(id, self.data)
+    }
+
+    pub fn bytes(mut self, bytes: &[u8]) -> Self {
+        self.data.extend_from_slice(bytes);
+        self
+    }
+
+    pub fn u8(self, value: u8) -> Self {
+        self.bytes(&[value])
+    }
+
+    pub fn i8(self, value: i8) -> Self {
+        self.u8(value as u8)
+    }
+
+    pub fn bool(self, value: bool) -> Self {
+        self.u8(value as u8)
+    }
+
+    pub fn u16(self, value: u16) -> Self {
+        self.bytes(&value.to_be_bytes())
+    }
+
+    pub fn i16(self, value: i16) -> Self {
+        self.bytes(&value.to_be_bytes())
+    }
+
+    pub fn i32(self, value: i32) -> Self {
+        self.bytes(&value.to_be_bytes())
+    }
+
+    pub fn i64(self, value: i64) -> Self {
+        self.bytes(&value.to_be_bytes())
+    }
+
+    pub fn f32(self, value: f32) -> Self {
+        self.bytes(&value.to_be_bytes())
+    }
+
+    pub fn f64(self, value: f64) -> Self {
+        self.bytes(&value.to_be_bytes())
+    }
+
+    pub fn varint(mut self, value: i32) -> Self {
+        write_varint(&mut self.data, value);
+        self
+    }
+
+    pub fn string(self, value: &str) -> Self {
+        self.varint(value.len() as i32).bytes(value.as_bytes())
+    }
+
+    pub fn position(self, pos: BlockPos) -> Self {
+        let packed = ((pos.x as i64 & 0x3FFFFFF) << 38) | ((pos.y as i64 & 0xFFF) << 26) | (pos.z as i64 & 0x3FFFFFF);
+        self.i64(packed)
+    }
+
+    /// Fails on tags NBT can't hold, like strings over 65535 bytes
+    pub fn nbt(mut self, tag: Option<&Tag>) -> io::Result<Self> {
+        match tag {
+            Some(tag) => nbt::write(&mut self.data, "", tag)?,
+            None => self.data.push(nbt::END),
+        }
+        Ok(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compressed_packets_round_trip() {
+        let packet = Packet::new(0x21, (0..1000).map(|i| (i % 7) as u8).collect());
+        for compression in [None, Some(64), Some(4096)] {
+            let mut frame = Vec::new();
+            write_packet(&mut frame, &packet, compression).unwrap();
+            assert_eq!(read_packet(&mut &frame[..], compression).unwrap(), packet);
+        }
+    }
+
+    #[test]
+    fn slot_nbt_round_trips() {
+        let tag = Tag::Compound([("display", Tag::String("name".to_string()))].into_iter().collect());
+        let packet = PacketWriter::new().nbt(Some(&tag)).unwrap().nbt(None).unwrap().finish(0);
+        let mut reader = packet.reader();
+        assert_eq!((reader.nbt().unwrap(), reader.nbt().unwrap()), (Some(tag), None));
+        assert_eq!(reader.remaining(), 0);
+
+        let too_long = Tag::String("x".repeat(70000));
+        assert!(PacketWriter::new().nbt(Some(&too_long)).is_err());
+    }
+
+    #[test]
+    fn packets_inflating_past_their_length_are_refused() {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&[0; 100_000]).unwrap();
+        let compressed = encoder.finish().unwrap();
+        let mut frame = Vec::new();
+        write_varint(&mut frame, (varint_len(10) + compressed.len()) as i32);
+        write_varint(&mut frame, 10);
+        frame.extend_from_slice(&compressed);
+        let err = read_packet(&mut &frame[..], Some(0)).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        assert!(err.to_string().contains("11 bytes instead of 10"), "{err}");
+    }
+}
@@ -0,0 +1,47 @@
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::net::TcpStream;
+
+use crate::net::codec::{read_packet, write_packet, Packet};
+
+/// Either end of a protocol 47 connection, handles the framing and the compression threshold.
+pub struct Connection<R: Read, W: Write> {
+    reader: BufReader<R>,
+    writer: BufWriter<W>,
+    /// Packets this big or bigger get compressed, `None` until Set Compression
+    compression: Option<usize>,
+}
+
+impl Connection<TcpStream, TcpStream> {
+    pub fn tcp(stream: TcpStream) -> io::Result<Self> {
+        stream.set_nodelay(true)?;
+        Ok(Self::new(stream.try_clone()?, stream))
+    }
+}
+
+impl<R: Read, W: Write> Connection<R, W> {
+    pub fn new(reader: R, writer: W) -> Self {
+        Self {
+            reader: BufReader::new(reader),
+            writer: BufWriter::new(writer),
+            compression: None,
+        }
+    }
+
+    pub fn compression(&self) -> Option<usize> {
+        self.compression
+    }
+
+    /// Takes effect from the next packet either way, negative thresholds turn it back off like vanilla
+    pub fn set_compression(&mut self, threshold: i32) {
+        self.compression = usize::try_from(threshold).ok();
+    }
+
+    pub fn send(&mut self, packet: &Packet) -> io::Result<()> {
+        write_packet(&mut self.writer, packet, self.compression)?;
+        self.writer.flush()
+    }
+
+    pub fn receive(&mut self) -> io::Result<Packet> {
+        read_packet(&mut self.reader, self.compression)
+    }
+}
@@ -0,0 +1,71 @@
+//! Minecraft 1.8.9's protocol, version 47.
+
+pub mod chunk;
+pub mod client;
+pub mod codec;
+pub mod connection;
+pub mod packet;
+pub mod replay;
+pub mod server;
+pub mod status;
+pub mod uuid;
+
+use std::io;
+
+use crate::invalid;
+
+pub const PROTOCOL_VERSION: i32 = 47;
+pub const DEFAULT_PORT: u16 = 25565;
+
+/// What the handshake asks to switch to
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum NextState {
+    Status = 1,
+    Login = 2,
+}
+
+/// Splits `host[:port]` the way the server list takes addresses, with IPv6 addresses in
+/// brackets when there's a port after them. A bare IPv6 address has too many colons for one
+/// of them to be a port, so it gets the default.
+pub fn parse_address(address: &str) -> io::Result<(&str, u16)> {
+    let (host, port) = if let Some(rest) = address.strip_prefix('[') {
+        let (host, rest) = rest.split_once(']').ok_or_else(|| invalid(format!("{address:?} is missing its closing bracket")))?;
+        match rest {
+            "" => (host, None),
+            _ => (host, Some(rest.strip_prefix(':').ok_or_else(|| invalid(format!("{address:?} has something other than a port after the address")))?)),
+        }
+    } else {
+        match address.split_once(':') {
+            Some((host, port)) if !port.contains(':') => (host, Some(port)),
+            _ => (address, None),
+        }
+    };
+    let port = match port {
+        Some(port) => port.parse().map_err(|_| invalid(format!("{port:?} isn't a port")))?,
+        None => DEFAULT_PORT,
+    };
+    Ok((host, port))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn addresses_split_into_host_and_port() {
+        assert_eq!(parse_address("localhost").unwrap(), ("localhost", DEFAULT_PORT));
+        assert_eq!(parse_address("example.com:25566").unwrap(), ("example.com", 25566));
+        assert_eq!(parse_address("127.0.0.1:1").unwrap(), ("127.0.0.1", 1));
+        assert_eq!(parse_address("[::1]:25565").unwrap(), ("::1", 25565));
+        assert_eq!(parse_address("[2001:db8::7]").unwrap(), ("2001:db8::7", DEFAULT_PORT));
+        assert_eq!(parse_address("::1").unwrap(), ("::1", DEFAULT_PORT));
+        assert_eq!(parse_address("2001:db8::7:25565").unwrap(), ("2001:db8::7:25565", DEFAULT_PORT));
+    }
+
+    #[test]
+    fn bad_ports_are_refused() {
+        for address in ["localhost:", "localhost:port", "localhost:65536", "[::1]:x", "[::1", "[::1]25565"] {
+            assert!(parse_address(address).is_err(), "{address}");
+        }
+    }
+}
@@ -0,0 +1,64 @@
+//! Protocol 47 packet ids, by connection state and direction. Only the ones we use are here.
+
+pub mod handshake {
+    pub const HANDSHAKE: i32 = 0x00;
+}
+
+pub mod status {
+    pub mod clientbound {
+        pub const RESPONSE: i32 = 0x00;
+        pub const PONG: i32 = 0x01;
+    }
+
+    pub mod serverbound {
+        pub const REQUEST: i32 = 0x00;
+        pub const PING: i32 = 0x01;
+    }
+}
+
+pub mod login {
+    pub mod clientbound {
+        pub const DISCONNECT: i32 = 0x00;
+        pub const ENCRYPTION_REQUEST: i32 = 0x01;
+        pub const LOGIN_SUCCESS: i32 = 0x02;
+        pub const SET_COMPRESSION: i32 = 0x03;
+    }
+
+    pub mod serverbound {
+        pub const LOGIN_START: i32 = 0x00;
+    }
+}
+
+pub mod play {
+    pub mod clientbound {
+        pub const KEEP_ALIVE: i32 = 0x00;
+        pub const JOIN_GAME: i32 = 0x01;
+        pub const CHAT_MESSAGE: i32 = 0x02;
+        pub const TIME_UPDATE: i32 = 0x03;
+        pub const SPAWN_POSITION: i32 = 0x05;
+        pub const PLAYER_POSITION_AND_LOOK: i32 = 0x08;
+        pub const SPAWN_PLAYER: i32 = 0x0C;
+        pub const DESTROY_ENTITIES: i32 = 0x13;
+        pub const ENTITY_TELEPORT: i32 = 0x18;
+        pub const ENTITY_HEAD_LOOK: i32 = 0x19;
+        pub const CHUNK_DATA: i32 = 0x21;
+        pub const MULTI_BLOCK_CHANGE: i32 = 0x22;
+        pub const BLOCK_CHANGE: i32 = 0x23;
+        pub const MAP_CHUNK_BULK: i32 = 0x26;
+        pub const PLAYER_LIST_ITEM: i32 = 0x38;
+        pub const PLAYER_ABILITIES: i32 = 0x39;
+        pub const DISCONNECT: i32 = 0x40;
+        pub const SET_COMPRESSION: i32 = 0x46;
+    }
+
+    pub mod serverbound {
+        pub const KEEP_ALIVE: i32 = 0x00;
+        pub const CHAT_MESSAGE: i32 = 0x01;
+        pub const PLAYER: i32 = 0x03;
+        pub const PLAYER_POSITION: i32 = 0x04;
+        pub const PLAYER_LOOK: i32 = 0x05;
+        pub const PLAYER_POSITION_AND_LOOK: i32 = 0x06;
+        pub const PLAYER_DIGGING: i32 = 0x07;
+        pub const BLOCK_PLACEMENT: i32 = 0x08;
+    }
+}
@@ -0,0 +1,152 @@
+use std::io::{self, Read, Write};
+use std::net::{Ipv4Addr, SocketAddr, TcpListener};
+use std::thread::{self, JoinHandle};
+
+use crate::net::codec::{read_packet, Packet};
+
+/// Wraps the reading half of a connection and copies everything that comes through into `copy`,
+/// which is how streams for `ReplayServer` get captured from a real server.
+pub struct Recorder<R: Read, W: Write> {
+    inner: R,
+    copy: W,
+}
+
+impl<R: Read, W: Write> Recorder<R, W> {
+    pub fn new(inner: R, copy: W) -> Self {
+        Self { inner, copy }
+    }
+}
+
+impl<R: Read, W: Write> Read for Recorder<R, W> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.copy.write_all(&buf[..read])?;
+        Ok(read)
+    }
+}
+
+/// Stands in for a real server: takes one connection on localhost, reads the client's handshake
+/// and login start, then sends a captured clientbound stream back byte for byte. Whatever the
+/// client sends after that is read and thrown away until it hangs up.
+pub struct ReplayServer {
+    addr: SocketAddr,
+    handle: JoinHandle<io::Result<Vec<Packet>>>,
+}
+
+impl ReplayServer {
+    pub fn start(capture: Vec<u8>) -> io::Result<Self> {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))?;
+        let addr = listener.local_addr()?;
+        let handle = thread::spawn(move || {
+            let (mut stream, _) = listener.accept()?;
+            // both of these go out before the server could have turned compression on
+            let received = vec![read_packet(&mut stream, None)?, read_packet(&mut stream, None)?];
+            stream.write_all(&capture)?;
+            io::copy(&mut stream, &mut io::sink())?;
+            Ok(received)
+        });
+        Ok(Self { addr, handle })
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Waits for the client to hang up and hands back the handshake and login start it sent
+    pub fn finish(self) -> io::Result<Vec<Packet>> {
+        self.handle.join().unwrap_or_else(|_| Err(io::Error::other("replay server panicked")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::{GLOWSTONE, STONE};
+    use crate::net::chunk::{chunk_data_packet, map_chunk_bulk_packet};
+    use crate::net::client::{Client, ClientEvent};
+    use crate::net::codec::{write_packet, PacketWriter};
+    use crate::net::packet::{handshake, login, play};
+    use crate::world::{BlockPos, Chunk, ChunkPos, LightType, World};
+
+    const THRESHOLD: i32 = 64;
+
+    /// A column with something in two sections, some light and a biome of its own
+    fn bulk_chunk(x: i32, z: i32, biome: u8) -> Chunk {
+        let mut chunk = Chunk::new(ChunkPos::new(x, z));
+        chunk.set_block(1, 2, 3, STONE);
+        chunk.set_block(5, 100, 9, GLOWSTONE);
+        chunk.set_light(LightType::Block, 5, 101, 9, 14);
+        chunk.set_light(LightType::Sky, 1, 3, 3, 7);
+        chunk.biomes[9 << 4 | 5] = biome;
+        chunk
+    }
+
+    /// What a server would send from login to kicking the player, compressed after the first packet
+    fn capture() -> Vec<u8> {
+        let mut chunk = Chunk::new(ChunkPos::new(2, -1));
+        chunk.set_block(3, 60, 4, STONE);
+        let packets = [
+            PacketWriter::new().string("0-0-0-0-0").string("replayer").finish(login::clientbound::LOGIN_SUCCESS),
+            PacketWriter::new().i32(7).u8(1).i8(0).u8(0).u8(20).string("default").bool(false).finish(play::clientbound::JOIN_GAME),
+            chunk_data_packet(&chunk, true),
+            map_chunk_bulk_packet(&[&bulk_chunk(-4, 0, 2), &bulk_chunk(-3, 0, 3)], true),
+            // like the nether sends, the flag goes for the whole packet
+            map_chunk_bulk_packet(&[&bulk_chunk(10, 10, 8)], false),
+            PacketWriter::new().position(BlockPos::new(35, 61, -12)).varint(STONE.0 as i32).finish(play::clientbound::BLOCK_CHANGE),
+            PacketWriter::new().varint(99).finish(play::clientbound::KEEP_ALIVE),
+            PacketWriter::new().string(r#"{"text":"hi"}"#).finish(play::clientbound::CHAT_MESSAGE),
+            PacketWriter::new().string(r#"{"text":"bye"}"#).finish(play::clientbound::DISCONNECT),
+        ];
+        let mut capture = Vec::new();
+        write_packet(&mut capture, &PacketWriter::new().varint(THRESHOLD).finish(login::clientbound::SET_COMPRESSION), None).unwrap();
+        for packet in &packets {
+            write_packet(&mut capture, packet, Some(THRESHOLD as usize)).unwrap();
+        }
+        capture
+    }
+
+    #[test]
+    fn client_plays_back_a_capture() {
+        let server = ReplayServer::start(capture()).unwrap();
+        let mut client = Client::connect("127.0.0.1", server.addr().port(), "replayer").unwrap();
+        assert_eq!(client.username, "replayer");
+
+        let mut world = World::new();
+        let mut events = Vec::new();
+        loop {
+            let event = client.poll(&mut world).unwrap();
+            events.push(event.clone());
+            if let ClientEvent::Disconnected(_) = event {
+                break;
+            }
+        }
+        assert_eq!(
+            events,
+            [
+                ClientEvent::JoinGame { entity_id: 7, gamemode: 1, dimension: 0 },
+                ClientEvent::ChunksLoaded(vec![ChunkPos::new(2, -1)]),
+                ClientEvent::ChunksLoaded(vec![ChunkPos::new(-4, 0), ChunkPos::new(-3, 0)]),
+                ClientEvent::ChunksLoaded(vec![ChunkPos::new(10, 10)]),
+                ClientEvent::BlocksChanged(vec![BlockPos::new(35, 61, -12)]),
+                ClientEvent::KeepAlive,
+                ClientEvent::Chat(r#"{"text":"hi"}"#.to_string()),
+                ClientEvent::Disconnected(r#"{"text":"bye"}"#.to_string()),
+            ]
+        );
+        assert_eq!(world.get_block(BlockPos::new(35, 60, -12)), STONE);
+        assert_eq!(world.get_block(BlockPos::new(35, 61, -12)), STONE);
+        for (x, z, biome, sky_light) in [(-4, 0, 2, 7), (-3, 0, 3, 7), (10, 10, 8, 0)] {
+            let chunk = world.chunk(ChunkPos::new(x, z)).unwrap();
+            let expected = bulk_chunk(x, z, biome);
+            assert_eq!((chunk.get_block(1, 2, 3), chunk.get_block(5, 100, 9)), (STONE, GLOWSTONE));
+            assert_eq!(chunk.light(LightType::Block, 5, 101, 9), 14);
+            assert_eq!(chunk.light(LightType::Sky, 1, 3, 3), sky_light);
+            assert_eq!(chunk.biomes, expected.biomes);
+        }
+
+        drop(client);
+        let received = server.finish().unwrap();
+        assert_eq!(received[0].id, handshake::HANDSHAKE);
+        assert_eq!((received[1].id, received[1].reader().string().unwrap()), (login::serverbound::LOGIN_START, "replayer".to_string()));
+    }
+}
@@ -0,0 +1,669 @@
+use std::collections::{BTreeMap, HashSet};
+use std::io::{self, ErrorKind};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use nalgebra::Vector3;
+use serde_json::{json, Value};
+
+use crate::aabb::Aabb;
+use crate::block::Face;
+use crate::command;
+use crate::game::Game;
+use crate::invalid;
+use crate::level::DO_DAYLIGHT_CYCLE;
+use crate::net::chunk::{chunk_data_packet, unload_chunk_packet};
+use crate::net::codec::{Packet, PacketReader, PacketWriter};
+use crate::net::connection::Connection;
+use crate::net::packet::{handshake, login, play};
+use crate::net::status::{answer_legacy_ping, answer_status_requests, ServerStatus};
+use crate::net::uuid::{format_uuid, offline_uuid};
+use crate::net::{NextState, PROTOCOL_VERSION};
+use crate::player::{PLAYER_HEIGHT, PLAYER_WIDTH};
+use crate::world::{BlockPos, BlockState, ChunkPos};
+
+/// Chunks sent in every direction around a player
+pub const VIEW_DISTANCE: i32 = 4;
+pub const MAX_PLAYERS: i32 = 20;
+/// Packets this big or bigger get compressed, vanilla's default
+const COMPRESSION_THRESHOLD: i32 = 256;
+/// Vanilla sends one every two seconds
+const KEEP_ALIVE_TICKS: u64 = 40;
+/// Clients run their own clocks, this keeps them in step once a second
+const TIME_UPDATE_TICKS: u64 = 20;
+/// Clients that go quiet this long are dropped, they keep alive far more often than this
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+const WRITE_TIMEOUT: Duration = Duration::from_secs(10);
+/// Everyone joins in creative, so breaking is instant and flying is allowed
+const GAMEMODE_CREATIVE: u8 = 1;
+/// Invulnerable, allowed to fly and creative mode
+const CREATIVE_ABILITIES: i8 = 0x01 | 0x04 | 0x08;
+const LEGACY_PING: u8 = 0xFE;
+/// Players further out than this on x or z get kicked, it's where vanilla's world ends
+const MAX_COORDINATE: f64 = 30_000_000.0;
+
+/// Digging statuses that mean the block is gone, creative breaks as soon as digging starts
+const DIG_STARTED: i8 = 0;
+const DIG_FINISHED: i8 = 2;
+
+type PacketSender = Connection<io::Empty, TcpStream>;
+
+/// What connection threads tell the tick loop about.
+enum Incoming {
+    Joined { entity_id: i32, name: String, stream: TcpStream, sender: PacketSender },
+    Packet(i32, Packet),
+    Left(i32, String),
+}
+
+/// A vanilla client that made it through login.
+struct RemotePlayer {
+    entity_id: i32,
+    name: String,
+    uuid: u128,
+    /// Kept to hang up on them, everything else goes through `sender`
+    stream: TcpStream,
+    sender: PacketSender,
+    /// Feet, the way the client reports it
+    position: Vector3<f64>,
+    yaw: f32,
+    pitch: f32,
+    on_ground: bool,
+    moved: bool,
+    /// Chunks this client has been sent
+    chunks: HashSet<ChunkPos>,
+    /// Set when sending to them failed, they're dropped at the end of the tick
+    gone: Option<String>,
+}
+
+impl RemotePlayer {
+    fn send(&mut self, packet: &Packet) {
+        if self.gone.is_none() {
+            if let Err(err) = self.sender.send(packet) {
+                self.gone = Some(err.to_string());
+            }
+        }
+    }
+
+    /// Tells them why and drops them at the end of the tick
+    fn kick(&mut self, reason: &str) {
+        self.send(&PacketWriter::new().string(&json!({ "text": reason }).to_string()).finish(play::clientbound::DISCONNECT));
+        self.gone.get_or_insert_with(|| format!("kicked: {reason}"));
+    }
+
+    fn chunk(&self) -> ChunkPos {
+        BlockPos::new(self.position.x.floor() as i32, 0, self.position.z.floor() as i32).chunk()
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        Aabb::around_feet(self.position.cast(), PLAYER_WIDTH, PLAYER_HEIGHT)
+    }
+}
+
+/// Lets vanilla 1.8.9 clients into a `Game`. Connections are read on their own threads and
+/// everything they send is applied in `tick`, so the world is only ever touched by whoever
+/// owns the game.
+pub struct Server {
+    addr: SocketAddr,
+    incoming: Receiver<Incoming>,
+    players: BTreeMap<i32, RemotePlayer>,
+    status: Arc<Mutex<ServerStatus>>,
+    /// Names of the players allowed to run commands from chat, lowercase since names aren't
+    /// case sensitive. Everyone else can only talk.
+    ops: HashSet<String>,
+}
+
+impl Server {
+    pub fn bind(addr: impl ToSocketAddrs, motd: &str) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        let addr = listener.local_addr()?;
+        let status = Arc::new(Mutex::new(ServerStatus {
+            version_name: "1.8.9".to_string(),
+            protocol: PROTOCOL_VERSION,
+            max_players: MAX_PLAYERS,
+            motd: motd.to_string(),
+            ..Default::default()
+        }));
+        let (events, incoming) = mpsc::channel();
+
+        let accept_status = status.clone();
+        thread::spawn(move || {
+            // entity ids only need to be unique, so connections get them in the order they came in
+            for (entity_id, stream) in (1..).zip(listener.incoming().map_while(Result::ok)) {
+                let (events, status) = (events.clone(), accept_status.clone());
+                thread::spawn(move || {
+                    if let Err(err) = handle_connection(entity_id, stream, &events, &status) {
+                        if err.kind() != ErrorKind::UnexpectedEof {
+                            println!("Connection {entity_id} failed: {err}");
+                        }
+                    }
+                });
+            }
+        });
+
+        Ok(Self { addr, incoming, players: BTreeMap::new(), status, ops: HashSet::new() })
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Lets `name` run commands from chat, false if they already could
+    pub fn op(&mut self, name: &str) -> bool {
+        self.ops.insert(name.to_lowercase())
+    }
+
+    /// False if `name` wasn't an op
+    pub fn deop(&mut self, name: &str) -> bool {
+        self.ops.remove(&name.to_lowercase())
+    }
+
+    pub fn ops(&self) -> Vec<&str> {
+        let mut ops: Vec<&str> = self.ops.iter().map(String::as_str).collect();
+        ops.sort();
+        ops
+    }
+
+    pub fn player_names(&self) -> Vec<&str> {
+        self.players.values().map(|player| player.name.as_str()).collect()
+    }
+
+    /// The chunks everyone is standing in, what the world needs loading around
+    pub fn player_chunks(&self) -> Vec<ChunkPos> {
+        self.players.values().map(RemotePlayer::chunk).collect()
+    }
+
+    /// Applies everything clients sent since the last tick, then sends out what changed.
+    /// Meant to run right after `Game::tick_world`.
+    pub fn tick(&mut self, game: &mut Game) {
+        while let Ok(incoming) = self.incoming.try_recv() {
+            match incoming {
+                Incoming::Joined { entity_id, name, stream, sender } => self.join(game, entity_id, name, stream, sender),
+                Incoming::Packet(entity_id, packet) => {
+                    if let Err(err) = self.handle(game, entity_id, &packet) {
+                        if let Some(player) = self.players.get_mut(&entity_id) {
+                            player.gone = Some(format!("bad packet {:#04x}: {err}", packet.id));
+                        }
+                    }
+                }
+                Incoming::Left(entity_id, reason) => self.leave(entity_id, &reason),
+            }
+        }
+
+        for player in self.players.values_mut() {
+            send_chunks(game, player);
+        }
+
+        let moved: Vec<(i32, Packet, Packet)> = self.players.values_mut()
+            .filter_map(|player| std::mem::take(&mut player.moved).then_some(player))
+            .map(|player| {
+                let teleport = PacketWriter::new()
+                    .varint(player.entity_id)
+                    .i32(fixed(player.position.x))
+                    .i32(fixed(player.position.y))
+                    .i32(fixed(player.position.z))
+                    .i8(angle(player.yaw))
+                    .i8(angle(player.pitch))
+                    .bool(player.on_ground)
+                    .finish(play::clientbound::ENTITY_TELEPORT);
+                let head = PacketWriter::new().varint(player.entity_id).i8(angle(player.yaw)).finish(play::clientbound::ENTITY_HEAD_LOOK);
+                (player.entity_id, teleport, head)
+            })
+            .collect();
+        for (entity_id, teleport, head) in moved {
+            for other in self.players.values_mut().filter(|other| other.entity_id != entity_id) {
+                other.send(&teleport);
+                other.send(&head);
+            }
+        }
+
+        if game.ticks.is_multiple_of(TIME_UPDATE_TICKS) {
+            self.send_time(game);
+        }
+        if game.ticks.is_multiple_of(KEEP_ALIVE_TICKS) {
+            self.broadcast(&PacketWriter::new().varint(game.ticks as i32).finish(play::clientbound::KEEP_ALIVE));
+        }
+
+        let gone: Vec<(i32, String)> = self.players.values().filter_map(|player| Some((player.entity_id, player.gone.clone()?))).collect();
+        for (entity_id, reason) in gone {
+            self.leave(entity_id, &reason);
+        }
+    }
+
+    /// Kicks everyone with `reason`, for when the server stops
+    pub fn shutdown(&mut self, reason: &str) {
+        self.broadcast(&PacketWriter::new().string(&json!({ "text": reason }).to_string()).finish(play::clientbound::DISCONNECT));
+        for (_, player) in std::mem::take(&mut self.players) {
+            let _ = player.stream.shutdown(Shutdown::Both);
+        }
+        self.update_status();
+    }
+
+    /// Tells everyone the time right away, for when a command changed it
+    pub fn send_time(&mut self, game: &Game) {
+        self.broadcast(&time_update(game));
+    }
+
+    fn broadcast(&mut self, packet: &Packet) {
+        for player in self.players.values_mut() {
+            player.send(packet);
+        }
+    }
+
+    fn join(&mut self, game: &mut Game, entity_id: i32, name: String, stream: TcpStream, sender: PacketSender) {
+        let mut player = RemotePlayer {
+            entity_id,
+            uuid: offline_uuid(&name),
+            name,
+            stream,
+            sender,
+            position: game.spawn.cast(),
+            yaw: 0.0,
+            pitch: 0.0,
+            on_ground: false,
+            moved: false,
+            chunks: HashSet::new(),
+            gone: None,
+        };
+        let spawn = BlockPos::new(game.spawn.x.floor() as i32, game.spawn.y.floor() as i32, game.spawn.z.floor() as i32);
+
+        player.send(&PacketWriter::new()
+            .i32(entity_id)
+            .u8(GAMEMODE_CREATIVE)
+            .i8(0)
+            .u8(1)
+            .u8(MAX_PLAYERS as u8)
+            .string("default")
+            .bool(false)
+            .finish(play::clientbound::JOIN_GAME));
+        player.send(&PacketWriter::new().position(spawn).finish(play::clientbound::SPAWN_POSITION));
+        player.send(&PacketWriter::new().i8(CREATIVE_ABILITIES).f32(0.05).f32(0.1).finish(play::clientbound::PLAYER_ABILITIES));
+        player.send(&time_update(game));
+        send_chunks(game, &mut player);
+        // the client sits on "Downloading terrain" until it knows where it is
+        player.send(&PacketWriter::new()
+            .f64(player.position.x)
+            .f64(player.position.y)
+            .f64(player.position.z)
+            .f32(player.yaw)
+            .f32(player.pitch)
+            .i8(0)
+            .finish(play::clientbound::PLAYER_POSITION_AND_LOOK));
+
+        // 1.8 clients need someone in the tab list before they can spawn them
+        let listed = player_list_add(&player);
+        player.send(&listed);
+        let spawned = spawn_player(&player);
+        for other in self.players.values_mut() {
+            other.send(&listed);
+            other.send(&spawned);
+            player.send(&player_list_add(other));
+            player.send(&spawn_player(other));
+        }
+
+        println!("{} joined the game", player.name);
+        let message = chat_packet(json!({ "translate": "multiplayer.player.joined", "with": [player.name], "color": "yellow" }));
+        self.players.insert(entity_id, player);
+        self.broadcast(&message);
+        self.update_status();
+    }
+
+    fn leave(&mut self, entity_id: i32, reason: &str) {
+        let Some(player) = self.players.remove(&entity_id) else {
+            return;
+        };
+        let _ = player.stream.shutdown(Shutdown::Both);
+        println!("{} left the game ({})", player.name, reason);
+
+        self.broadcast(&PacketWriter::new().varint(1).varint(entity_id).finish(play::clientbound::DESTROY_ENTITIES));
+        let (most, least) = uuid_halves(player.uuid);
+        // action 4 is remove
+        self.broadcast(&PacketWriter::new().varint(4).varint(1).i64(most).i64(least).finish(play::clientbound::PLAYER_LIST_ITEM));
+        self.broadcast(&chat_packet(json!({ "translate": "multiplayer.player.left", "with": [player.name], "color": "yellow" })));
+        self.update_status();
+    }
+
+    fn handle(&mut self, game: &mut Game, entity_id: i32, packet: &Packet) -> io::Result<()> {
+        let mut reader = packet.reader();
+        let others: Vec<Aabb> = self.players.values().map(RemotePlayer::bounding_box).collect();
+        let Some(player) = self.players.get_mut(&entity_id) else {
+            return Ok(());
+        };
+
+        match packet.id {
+            play::serverbound::CHAT_MESSAGE => {
+                let message = reader.string()?;
+                if let Some(command) = message.strip_prefix('/') {
+                    if !self.ops.contains(&player.name.to_lowercase()) {
+                        println!("{} isn't allowed to run /{}", player.name, command);
+                        player.send(&chat_packet(json!({ "translate": "commands.generic.permission", "color": "red" })));
+                        return Ok(());
+                    }
+                    println!("{} ran /{}", player.name, command);
+                    let reply = match command::run(game, command) {
+                        Ok(message) => json!({ "text": message }),
+                        Err(message) => json!({ "text": message, "color": "red" }),
+                    };
+                    player.send(&chat_packet(reply));
+                    // the only commands there are change the time
+                    self.send_time(game);
+                    return Ok(());
+                }
+                println!("<{}> {}", player.name, message);
+                let chat = chat_packet(json!({ "translate": "chat.type.text", "with": [player.name, message] }));
+                self.broadcast(&chat);
+            }
+            play::serverbound::PLAYER => player.on_ground = reader.bool()?,
+            play::serverbound::PLAYER_POSITION => {
+                let Some(position) = read_position(&mut reader)? else {
+                    player.kick("Illegal position");
+                    return Ok(());
+                };
+                player.position = position;
+                player.on_ground = reader.bool()?;
+                player.moved = true;
+            }
+            play::serverbound::PLAYER_LOOK => {
+                (player.yaw, player.pitch) = (reader.f32()?, reader.f32()?);
+                player.on_ground = reader.bool()?;
+                player.moved = true;
+            }
+            play::serverbound::PLAYER_POSITION_AND_LOOK => {
+                let Some(position) = read_position(&mut reader)? else {
+                    player.kick("Illegal position");
+                    return Ok(());
+                };
+                player.position = position;
+                (player.yaw, player.pitch) = (reader.f32()?, reader.f32()?);
+                player.on_ground = reader.bool()?;
+                player.moved = true;
+            }
+            play::serverbound::PLAYER_DIGGING => {
+                let status = reader.i8()?;
+                let pos = reader.position()?;
+                if status == DIG_STARTED || status == DIG_FINISHED {
+                    if game.break_block(pos) {
+                        self.broadcast(&block_change(pos, BlockState::AIR));
+                    } else {
+                        player.send(&block_change(pos, game.world.get_block(pos)));
+                    }
+                }
+            }
+            play::serverbound::BLOCK_PLACEMENT => {
+                let pos = reader.position()?;
+                let face = reader.u8()?;
+                let item = reader.i16()?;
+                // 255 is using the held item on nothing, like eating
+                let Some(face) = Face::from_index(face as usize) else {
+                    return Ok(());
+                };
+                let (dx, dy, dz) = face.offset();
+                let target = BlockPos::new(pos.x + dx, pos.y + dy, pos.z + dz);
+
+                let placed = if item >= 0 {
+                    let (_count, damage, _tag) = (reader.i8()?, reader.i16()?, reader.nbt()?);
+                    let state = BlockState::new(item as u16, damage as u8);
+                    // items past 255 aren't blocks, and the registry only knows blocks
+                    item < 256 && game.registry.get(state).is_some() && game.place_block_avoiding(target, state, &others)
+                } else {
+                    false
+                };
+                if placed {
+                    self.broadcast(&block_change(target, game.world.get_block(target)));
+                } else {
+                    // the client already shows the block, put back what's really there
+                    player.send(&block_change(target, game.world.get_block(target)));
+                    player.send(&block_change(pos, game.world.get_block(pos)));
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn update_status(&self) {
+        let mut status = self.status.lock().unwrap();
+        status.online = self.players.len() as i32;
+        status.sample = self.players.values().take(12).map(|player| player.name.clone()).collect();
+    }
+}
+
+/// Reads a connection from its first byte: legacy and modern status pings get answered right
+/// here, logins get handed to the tick loop and then everything they send is passed along.
+fn handle_connection(entity_id: i32, stream: TcpStream, events: &Sender<Incoming>, status: &Mutex<ServerStatus>) -> io::Result<()> {
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+    let mut first = [0];
+    if stream.peek(&mut first)? == 1 && first[0] == LEGACY_PING {
+        let status = status.lock().unwrap().clone();
+        return answer_legacy_ping(stream, &status);
+    }
+
+    let mut connection = Connection::tcp(stream.try_clone()?)?;
+    let packet = connection.receive()?;
+    if packet.id != handshake::HANDSHAKE {
+        return Err(invalid(format!("expected a handshake, got packet {:#04x}", packet.id)));
+    }
+    let mut reader = packet.reader();
+    let (protocol, _host, _port, next) = (reader.varint()?, reader.string()?, reader.u16()?, reader.varint()?);
+    if next == NextState::Status as i32 {
+        let status = status.lock().unwrap().clone();
+        return answer_status_requests(&mut connection, &status);
+    }
+    if next != NextState::Login as i32 {
+        return Err(invalid(format!("handshake asked for unknown state {next}")));
+    }
+
+    let packet = connection.receive()?;
+    if packet.id != login::serverbound::LOGIN_START {
+        return Err(invalid(format!("expected login start, got packet {:#04x}", packet.id)));
+    }
+    let name = packet.reader().string()?;
+    if protocol != PROTOCOL_VERSION {
+        let reason = json!({ "text": format!("This server is on 1.8.9 (protocol {PROTOCOL_VERSION}), you're on protocol {protocol}") });
+        return connection.send(&PacketWriter::new().string(&reason.to_string()).finish(login::clientbound::DISCONNECT));
+    }
+    if name.is_empty() || name.len() > 16 {
+        let reason = json!({ "text": "Invalid username" });
+        return connection.send(&PacketWriter::new().string(&reason.to_string()).finish(login::clientbound::DISCONNECT));
+    }
+
+    connection.send(&PacketWriter::new().varint(COMPRESSION_THRESHOLD).finish(login::clientbound::SET_COMPRESSION))?;
+    connection.set_compression(COMPRESSION_THRESHOLD);
+    connection.send(&PacketWriter::new().string(&format_uuid(offline_uuid(&name))).string(&name).finish(login::clientbound::LOGIN_SUCCESS))?;
+
+    let mut sender = Connection::new(io::empty(), stream.try_clone()?);
+    sender.set_compression(COMPRESSION_THRESHOLD);
+    let _ = events.send(Incoming::Joined { entity_id, name, stream, sender });
+
+    loop {
+        match connection.receive() {
+            Ok(packet) => {
+                if events.send(Incoming::Packet(entity_id, packet)).is_err() {
+                    return Ok(());
+                }
+            }
+            Err(err) => {
+                let reason = if err.kind() == ErrorKind::UnexpectedEof { "disconnected".to_string() } else { err.to_string() };
+                let _ = events.send(Incoming::Left(entity_id, reason));
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// Sends the chunks that came into view around a player and unloads the ones that left it,
+/// generating any that don't exist yet. Chunks already sent get sent again when generating
+/// their neighbours decorated them.
+fn send_chunks(game: &mut Game, player: &mut RemotePlayer) {
+    let center = player.chunk();
+    let changed = game.generate_around(center, VIEW_DISTANCE);
+
+    let mut wanted = HashSet::new();
+    for x in center.x - VIEW_DISTANCE..=center.x + VIEW_DISTANCE {
+        for z in center.z - VIEW_DISTANCE..=center.z + VIEW_DISTANCE {
+            wanted.insert(ChunkPos::new(x, z));
+        }
+    }
+
+    let stale: Vec<ChunkPos> = player.chunks.difference(&wanted).copied().collect();
+    for pos in stale {
+        player.chunks.remove(&pos);
+        player.send(&unload_chunk_packet(pos));
+    }
+    let mut fresh: Vec<ChunkPos> = wanted.into_iter()
+        .filter(|pos| !player.chunks.contains(pos) || changed.contains(pos))
+        .collect();
+    // nearest first so the ground under them shows up before anything else
+    fresh.sort_by_key(|pos| (pos.x - center.x).pow(2) + (pos.z - center.z).pow(2));
+    for pos in fresh {
+        if let Some(chunk) = game.world.chunk(pos) {
+            player.send(&chunk_data_packet(chunk, true));
+            player.chunks.insert(pos);
+        }
+    }
+}
+
+/// The feet position of a move packet, `None` if it's somewhere no player could be
+fn read_position(reader: &mut PacketReader) -> io::Result<Option<Vector3<f64>>> {
+    let position = Vector3::new(reader.f64()?, reader.f64()?, reader.f64()?);
+    let valid = position.iter().all(|coordinate| coordinate.is_finite()) && position.x.abs() <= MAX_COORDINATE && position.z.abs() <= MAX_COORDINATE;
+    Ok(valid.then_some(position))
+}
+
+fn player_list_add(player: &RemotePlayer) -> Packet {
+    let (most, least) = uuid_halves(player.uuid);
+    // action 0 is add: uuid, name, no skin properties, gamemode, ping and no display name
+    PacketWriter::new()
+        .varint(0)
+        .varint(1)
+        .i64(most)
+        .i64(least)
+        .string(&player.name)
+        .varint(0)
+        .varint(GAMEMODE_CREATIVE as i32)
+        .varint(0)
+        .bool(false)
+        .finish(play::clientbound::PLAYER_LIST_ITEM)
+}
+
+fn spawn_player(player: &RemotePlayer) -> Packet {
+    let (most, least) = uuid_halves(player.uuid);
+    PacketWriter::new()
+        .varint(player.entity_id)
+        .i64(most)
+        .i64(least)
+        .i32(fixed(player.position.x))
+        .i32(fixed(player.position.y))
+        .i32(fixed(player.position.z))
+        .i8(angle(player.yaw))
+        .i8(angle(player.pitch))
+        // empty hand, and 0x7F ends the empty entity metadata
+        .i16(0)
+        .u8(0x7F)
+        .finish(play::clientbound::SPAWN_PLAYER)
+}
+
+fn block_change(pos: BlockPos, state: BlockState) -> Packet {
+    PacketWriter::new().position(pos).varint(state.0 as i32).finish(play::clientbound::BLOCK_CHANGE)
+}
+
+/// A negative time of day stops the sun on the client, which is how vanilla says `doDaylightCycle` is off
+fn time_update(game: &Game) -> Packet {
+    let day_time = match game.rules.get_bool(DO_DAYLIGHT_CYCLE) {
+        true => game.day_time as i64,
+        // -0 would still be 0, so sunrise goes out as -1
+        false => -(game.day_time as i64).max(1),
+    };
+    PacketWriter::new().i64(game.ticks as i64).i64(day_time).finish(play::clientbound::TIME_UPDATE)
+}
+
+fn chat_packet(message: Value) -> Packet {
+    // position 0 is the chat box
+    PacketWriter::new().string(&message.to_string()).i8(0).finish(play::clientbound::CHAT_MESSAGE)
+}
+
+fn uuid_halves(uuid: u128) -> (i64, i64) {
+    ((uuid >> 64) as i64, uuid as i64)
+}
+
+/// Entity positions go out as fixed point with 5 fractional bits
+fn fixed(coordinate: f64) -> i32 {
+    (coordinate * 32.0).floor() as i32
+}
+
+/// Angles go out in 256ths of a turn
+fn angle(degrees: f32) -> i8 {
+    (degrees / 360.0 * 256.0).floor() as i32 as i8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::net::client::{Client, ClientEvent};
+    use crate::world::World;
+
+    /// Polls until the server answers in chat, past the joined message everyone gets
+    fn next_reply(client: &mut Client<TcpStream, TcpStream>, world: &mut World) -> String {
+        loop {
+            if let ClientEvent::Chat(message) = client.poll(world).unwrap() {
+                if !message.contains("multiplayer.player.joined") {
+                    return message;
+                }
+            }
+        }
+    }
+
+    fn position(x: f64, y: f64, z: f64) -> io::Result<Option<Vector3<f64>>> {
+        let packet = PacketWriter::new().f64(x).f64(y).f64(z).finish(play::serverbound::PLAYER_POSITION);
+        read_position(&mut packet.reader())
+    }
+
+    #[test]
+    fn positions_off_the_world_are_refused() {
+        assert_eq!(position(1.5, 64.0, -MAX_COORDINATE).unwrap(), Some(Vector3::new(1.5, 64.0, -MAX_COORDINATE)));
+        // nothing stops players from flying high or digging down out of the world
+        assert!(position(0.0, 1e9, 0.0).unwrap().is_some());
+        for (x, y, z) in [(f64::NAN, 64.0, 0.0), (0.0, f64::INFINITY, 0.0), (0.0, 64.0, f64::NEG_INFINITY), (3e7 + 1.0, 64.0, 0.0), (0.0, 64.0, -1e300)] {
+            assert_eq!(position(x, y, z).unwrap(), None, "{x} {y} {z}");
+        }
+    }
+
+    #[test]
+    fn only_ops_run_commands_from_chat() {
+        let mut game = Game::new(1);
+        let mut server = Server::bind("127.0.0.1:0", "test").unwrap();
+        let port = server.addr().port();
+        let (replies, replied) = mpsc::channel();
+        let (opped, op_done) = mpsc::channel();
+        let player = thread::spawn(move || {
+            let mut client = Client::connect("127.0.0.1", port, "Tester").unwrap();
+            let mut world = World::new();
+            for _ in 0..2 {
+                client.send_chat("/time set 1000").unwrap();
+                replies.send(next_reply(&mut client, &mut world)).unwrap();
+                op_done.recv().unwrap();
+            }
+        });
+
+        let tick_until_reply = |game: &mut Game, server: &mut Server| loop {
+            server.tick(game);
+            if let Ok(reply) = replied.try_recv() {
+                return reply;
+            }
+            thread::sleep(Duration::from_millis(5));
+        };
+        assert!(tick_until_reply(&mut game, &mut server).contains("commands.generic.permission"));
+        assert_eq!(game.day_time, 0);
+
+        // names aren't case sensitive
+        assert!(server.op("tester"));
+        opped.send(()).unwrap();
+        assert!(tick_until_reply(&mut game, &mut server).contains("Set the time to 1000"));
+        assert_eq!(game.day_time, 1000);
+        opped.send(()).unwrap();
+        player.join().unwrap();
+    }
+}
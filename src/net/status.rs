@@ -0,0 +1,358 @@
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::invalid;
+use crate::net::codec::{PacketWriter};
+use crate::net::connection::Connection;
+use crate::net::packet::{handshake, status};
+use crate::net::{NextState, PROTOCOL_VERSION};
+
+/// What 1.6 and older clients open a server list ping with
+const LEGACY_PING: u8 = 0xFE;
+/// What the answer to one starts with
+const LEGACY_KICK: u8 = 0xFF;
+/// Vanilla 1.8 answers legacy pings with this, so old clients show the server as too new
+const LEGACY_PROTOCOL: i32 = 127;
+const TIMEOUT: Duration = Duration::from_secs(5);
+/// How long a legacy ping gets to send the rest of its request
+const LEGACY_GRACE: Duration = Duration::from_millis(250);
+
+/// What a server shows in the multiplayer list.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ServerStatus {
+    pub version_name: String,
+    pub protocol: i32,
+    pub online: i32,
+    pub max_players: i32,
+    /// Names the server chose to show, usually a few of the players online
+    pub sample: Vec<String>,
+    /// Plain text, any formatting in the chat component is dropped
+    pub motd: String,
+    /// A `data:image/png;base64,` URI of a 64x64 icon
+    pub favicon: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct StatusJson {
+    version: VersionJson,
+    players: PlayersJson,
+    description: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    favicon: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct VersionJson {
+    name: String,
+    protocol: i32,
+}
+
+#[derive(Serialize, Deserialize)]
+struct PlayersJson {
+    max: i32,
+    online: i32,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    sample: Vec<SampleJson>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SampleJson {
+    name: String,
+    id: String,
+}
+
+impl ServerStatus {
+    pub fn from_json(json: &str) -> io::Result<Self> {
+        let parsed: StatusJson = serde_json::from_str(json).map_err(|err| invalid(format!("bad status json: {err}")))?;
+        Ok(Self {
+            version_name: parsed.version.name,
+            protocol: parsed.version.protocol,
+            online: parsed.players.online,
+            max_players: parsed.players.max,
+            sample: parsed.players.sample.into_iter().map(|player| player.name).collect(),
+            motd: chat_text(&parsed.description),
+            favicon: parsed.favicon,
+        })
+    }
+
+    pub fn to_json(&self) -> String {
+        let json = StatusJson {
+            version: VersionJson { name: self.version_name.clone(), protocol: self.protocol },
+            players: PlayersJson {
+                max: self.max_players,
+                online: self.online,
+                // offline mode has no real ids to show, vanilla clients only look at the names anyway
+                sample: self.sample.iter().map(|name| SampleJson { name: name.clone(), id: "00000000-0000-0000-0000-000000000000".into() }).collect(),
+            },
+            description: serde_json::json!({ "text": self.motd }),
+            favicon: self.favicon.clone(),
+        };
+        serde_json::to_string(&json).unwrap()
+    }
+}
+
+/// The text of a chat component and all its `extra` children, or the string itself for a bare string
+pub fn chat_text(component: &Value) -> String {
+    match component {
+        Value::String(text) => text.clone(),
+        Value::Array(parts) => parts.iter().map(chat_text).collect(),
+        Value::Object(fields) => {
+            let mut text = fields.get("text").map(chat_text).unwrap_or_default();
+            if let Some(extra) = fields.get("extra") {
+                text += &chat_text(extra);
+            }
+            text
+        }
+        _ => String::new(),
+    }
+}
+
+/// Asks a server for its status the way the 1.8 server list does, and how long a ping took
+pub fn ping(host: &str, port: u16) -> io::Result<(ServerStatus, Duration)> {
+    let stream = TcpStream::connect((host, port))?;
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    let mut connection = Connection::tcp(stream)?;
+
+    connection.send(&PacketWriter::new()
+        .varint(PROTOCOL_VERSION)
+        .string(host)
+        .u16(port)
+        .varint(NextState::Status as i32)
+        .finish(handshake::HANDSHAKE))?;
+    connection.send(&PacketWriter::new().finish(status::serverbound::REQUEST))?;
+    let response = connection.receive()?;
+    if response.id != status::clientbound::RESPONSE {
+        return Err(invalid(format!("expected a status response, got packet {:#04x}", response.id)));
+    }
+    let status = ServerStatus::from_json(&response.reader().string()?)?;
+
+    // vanilla sends its clock, any number works as long as it comes back
+    let payload = SystemTime::now().duration_since(UNIX_EPOCH).map(|time| time.as_millis() as i64).unwrap_or(0);
+    let started = Instant::now();
+    connection.send(&PacketWriter::new().i64(payload).finish(status::serverbound::PING))?;
+    let pong = connection.receive()?;
+    let latency = started.elapsed();
+    if pong.id != status::clientbound::PONG || pong.reader().i64()? != payload {
+        return Err(invalid("server didn't answer the ping with a matching pong"));
+    }
+    Ok((status, latency))
+}
+
+/// The 1.6 style ping, for servers too old to know the handshake. Only the version, motd and
+/// player counts come back, and the latency is the whole round trip.
+pub fn ping_legacy(host: &str, port: u16) -> io::Result<(ServerStatus, Duration)> {
+    let mut stream = TcpStream::connect((host, port))?;
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    let started = Instant::now();
+
+    // 0xFE 0x01, then an MC|PingHost plugin message carrying the protocol, host and port
+    let mut request = vec![LEGACY_PING, 0x01, 0xFA];
+    write_utf16(&mut request, "MC|PingHost");
+    let mut payload = vec![74];
+    write_utf16(&mut payload, host);
+    payload.extend_from_slice(&(port as i32).to_be_bytes());
+    request.extend_from_slice(&(payload.len() as u16).to_be_bytes());
+    request.extend_from_slice(&payload);
+    stream.write_all(&request)?;
+
+    let mut header = [0; 3];
+    stream.read_exact(&mut header)?;
+    if header[0] != LEGACY_KICK {
+        return Err(invalid(format!("expected a legacy kick, got {:#04x}", header[0])));
+    }
+    let length = u16::from_be_bytes([header[1], header[2]]) as usize;
+    let mut text = vec![0; length * 2];
+    stream.read_exact(&mut text)?;
+    let latency = started.elapsed();
+    let units: Vec<u16> = text.chunks_exact(2).map(|unit| u16::from_be_bytes([unit[0], unit[1]])).collect();
+    let text = String::from_utf16(&units).map_err(|_| invalid("legacy ping answer isn't valid UTF-16"))?;
+
+    let number = |field: &str| field.parse().map_err(|_| invalid(format!("{field:?} isn't a number")));
+    let status = if let Some(fields) = text.strip_prefix("§1\0") {
+        // 1.4 and newer: protocol, version, motd, online and max, nul separated
+        let fields: Vec<&str> = fields.split('\0').collect();
+        let [protocol, version, motd, online, max] = fields[..] else {
+            return Err(invalid(format!("legacy ping answer has {} fields instead of 5", fields.len())));
+        };
+        ServerStatus {
+            version_name: version.to_string(),
+            protocol: number(protocol)?,
+            online: number(online)?,
+            max_players: number(max)?,
+            motd: motd.to_string(),
+            ..Default::default()
+        }
+    } else {
+        // beta 1.8 to 1.3 only send motd§online§max
+        let mut fields = text.rsplitn(3, '§');
+        let (max, online, motd) = (fields.next(), fields.next(), fields.next());
+        let (Some(max), Some(online), Some(motd)) = (max, online, motd) else {
+            return Err(invalid("legacy ping answer is missing its player counts"));
+        };
+        ServerStatus {
+            online: number(online)?,
+            max_players: number(max)?,
+            motd: motd.to_string(),
+            ..Default::default()
+        }
+    };
+    Ok((status, latency))
+}
+
+/// Serves one status connection, legacy or not. Modern clients get their handshake read here
+/// too, so this is only for connections nobody has read from yet.
+pub fn answer_status(stream: TcpStream, server_status: &ServerStatus) -> io::Result<()> {
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    let mut first = [0];
+    if stream.peek(&mut first)? == 1 && first[0] == LEGACY_PING {
+        return answer_legacy_ping(stream, server_status);
+    }
+
+    let mut connection = Connection::tcp(stream)?;
+    let packet = connection.receive()?;
+    let mut reader = packet.reader();
+    if packet.id != handshake::HANDSHAKE {
+        return Err(invalid(format!("expected a handshake, got packet {:#04x}", packet.id)));
+    }
+    let (_protocol, _host, _port) = (reader.varint()?, reader.string()?, reader.u16()?);
+    if reader.varint()? != NextState::Status as i32 {
+        return Err(invalid("handshake isn't asking for the status"));
+    }
+    answer_status_requests(&mut connection, server_status)
+}
+
+/// Answers the status request and ping that follow a status handshake, until the client hangs up
+pub fn answer_status_requests<R: Read, W: Write>(connection: &mut Connection<R, W>, server_status: &ServerStatus) -> io::Result<()> {
+    loop {
+        let packet = match connection.receive() {
+            Ok(packet) => packet,
+            Err(err) if err.kind() == ErrorKind::UnexpectedEof => return Ok(()),
+            Err(err) => return Err(err),
+        };
+        match packet.id {
+            status::serverbound::REQUEST => {
+                connection.send(&PacketWriter::new().string(&server_status.to_json()).finish(status::clientbound::RESPONSE))?;
+            }
+            status::serverbound::PING => {
+                connection.send(&PacketWriter::new().i64(packet.reader().i64()?).finish(status::clientbound::PONG))?;
+                // vanilla hangs up after the pong
+                return Ok(());
+            }
+            id => return Err(invalid(format!("unexpected packet {id:#04x} in the status state"))),
+        }
+    }
+}
+
+/// Kicks a legacy ping with the status packed into the reason, the way 1.8 servers do
+pub fn answer_legacy_ping(mut stream: TcpStream, server_status: &ServerStatus) -> io::Result<()> {
+    // 1.6 follows 0xFE with 0x01 and an MC|PingHost plugin message, older clients stop early and
+    // just wait, so whatever doesn't show up quickly isn't waited for. Everything that did come is
+    // read though, closing with unread data would reset the connection under the answer.
+    stream.set_read_timeout(Some(LEGACY_GRACE))?;
+    let _ = skip_legacy_request(&mut stream);
+
+    let text = format!(
+        "§1\0{}\0{}\0{}\0{}\0{}",
+        LEGACY_PROTOCOL, server_status.version_name, server_status.motd, server_status.online, server_status.max_players,
+    );
+    let units: Vec<u16> = text.encode_utf16().collect();
+    let mut answer = vec![LEGACY_KICK];
+    answer.extend_from_slice(&(units.len() as u16).to_be_bytes());
+    answer.extend(units.iter().flat_map(|unit| unit.to_be_bytes()));
+    stream.write_all(&answer)
+}
+
+/// A fake server on localhost that answers every status ping with the same status, for trying
+/// `ping` and `ping_legacy` without a real server around.
+pub struct StatusServer {
+    addr: SocketAddr,
+}
+
+impl StatusServer {
+    pub fn start(server_status: ServerStatus) -> io::Result<Self> {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))?;
+        let addr = listener.local_addr()?;
+        thread::spawn(move || {
+            for stream in listener.incoming().map_while(Result::ok) {
+                if let Err(err) = answer_status(stream, &server_status) {
+                    eprintln!("Status ping failed: {err}");
+                }
+            }
+        });
+        Ok(Self { addr })
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+}
+
+fn skip_legacy_request(stream: &mut TcpStream) -> io::Result<()> {
+    let mut header = [0; 3];
+    stream.read_exact(&mut header)?;
+    let mut length = [0; 2];
+    stream.read_exact(&mut length)?;
+    // the channel name in UTF-16, then the payload's length in bytes
+    io::copy(&mut stream.take(u16::from_be_bytes(length) as u64 * 2), &mut io::sink())?;
+    stream.read_exact(&mut length)?;
+    io::copy(&mut stream.take(u16::from_be_bytes(length) as u64), &mut io::sink())?;
+    Ok(())
+}
+
+fn write_utf16(out: &mut Vec<u8>, text: &str) {
+    let units: Vec<u16> = text.encode_utf16().collect();
+    out.extend_from_slice(&(units.len() as u16).to_be_bytes());
+    out.extend(units.iter().flat_map(|unit| unit.to_be_bytes()));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn server_status() -> ServerStatus {
+        ServerStatus {
+            version_name: "1.8.9".to_string(),
+            protocol: PROTOCOL_VERSION,
+            online: 2,
+            max_players: 20,
+            sample: vec!["alex".to_string(), "steve".to_string()],
+            motd: "A §atest§r server".to_string(),
+            favicon: Some("data:image/png;base64,AAAA".to_string()),
+        }
+    }
+
+    #[test]
+    fn ping_gets_the_whole_status() {
+        let server = StatusServer::start(server_status()).unwrap();
+        let (status, latency) = ping("127.0.0.1", server.addr().port()).unwrap();
+        assert_eq!(status, server_status());
+        assert!(latency < TIMEOUT);
+    }
+
+    #[test]
+    fn legacy_ping_gets_the_counts_and_motd() {
+        let server = StatusServer::start(server_status()).unwrap();
+        let (status, _) = ping_legacy("127.0.0.1", server.addr().port()).unwrap();
+        let expected = ServerStatus {
+            version_name: "1.8.9".to_string(),
+            protocol: LEGACY_PROTOCOL,
+            online: 2,
+            max_players: 20,
+            motd: "A §atest§r server".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(status, expected);
+    }
+
+    #[test]
+    fn status_json_round_trips() {
+        assert_eq!(ServerStatus::from_json(&server_status().to_json()).unwrap(), server_status());
+        let json = r#"{"version":{"name":"x","protocol":5},"players":{"max":1,"online":0},"description":{"text":"a","extra":[{"text":"b"},"c"]}}"#;
+        assert_eq!(ServerStatus::from_json(json).unwrap().motd, "abc");
+    }
+}
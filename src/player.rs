@@ -0,0 +1,279 @@
+use nalgebra::Vector3;
+
+use crate::aabb::Aabb;
+use crate::block::BlockRegistry;
+use crate::game::TICKS_PER_SECOND;
+use crate::world::{BlockPos, World};
+
+pub const PLAYER_WIDTH: f32 = 0.6;
+pub const PLAYER_HEIGHT: f32 = 1.8;
+/// How far above the player's feet the camera sits
+pub const PLAYER_EYE_HEIGHT: f32 = 1.62;
+
+// all per tick, in blocks
+const GRAVITY: f32 = 0.08;
+const AIR_DRAG: f32 = 0.98;
+const TERMINAL_VELOCITY: f32 = 3.92;
+const JUMP_VELOCITY: f32 = 0.42;
+const GROUND_ACCELERATION: f32 = 0.1;
+const AIR_ACCELERATION: f32 = 0.02;
+/// Horizontal velocity kept each tick, vanilla's block slipperiness (0.6) times 0.91 on the ground
+const GROUND_FRICTION: f32 = 0.546;
+const AIR_FRICTION: f32 = 0.91;
+/// Two jump presses less than this many ticks apart toggle flying
+const DOUBLE_TAP_TICKS: u32 = 7;
+/// How much wider sprinting makes the view, half of vanilla's 30% sprint speed boost
+const SPRINT_FOV_MULTIPLIER: f32 = 1.15;
+
+/// What the player is pressing during a tick.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct PlayerInput {
+    /// -1..1, positive is forwards
+    pub forward: f32,
+    /// -1..1, positive is to the left like vanilla's `moveStrafing`
+    pub strafe: f32,
+    pub jump: bool,
+    pub sneak: bool,
+    /// Held to start sprinting, which then carries on until the player stops going forwards
+    pub sprint: bool,
+    /// Jump went down since the last tick, what the double tap is counted from
+    pub jump_pressed: bool,
+}
+
+pub struct Player {
+    /// Feet, in the middle of the bounding box
+    pub position: Vector3<f32>,
+    /// Where the feet were at the start of the last tick, for interpolating between ticks
+    pub previous_position: Vector3<f32>,
+    pub velocity: Vector3<f32>,
+    pub yaw: f32,
+    pub pitch: f32,
+    pub on_ground: bool,
+    /// Free flight through everything, like the camera used to move
+    pub flying: bool,
+    /// Blocks per second while flying
+    pub fly_speed: f32,
+    pub sprinting: bool,
+    double_tap_timer: u32,
+}
+
+impl Player {
+    pub fn new(position: Vector3<f32>) -> Self {
+        Self {
+            position,
+            previous_position: position,
+            velocity: Vector3::zeros(),
+            yaw: 0.0,
+            pitch: 0.0,
+            on_ground: false,
+            flying: false,
+            fly_speed: 1.0,
+            sprinting: false,
+            double_tap_timer: 0,
+        }
+    }
+
+    pub fn bounding_box(&self) -> Aabb {
+        Aabb::around_feet(self.position, PLAYER_WIDTH, PLAYER_HEIGHT)
+    }
+
+    /// Where the camera goes, `partial_tick` of the way from the last tick to the current one
+    pub fn eye_position(&self, partial_tick: f32) -> Vector3<f32> {
+        self.previous_position.lerp(&self.position, partial_tick) + Vector3::new(0.0, PLAYER_EYE_HEIGHT, 0.0)
+    }
+
+    /// How much wider the view gets the faster the player goes, vanilla's FOV modifier without the smoothing
+    pub fn fov_multiplier(&self) -> f32 {
+        let mut multiplier = 1.0;
+        if self.flying {
+            multiplier *= 1.1;
+        }
+        if self.sprinting {
+            multiplier *= SPRINT_FOV_MULTIPLIER;
+        }
+        multiplier
+    }
+
+    pub fn tick(&mut self, world: &World, registry: &BlockRegistry, input: &PlayerInput) {
+        self.previous_position = self.position;
+
+        if self.double_tap_timer > 0 {
+            self.double_tap_timer -= 1;
+        }
+        if input.jump_pressed {
+            if self.double_tap_timer > 0 {
+                self.flying = !self.flying;
+                self.velocity = Vector3::zeros();
+                self.double_tap_timer = 0;
+            } else {
+                self.double_tap_timer = DOUBLE_TAP_TICKS;
+            }
+        }
+
+        if input.forward <= 0.0 || input.sneak {
+            self.sprinting = false;
+        } else if input.sprint {
+            self.sprinting = true;
+        }
+
+        let forward = Vector3::new(self.yaw.to_radians().cos(), 0.0, self.yaw.to_radians().sin());
+        let left = Vector3::new(forward.z, 0.0, -forward.x);
+
+        if self.flying {
+            let vertical = input.jump as i32 - input.sneak as i32;
+            let step = self.fly_speed / TICKS_PER_SECOND as f32;
+            self.position += (forward * input.forward + left * input.strafe + Vector3::new(0.0, vertical as f32, 0.0)) * step;
+            self.on_ground = false;
+            return;
+        }
+
+        if input.jump && self.on_ground {
+            self.velocity.y = JUMP_VELOCITY;
+        }
+
+        // vanilla's moveFlying, diagonals don't go any faster than straight lines
+        let length = (input.forward * input.forward + input.strafe * input.strafe).sqrt();
+        if length >= 0.01 {
+            let acceleration = if self.on_ground { GROUND_ACCELERATION } else { AIR_ACCELERATION };
+            let scale = acceleration / length.max(1.0);
+            self.velocity += (forward * input.forward + left * input.strafe) * scale;
+        }
+
+        self.move_colliding(world, registry);
+
+        self.velocity.y = ((self.velocity.y - GRAVITY) * AIR_DRAG).max(-TERMINAL_VELOCITY);
+        let friction = if self.on_ground { GROUND_FRICTION } else { AIR_FRICTION };
+        self.velocity.x *= friction;
+        self.velocity.z *= friction;
+    }
+
+    /// Moves by `velocity` one axis at a time, y first then x and z like vanilla, stopping at solid blocks
+    fn move_colliding(&mut self, world: &World, registry: &BlockRegistry) {
+        let mut bounds = self.bounding_box();
+        let obstacles = solid_boxes(world, registry, &bounds.expand_towards(self.velocity));
+
+        let mut moved = self.velocity;
+        for axis in [1, 0, 2] {
+            moved[axis] = obstacles.iter().fold(moved[axis], |offset, obstacle| obstacle.clip_offset(&bounds, axis, offset));
+            let mut offset = Vector3::zeros();
+            offset[axis] = moved[axis];
+            bounds = bounds.offset(offset);
+        }
+
+        self.on_ground = self.velocity.y < 0.0 && moved.y != self.velocity.y;
+        for axis in 0..3 {
+            if moved[axis] != self.velocity[axis] {
+                self.velocity[axis] = 0.0;
+            }
+        }
+        self.position += moved;
+    }
+}
+
+/// Every solid block touching `area`
+fn solid_boxes(world: &World, registry: &BlockRegistry, area: &Aabb) -> Vec<Aabb> {
+    let mut boxes = Vec::new();
+    for x in area.min.x.floor() as i32..=area.max.x.floor() as i32 {
+        for y in area.min.y.floor() as i32..=area.max.y.floor() as i32 {
+            for z in area.min.z.floor() as i32..=area.max.z.floor() as i32 {
+                let pos = BlockPos::new(x, y, z);
+                if registry.is_solid(world.get_block(pos)) {
+                    boxes.push(Aabb::block(pos));
+                }
+            }
+        }
+    }
+    boxes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::STONE;
+    use crate::world::{Chunk, ChunkPos};
+
+    const GROUND_Y: i32 = 64;
+
+    /// A stone floor under the whole chunk, the top of it at `GROUND_Y`
+    fn floor() -> World {
+        let mut world = World::new();
+        world.load_chunk(Chunk::new(ChunkPos::new(0, 0)));
+        for x in 0..16 {
+            for z in 0..16 {
+                world.set_block(BlockPos::new(x, GROUND_Y - 1, z), STONE);
+            }
+        }
+        world
+    }
+
+    #[test]
+    fn falls_onto_the_ground_and_stays_there() {
+        let (world, registry) = (floor(), BlockRegistry::vanilla());
+        let mut player = Player::new(Vector3::new(8.5, GROUND_Y as f32 + 10.0, 8.5));
+        for _ in 0..100 {
+            player.tick(&world, &registry, &PlayerInput::default());
+        }
+        assert!(player.on_ground);
+        assert_eq!(player.position, Vector3::new(8.5, GROUND_Y as f32, 8.5));
+
+        // standing still doesn't sink or bounce
+        for _ in 0..20 {
+            player.tick(&world, &registry, &PlayerInput::default());
+            assert!(player.on_ground);
+            assert_eq!(player.position.y, GROUND_Y as f32);
+        }
+    }
+
+    #[test]
+    fn walls_stop_the_player() {
+        let (mut world, registry) = (floor(), BlockRegistry::vanilla());
+        for z in 0..16 {
+            world.set_block(BlockPos::new(12, GROUND_Y, z), STONE);
+            world.set_block(BlockPos::new(12, GROUND_Y + 1, z), STONE);
+        }
+        // yaw 0 faces +x, straight at the wall
+        let mut player = Player::new(Vector3::new(8.5, GROUND_Y as f32, 8.5));
+        let walk = PlayerInput { forward: 1.0, ..PlayerInput::default() };
+        for _ in 0..100 {
+            player.tick(&world, &registry, &walk);
+        }
+        assert!((player.position.x - (12.0 - PLAYER_WIDTH / 2.0)).abs() < 1e-4, "stopped at {}", player.position.x);
+        assert_eq!(player.velocity.x, 0.0);
+        assert_eq!((player.position.y, player.position.z), (GROUND_Y as f32, 8.5));
+    }
+
+    #[test]
+    fn double_tapping_jump_toggles_flying() {
+        let (world, registry) = (floor(), BlockRegistry::vanilla());
+        let mut player = Player::new(Vector3::new(8.5, GROUND_Y as f32, 8.5));
+        let press = PlayerInput { jump: true, jump_pressed: true, ..PlayerInput::default() };
+        let tap_twice = |player: &mut Player, gap: u32| {
+            player.tick(&world, &registry, &press);
+            for _ in 1..gap {
+                player.tick(&world, &registry, &PlayerInput::default());
+            }
+            player.tick(&world, &registry, &press);
+        };
+
+        tap_twice(&mut player, DOUBLE_TAP_TICKS - 1);
+        assert!(player.flying);
+        assert!(!player.on_ground);
+
+        // flying doesn't fall
+        let height = player.position.y;
+        for _ in 0..20 {
+            player.tick(&world, &registry, &PlayerInput::default());
+        }
+        assert_eq!(player.position.y, height);
+
+        tap_twice(&mut player, 2);
+        assert!(!player.flying);
+
+        // too slow for a double tap
+        for _ in 0..50 {
+            player.tick(&world, &registry, &PlayerInput::default());
+        }
+        tap_twice(&mut player, DOUBLE_TAP_TICKS);
+        assert!(!player.flying);
+    }
+}
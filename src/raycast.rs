@@ -0,0 +1,171 @@
+use nalgebra::Vector3;
+
+use crate::block::{BlockRegistry, Face};
+use crate::world::{BlockPos, World};
+
+/// How far vanilla lets you reach in survival
+pub const REACH: f32 = 4.5;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RayHit {
+    pub block: BlockPos,
+    /// The face of `block` the ray went in through
+    pub face: Face,
+    /// From the ray's origin to where it entered the block
+    pub distance: f32,
+}
+
+impl RayHit {
+    /// The block touching the hit face, where a placed block goes
+    pub fn adjacent(&self) -> BlockPos {
+        let (dx, dy, dz) = self.face.offset();
+        BlockPos::new(self.block.x + dx, self.block.y + dy, self.block.z + dz)
+    }
+}
+
+/// Walks the block grid along a ray (Amanatides & Woo's DDA) and returns the first block the
+/// crosshair can target within `max_distance`. A block the origin is already inside counts as
+/// hit straight away, through the face the ray is mostly heading away from.
+pub fn raycast(world: &World, registry: &BlockRegistry, origin: Vector3<f32>, direction: Vector3<f32>, max_distance: f32) -> Option<RayHit> {
+    let direction = direction.try_normalize(f32::EPSILON)?;
+    let mut block = [origin.x.floor() as i32, origin.y.floor() as i32, origin.z.floor() as i32];
+
+    if registry.is_targetable(world.get_block(BlockPos::new(block[0], block[1], block[2]))) {
+        let axis = direction.iamax();
+        return Some(RayHit {
+            block: BlockPos::new(block[0], block[1], block[2]),
+            face: entry_face(axis, direction[axis] > 0.0),
+            distance: 0.0,
+        });
+    }
+
+    let mut step = [0; 3];
+    // distance along the ray to the next grid line on each axis, and between grid lines
+    let mut t_max = [f32::INFINITY; 3];
+    let mut t_delta = [f32::INFINITY; 3];
+    for axis in 0..3 {
+        if direction[axis] > 0.0 {
+            step[axis] = 1;
+            t_delta[axis] = 1.0 / direction[axis];
+            t_max[axis] = (block[axis] as f32 + 1.0 - origin[axis]) * t_delta[axis];
+        } else if direction[axis] < 0.0 {
+            step[axis] = -1;
+            t_delta[axis] = -1.0 / direction[axis];
+            t_max[axis] = (origin[axis] - block[axis] as f32) * t_delta[axis];
+        }
+    }
+
+    loop {
+        let axis = if t_max[0] < t_max[1] {
+            if t_max[0] < t_max[2] { 0 } else { 2 }
+        } else if t_max[1] < t_max[2] {
+            1
+        } else {
+            2
+        };
+        let distance = t_max[axis];
+        if distance > max_distance {
+            return None;
+        }
+        block[axis] += step[axis];
+        t_max[axis] += t_delta[axis];
+
+        let pos = BlockPos::new(block[0], block[1], block[2]);
+        if registry.is_targetable(world.get_block(pos)) {
+            return Some(RayHit { block: pos, face: entry_face(axis, step[axis] > 0), distance });
+        }
+    }
+}
+
+/// The face a ray crosses when moving along `axis`, going positive means it comes in on the negative side
+fn entry_face(axis: usize, positive: bool) -> Face {
+    match (axis, positive) {
+        (0, true) => Face::West,
+        (0, false) => Face::East,
+        (1, true) => Face::Down,
+        (1, false) => Face::Up,
+        (_, true) => Face::North,
+        (_, false) => Face::South,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::{STONE, WATER};
+    use crate::world::{BlockState, Chunk, ChunkPos};
+
+    const EYE: Vector3<f32> = Vector3::new(8.5, 65.5, 8.5);
+
+    fn world_with(blocks: &[(BlockPos, BlockState)]) -> World {
+        let mut world = World::new();
+        world.load_chunk(Chunk::new(ChunkPos::new(0, 0)));
+        for &(pos, state) in blocks {
+            assert!(world.set_block(pos, state));
+        }
+        world
+    }
+
+    #[test]
+    fn hits_along_every_axis() {
+        let registry = BlockRegistry::vanilla();
+        let cases = [
+            (Vector3::new(1.0, 0.0, 0.0), BlockPos::new(10, 65, 8), Face::West),
+            (Vector3::new(-1.0, 0.0, 0.0), BlockPos::new(6, 65, 8), Face::East),
+            (Vector3::new(0.0, 1.0, 0.0), BlockPos::new(8, 67, 8), Face::Down),
+            (Vector3::new(0.0, -1.0, 0.0), BlockPos::new(8, 63, 8), Face::Up),
+            (Vector3::new(0.0, 0.0, 1.0), BlockPos::new(8, 65, 10), Face::North),
+            (Vector3::new(0.0, 0.0, -1.0), BlockPos::new(8, 65, 6), Face::South),
+        ];
+        for (direction, block, face) in cases {
+            let world = world_with(&[(block, STONE)]);
+            let hit = raycast(&world, &registry, EYE, direction, REACH).unwrap();
+            assert_eq!((hit.block, hit.face), (block, face));
+            // from the middle of a block the next one over starts half a block away
+            assert!((hit.distance - 1.5).abs() < 1e-5, "{direction:?} hit at {}", hit.distance);
+            let (dx, dy, dz) = (direction.x as i32, direction.y as i32, direction.z as i32);
+            assert_eq!(hit.adjacent(), BlockPos::new(block.x - dx, block.y - dy, block.z - dz));
+        }
+    }
+
+    #[test]
+    fn adjacent_is_in_front_of_the_hit_face() {
+        let block = BlockPos::new(3, 40, -2);
+        for face in Face::ALL {
+            let hit = RayHit { block, face, distance: 1.0 };
+            let (dx, dy, dz) = face.offset();
+            assert_eq!(hit.adjacent(), BlockPos::new(3 + dx, 40 + dy, -2 + dz));
+        }
+    }
+
+    #[test]
+    fn stops_at_the_reach_limit() {
+        let registry = BlockRegistry::vanilla();
+        let world = world_with(&[(BlockPos::new(13, 65, 8), STONE)]);
+        // the block starts 4.5 blocks away
+        let direction = Vector3::new(1.0, 0.0, 0.0);
+        assert!(raycast(&world, &registry, EYE, direction, 4.4).is_none());
+        assert_eq!(raycast(&world, &registry, EYE, direction, REACH).map(|hit| hit.block), Some(BlockPos::new(13, 65, 8)));
+        assert!(raycast(&world, &registry, EYE, Vector3::new(0.0, 1.0, 0.0), 100.0).is_none());
+    }
+
+    #[test]
+    fn goes_through_liquids() {
+        let registry = BlockRegistry::vanilla();
+        let water: Vec<_> = (9..12).map(|x| (BlockPos::new(x, 65, 8), WATER)).collect();
+        let mut blocks = water.clone();
+        blocks.push((BlockPos::new(12, 65, 8), STONE));
+        let world = world_with(&blocks);
+        let hit = raycast(&world, &registry, EYE, Vector3::new(1.0, 0.0, 0.0), REACH).unwrap();
+        assert_eq!((hit.block, hit.face), (BlockPos::new(12, 65, 8), Face::West));
+        assert!(raycast(&world_with(&water), &registry, EYE, Vector3::new(1.0, 0.0, 0.0), REACH).is_none());
+    }
+
+    #[test]
+    fn starting_inside_a_block_hits_it() {
+        let registry = BlockRegistry::vanilla();
+        let world = world_with(&[(BlockPos::new(8, 65, 8), STONE)]);
+        let hit = raycast(&world, &registry, EYE, Vector3::new(0.2, -1.0, 0.1), REACH).unwrap();
+        assert_eq!((hit.block, hit.face, hit.distance), (BlockPos::new(8, 65, 8), Face::Up, 0.0));
+    }
+}
@@ -0,0 +1,329 @@
+use std::collections::{HashMap, HashSet};
+use std::f32::consts::SQRT_2;
+
+use gl::types::*;
+use nalgebra::{Matrix4, Perspective3, Translation3, Vector3};
+
+use nanocraft::atlas::TextureAtlas;
+use nanocraft::block::BlockRegistry;
+use nanocraft::game::TICKS_PER_SECOND;
+use nanocraft::mesh::{mesh_section, MeshMode, SectionMesh, SmoothLighting, FLOATS_PER_VERTEX};
+use nanocraft::world::{BlockPos, ChunkPos, CHUNK_WIDTH, SECTION_COUNT, SECTION_HEIGHT, World};
+
+/// A section mesh that has been uploaded to its own vertex buffer
+pub struct GpuMesh {
+    vao: GLuint,
+    vbo: GLuint,
+    vertex_count: usize,
+}
+
+impl GpuMesh {
+    pub fn upload(mesh: &SectionMesh) -> Self {
+        let (mut vao, mut vbo) = (0, 0);
+        let stride = (FLOATS_PER_VERTEX * std::mem::size_of::<f32>()) as i32;
+        unsafe {
+            gl::GenVertexArrays(1, &mut vao);
+            gl::GenBuffers(1, &mut vbo);
+            gl::BindVertexArray(vao);
+
+            gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
+            gl::BufferData(
+                gl::ARRAY_BUFFER,
+                (mesh.vertices.len() * std::mem::size_of::<f32>()) as isize,
+                mesh.vertices.as_ptr() as *const _,
+                gl::STATIC_DRAW,
+            );
+
+            // Vertex attribute for positions
+            gl::VertexAttribPointer(0, 3, gl::FLOAT, gl::FALSE, stride, std::ptr::null());
+            gl::EnableVertexAttribArray(0);
+
+            // Vertex attribute for texture coordinates
+            gl::VertexAttribPointer(1, 2, gl::FLOAT, gl::FALSE, stride, (3 * std::mem::size_of::<f32>()) as *const _);
+            gl::EnableVertexAttribArray(1);
+
+            // Vertex attribute for the atlas tile
+            gl::VertexAttribPointer(2, 4, gl::FLOAT, gl::FALSE, stride, (5 * std::mem::size_of::<f32>()) as *const _);
+            gl::EnableVertexAttribArray(2);
+
+            // Vertex attribute for the biome tint
+            gl::VertexAttribPointer(3, 3, gl::FLOAT, gl::FALSE, stride, (9 * std::mem::size_of::<f32>()) as *const _);
+            gl::EnableVertexAttribArray(3);
+
+            // Vertex attribute for sky and block light
+            gl::VertexAttribPointer(4, 2, gl::FLOAT, gl::FALSE, stride, (12 * std::mem::size_of::<f32>()) as *const _);
+            gl::EnableVertexAttribArray(4);
+
+            // Vertex attribute for face shading and ambient occlusion
+            gl::VertexAttribPointer(5, 1, gl::FLOAT, gl::FALSE, stride, (14 * std::mem::size_of::<f32>()) as *const _);
+            gl::EnableVertexAttribArray(5);
+
+            gl::BindVertexArray(0);
+        }
+
+        Self { vao, vbo, vertex_count: mesh.vertex_count() }
+    }
+}
+
+impl Drop for GpuMesh {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteBuffers(1, &self.vbo);
+            gl::DeleteVertexArrays(1, &self.vao);
+        }
+    }
+}
+
+const MIN_RENDER_DISTANCE: i32 = 2;
+const MAX_RENDER_DISTANCE: i32 = 32;
+const DEFAULT_RENDER_DISTANCE: i32 = 8;
+/// Meshing a chunk takes a few milliseconds, any more than this a frame and walking stutters
+const CHUNKS_MESHED_PER_FRAME: usize = 2;
+
+const MIN_FOV: f32 = 30.0;
+const MAX_FOV: f32 = 110.0;
+/// Vanilla's, in degrees up and down
+const DEFAULT_FOV: f32 = 70.0;
+const NEAR_PLANE: f32 = 0.1;
+
+/// The camera's perspective, kept the same shape as the framebuffer
+pub struct Projection {
+    /// Vertical, in degrees, before sprinting widens it
+    fov: f32,
+    /// Sprinting and flying's widening, eased towards what the player's going at
+    fov_multiplier: f32,
+    aspect: f32,
+}
+
+impl Default for Projection {
+    fn default() -> Self {
+        Self { fov: DEFAULT_FOV, fov_multiplier: 1.0, aspect: 1.0 }
+    }
+}
+
+impl Projection {
+    pub fn fov(&self) -> f32 {
+        self.fov
+    }
+
+    /// Clamped to 30..=110 degrees
+    pub fn set_fov(&mut self, degrees: f32) {
+        self.fov = degrees.clamp(MIN_FOV, MAX_FOV);
+    }
+
+    /// A minimised window's framebuffer has no size, it keeps its old shape until it comes back
+    pub fn resize(&mut self, width: i32, height: i32) {
+        if width > 0 && height > 0 {
+            self.aspect = width as f32 / height as f32;
+        }
+    }
+
+    /// Eases the widening towards `target` over a frame `seconds` long, half the way each tick
+    /// like vanilla so it doesn't snap when a sprint starts or stops
+    pub fn update(&mut self, target: f32, seconds: f32) {
+        let eased = 1.0 - 0.5f32.powf(seconds * TICKS_PER_SECOND as f32);
+        self.fov_multiplier += (target - self.fov_multiplier) * eased;
+    }
+
+    pub fn matrix(&self, far: f32) -> Matrix4<f32> {
+        Perspective3::new(self.aspect, (self.fov * self.fov_multiplier).to_radians(), NEAR_PLANE, far).to_homogeneous()
+    }
+}
+
+/// Keeps one mesh per non-empty chunk section within the render distance and draws them.
+#[derive(Default)]
+pub struct ChunkRenderer {
+    pub mesh_mode: MeshMode,
+    pub smooth_lighting: SmoothLighting,
+    pub projection: Projection,
+    /// In chunks out from the one the camera is in, set with `set_render_distance`
+    render_distance: i32,
+    sections: HashMap<(ChunkPos, usize), GpuMesh>,
+    /// Chunks with meshes, and which of their sides had a chunk loaded beside them at the time.
+    /// The rest in range, and those whose neighbours came or went since, get meshed a few at a time.
+    meshed: HashMap<ChunkPos, u8>,
+}
+
+impl ChunkRenderer {
+    pub fn new() -> Self {
+        Self { render_distance: DEFAULT_RENDER_DISTANCE, ..Self::default() }
+    }
+
+    pub fn render_distance(&self) -> i32 {
+        self.render_distance
+    }
+
+    /// Clamped to 2..=32 chunks
+    pub fn set_render_distance(&mut self, chunks: i32) {
+        self.render_distance = chunks.clamp(MIN_RENDER_DISTANCE, MAX_RENDER_DISTANCE);
+    }
+
+    /// How far the render distance reaches in blocks, as far as anything is drawn in every direction
+    pub fn view_distance(&self) -> f32 {
+        (self.render_distance * CHUNK_WIDTH as i32) as f32
+    }
+
+    /// Reaches a little past the view distance so the corners of the furthest chunks still get drawn
+    pub fn projection_matrix(&self) -> Matrix4<f32> {
+        self.projection.matrix(self.view_distance() * SQRT_2)
+    }
+
+    /// Follows the camera into `center`: drops the meshes that are out of range now and meshes
+    /// the closest few chunks in range that aren't yet
+    pub fn update(&mut self, world: &World, registry: &BlockRegistry, atlas: &TextureAtlas, center: ChunkPos) {
+        let distance = self.render_distance;
+        self.sections.retain(|&(pos, _), _| within(center, pos, distance));
+        self.meshed.retain(|&pos, _| within(center, pos, distance));
+
+        let mut waiting: Vec<ChunkPos> = (-distance..=distance)
+            .flat_map(|dx| (-distance..=distance).map(move |dz| ChunkPos::new(center.x + dx, center.z + dz)))
+            .filter(|&pos| world.is_loaded(pos) && self.meshed.get(&pos) != Some(&loaded_sides(world, pos)))
+            .collect();
+        waiting.sort_by_key(|pos| (pos.x - center.x).pow(2) + (pos.z - center.z).pow(2));
+        for pos in waiting.into_iter().take(CHUNKS_MESHED_PER_FRAME) {
+            for section_y in 0..SECTION_COUNT {
+                self.rebuild_section(world, registry, atlas, pos, section_y);
+            }
+            self.meshed.insert(pos, loaded_sides(world, pos));
+        }
+    }
+
+    fn rebuild_section(&mut self, world: &World, registry: &BlockRegistry, atlas: &TextureAtlas, chunk_pos: ChunkPos, section_y: usize) {
+        let mesh = mesh_section(world, registry, atlas, chunk_pos, section_y, self.mesh_mode, self.smooth_lighting);
+        if mesh.is_empty() {
+            self.sections.remove(&(chunk_pos, section_y));
+        } else {
+            self.sections.insert((chunk_pos, section_y), GpuMesh::upload(&mesh));
+        }
+    }
+
+    /// Has every chunk meshed again, nearest first. Until its turn comes each keeps its old mesh.
+    pub fn rebuild_all(&mut self) {
+        self.meshed.clear();
+    }
+
+    /// Has chunks meshed again that changed all over, like the ones a new chunk's decorations
+    /// spilled into. Chunks beside ones that were loaded or unloaded get that on their own.
+    pub fn chunks_changed(&mut self, changed: &[ChunkPos]) {
+        for pos in changed {
+            self.meshed.remove(pos);
+        }
+    }
+
+    /// Re-meshes the sections of the changed block and of every block touching it, corners
+    /// included, since their faces may have appeared or disappeared or their corners gone darker
+    pub fn block_changed(&mut self, world: &World, registry: &BlockRegistry, atlas: &TextureAtlas, pos: BlockPos) {
+        let mut sections = HashSet::new();
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                for dz in -1..=1 {
+                    let neighbour = BlockPos::new(pos.x + dx, pos.y + dy, pos.z + dz);
+                    if (0..(SECTION_COUNT * SECTION_HEIGHT) as i32).contains(&neighbour.y) {
+                        sections.insert((neighbour.chunk(), neighbour.y as usize / SECTION_HEIGHT));
+                    }
+                }
+            }
+        }
+        for (chunk_pos, section_y) in sections {
+            if self.meshed.contains_key(&chunk_pos) {
+                self.rebuild_section(world, registry, atlas, chunk_pos, section_y);
+            }
+        }
+    }
+
+    /// Re-meshes every section with light changes since the last call
+    pub fn light_changed(&mut self, world: &mut World, registry: &BlockRegistry, atlas: &TextureAtlas) {
+        for (chunk_pos, section_y) in world.take_light_changes() {
+            // the rest get their light when they're meshed
+            if self.meshed.contains_key(&chunk_pos) {
+                self.rebuild_section(world, registry, atlas, chunk_pos, section_y);
+            }
+        }
+    }
+
+    /// Draws every section, the atlas texture has to be bound already
+    pub unsafe fn draw(&self, model_location: GLint) {
+        for ((chunk_pos, section_y), mesh) in &self.sections {
+            let origin = chunk_pos.origin();
+            let translation = Translation3::new(origin.x as f32, (section_y * SECTION_HEIGHT) as f32, origin.z as f32);
+            let model: Matrix4<f32> = translation.to_homogeneous();
+            gl::UniformMatrix4fv(model_location, 1, gl::FALSE, model.as_ptr());
+
+            gl::BindVertexArray(mesh.vao);
+            gl::DrawArrays(gl::TRIANGLES, 0, mesh.vertex_count as i32);
+        }
+    }
+}
+
+/// A bit for each side of `pos` with a chunk loaded beside it, which decides whether the faces
+/// along that side get meshed
+fn loaded_sides(world: &World, pos: ChunkPos) -> u8 {
+    [(1, 0), (-1, 0), (0, 1), (0, -1)].iter()
+        .enumerate()
+        .filter(|(_, &(dx, dz))| world.is_loaded(ChunkPos::new(pos.x + dx, pos.z + dz)))
+        .fold(0, |sides, (i, _)| sides | 1 << i)
+}
+
+/// Whether `pos` is in the square of chunks `distance` out from `center`
+fn within(center: ChunkPos, pos: ChunkPos, distance: i32) -> bool {
+    (pos.x - center.x).abs() <= distance && (pos.z - center.z).abs() <= distance
+}
+
+/// The black wireframe drawn around the block under the crosshair
+pub struct SelectionOutline {
+    vao: GLuint,
+    vbo: GLuint,
+}
+
+impl SelectionOutline {
+    pub fn new() -> Self {
+        // the twelve edges of a unit cube as line pairs
+        let mut vertices: Vec<f32> = Vec::with_capacity(24 * 3);
+        for a in [0.0, 1.0] {
+            for b in [0.0, 1.0] {
+                vertices.extend_from_slice(&[0.0, a, b, 1.0, a, b]);
+                vertices.extend_from_slice(&[a, 0.0, b, a, 1.0, b]);
+                vertices.extend_from_slice(&[a, b, 0.0, a, b, 1.0]);
+            }
+        }
+
+        let (mut vao, mut vbo) = (0, 0);
+        unsafe {
+            gl::GenVertexArrays(1, &mut vao);
+            gl::GenBuffers(1, &mut vbo);
+            gl::BindVertexArray(vao);
+            gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
+            gl::BufferData(
+                gl::ARRAY_BUFFER,
+                (vertices.len() * std::mem::size_of::<f32>()) as isize,
+                vertices.as_ptr() as *const _,
+                gl::STATIC_DRAW,
+            );
+            gl::VertexAttribPointer(0, 3, gl::FLOAT, gl::FALSE, 3 * std::mem::size_of::<f32>() as i32, std::ptr::null());
+            gl::EnableVertexAttribArray(0);
+            gl::BindVertexArray(0);
+        }
+
+        Self { vao, vbo }
+    }
+
+    /// Expects the outline shader to be in use with its view and projection set
+    pub unsafe fn draw(&self, model_location: GLint, block: BlockPos) {
+        // grown a little past the block so the lines don't z-fight with its faces
+        let grow = 0.002;
+        let model = Translation3::new(block.x as f32 - grow, block.y as f32 - grow, block.z as f32 - grow).to_homogeneous()
+            * Matrix4::new_nonuniform_scaling(&Vector3::new(1.0 + grow * 2.0, 1.0 + grow * 2.0, 1.0 + grow * 2.0));
+        gl::UniformMatrix4fv(model_location, 1, gl::FALSE, model.as_ptr());
+        gl::BindVertexArray(self.vao);
+        gl::DrawArrays(gl::LINES, 0, 24);
+    }
+}
+
+impl Drop for SelectionOutline {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteBuffers(1, &self.vbo);
+            gl::DeleteVertexArrays(1, &self.vao);
+        }
+    }
+}
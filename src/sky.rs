@@ -0,0 +1,368 @@
+//! The sky behind the world: a gradient from the fog colour at the horizon up to the sky colour,
+//! the sun and moon going round with the time of day, and stars once it gets dark. The colours
+//! are worked out with vanilla's formulas.
+
+use std::array;
+use std::ffi::CString;
+use std::f32::consts::PI;
+
+use gl::types::*;
+use nalgebra::{Matrix4, Rotation3, Vector3, Vector4};
+
+use nanocraft::block::{FLOWING_LAVA, FLOWING_WATER, LAVA, WATER};
+use nanocraft::level::{Weather, MOON_PHASES};
+use nanocraft::world::BlockState;
+use nanocraft::worldgen::random::JavaRandom;
+
+use crate::{compile_shader, link_program};
+
+/// The sky over plains, vanilla works it out from the biome's temperature
+const DAY_SKY: [f32; 3] = [0.471, 0.655, 1.0];
+/// How far out the sun, moon and stars are, and how big the sun and moon are at that distance
+const SKY_RADIUS: f32 = 100.0;
+const SUN_SIZE: f32 = 30.0;
+const MOON_SIZE: f32 = 20.0;
+/// Vanilla's stars, the same ones in every world
+const STAR_SEED: i64 = 10842;
+const STAR_ATTEMPTS: usize = 1500;
+const FLOATS_PER_VERTEX: usize = 5;
+const QUAD_VERTICES: i32 = 6;
+/// Vanilla's furthest render distance, where the fog colour stops being pulled towards the sky's
+const MAX_FOG_DISTANCE: f32 = 32.0;
+/// How far you can see in water and lava
+const WATER_FOG_END: f32 = 24.0;
+const LAVA_FOG_END: f32 = 2.0;
+
+/// Which of the bodies the body shader is drawing
+const SUN: i32 = 0;
+const MOON: i32 = 1;
+const STARS: i32 = 2;
+
+/// The colours of the sky for a time of day and weather
+pub struct SkyColors {
+    pub sky: Vector3<f32>,
+    /// What the sky fades into at the horizon
+    pub fog: Vector3<f32>,
+    /// The glow on the horizon around the sun while it rises and sets, alpha is how strong it is
+    pub sunrise: Vector4<f32>,
+    pub stars: f32,
+    /// How much of the sun, moon and stars shows through the clouds
+    pub bodies: f32,
+}
+
+impl SkyColors {
+    /// The fog is pulled towards the sky colour the shorter `render_distance` is, so there's less
+    /// of a line where the world ends
+    pub fn new(celestial_angle: f32, weather: &Weather, render_distance: i32) -> Self {
+        let cos = (celestial_angle * PI * 2.0).cos();
+        let daylight = (cos * 2.0 + 0.5).clamp(0.0, 1.0);
+        let mut sky = Vector3::from(DAY_SKY) * daylight;
+        let mut fog = Vector3::new(
+            0.7529412 * (daylight * 0.94 + 0.06),
+            0.84705883 * (daylight * 0.94 + 0.06),
+            daylight * 0.91 + 0.09,
+        );
+
+        // rain washes the colour out of the sky and darkens the fog, thunder even more so
+        if weather.raining {
+            sky = sky * 0.25 + Vector3::repeat(luminance(&sky) * 0.6 * 0.75);
+            fog.component_mul_assign(&Vector3::new(0.5, 0.5, 0.6));
+        }
+        if weather.thundering {
+            sky = sky * 0.25 + Vector3::repeat(luminance(&sky) * 0.2 * 0.75);
+            fog *= 0.5;
+        }
+
+        let towards_sky = 1.0 - (0.25 + 0.75 * render_distance as f32 / MAX_FOG_DISTANCE).powf(0.25);
+        fog += (sky - fog) * towards_sky;
+
+        let sunrise = if cos.abs() <= 0.4 {
+            let t = cos / 0.4 * 0.5 + 0.5;
+            let alpha = 1.0 - (1.0 - (t * PI).sin()) * 0.99;
+            Vector4::new(t * 0.3 + 0.7, t * t * 0.7 + 0.2, 0.2, alpha * alpha)
+        } else {
+            Vector4::zeros()
+        };
+        let stars = (1.0 - (cos * 2.0 + 0.25)).clamp(0.0, 1.0);
+        let bodies = if weather.raining { 0.0 } else { 1.0 };
+
+        Self { sky, fog, sunrise, stars: stars * stars * 0.5, bodies }
+    }
+}
+
+/// What the world fades into with distance, linearly from `start` to `end` blocks away
+pub struct Fog {
+    pub color: Vector3<f32>,
+    pub start: f32,
+    pub end: f32,
+    /// Under water and in lava the sky doesn't show through, it's all fog
+    pub hides_sky: bool,
+}
+
+impl Fog {
+    /// Fades to the sky's fog colour at `view_distance`, or much sooner if the camera is in water
+    /// or lava, `eye_block` being the block it's in
+    pub fn new(colors: &SkyColors, view_distance: f32, eye_block: BlockState) -> Self {
+        // liquids keep their level in the metadata, so only the ids are compared
+        let in_any = |blocks: [BlockState; 2]| blocks.iter().any(|block| block.id() == eye_block.id());
+        if in_any([WATER, FLOWING_WATER]) {
+            Self { color: Vector3::new(0.02, 0.02, 0.2), start: 0.0, end: WATER_FOG_END.min(view_distance), hides_sky: true }
+        } else if in_any([LAVA, FLOWING_LAVA]) {
+            Self { color: Vector3::new(0.6, 0.1, 0.0), start: 0.0, end: LAVA_FOG_END, hides_sky: true }
+        } else {
+            Self { color: colors.fog, start: view_distance * 0.75, end: view_distance, hides_sky: false }
+        }
+    }
+}
+
+fn luminance(color: &Vector3<f32>) -> f32 {
+    color.x * 0.3 + color.y * 0.59 + color.z * 0.11
+}
+
+/// Draws the sky gradient and everything in it.
+pub struct SkyRenderer {
+    gradient_program: GLuint,
+    body_program: GLuint,
+    /// The gradient is a single triangle made up in its vertex shader, but GL still wants a VAO bound
+    gradient_vao: GLuint,
+    vao: GLuint,
+    vbo: GLuint,
+    star_vertex_count: i32,
+}
+
+impl SkyRenderer {
+    pub fn new() -> Self {
+        let gradient_program = link_program(
+            compile_shader(GRADIENT_VERTEX_SHADER_SOURCE, gl::VERTEX_SHADER),
+            compile_shader(GRADIENT_FRAGMENT_SHADER_SOURCE, gl::FRAGMENT_SHADER),
+        );
+        let body_program = link_program(
+            compile_shader(BODY_VERTEX_SHADER_SOURCE, gl::VERTEX_SHADER),
+            compile_shader(BODY_FRAGMENT_SHADER_SOURCE, gl::FRAGMENT_SHADER),
+        );
+
+        // the sun straight up and the moon straight down, the sky gets turned to move them
+        let mut vertices = Vec::new();
+        push_quad(&mut vertices, SKY_RADIUS, SUN_SIZE);
+        push_quad(&mut vertices, -SKY_RADIUS, MOON_SIZE);
+        push_stars(&mut vertices);
+        let star_vertex_count = (vertices.len() / FLOATS_PER_VERTEX) as i32 - QUAD_VERTICES * 2;
+
+        let (mut gradient_vao, mut vao, mut vbo) = (0, 0, 0);
+        let stride = (FLOATS_PER_VERTEX * std::mem::size_of::<f32>()) as i32;
+        unsafe {
+            gl::GenVertexArrays(1, &mut gradient_vao);
+            gl::GenVertexArrays(1, &mut vao);
+            gl::GenBuffers(1, &mut vbo);
+            gl::BindVertexArray(vao);
+            gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
+            gl::BufferData(
+                gl::ARRAY_BUFFER,
+                (vertices.len() * std::mem::size_of::<f32>()) as isize,
+                vertices.as_ptr() as *const _,
+                gl::STATIC_DRAW,
+            );
+            gl::VertexAttribPointer(0, 3, gl::FLOAT, gl::FALSE, stride, std::ptr::null());
+            gl::EnableVertexAttribArray(0);
+            // where on the quad, -1 to 1 both ways
+            gl::VertexAttribPointer(1, 2, gl::FLOAT, gl::FALSE, stride, (3 * std::mem::size_of::<f32>()) as *const _);
+            gl::EnableVertexAttribArray(1);
+            gl::BindVertexArray(0);
+        }
+
+        Self { gradient_program, body_program, gradient_vao, vao, vbo, star_vertex_count }
+    }
+
+    /// Covers the whole screen, so it goes before anything else. Only the rotation of `view` is used,
+    /// the sky is always the same distance away.
+    pub unsafe fn draw(&self, view: &Matrix4<f32>, projection: &Matrix4<f32>, colors: &SkyColors, celestial_angle: f32, moon_phase: u32) {
+        let mut rotation = *view;
+        rotation.fixed_view_mut::<3, 1>(0, 3).fill(0.0);
+        let inverse = (projection * rotation).try_inverse().unwrap_or_else(Matrix4::identity);
+        let angle = celestial_angle * PI * 2.0;
+        let sun = Vector3::new(-angle.sin(), angle.cos(), 0.0);
+
+        gl::Disable(gl::DEPTH_TEST);
+        gl::DepthMask(gl::FALSE);
+
+        gl::UseProgram(self.gradient_program);
+        gl::UniformMatrix4fv(uniform(self.gradient_program, "inverseViewProjection"), 1, gl::FALSE, inverse.as_ptr());
+        gl::Uniform3fv(uniform(self.gradient_program, "sky"), 1, colors.sky.as_ptr());
+        gl::Uniform3fv(uniform(self.gradient_program, "fog"), 1, colors.fog.as_ptr());
+        gl::Uniform4fv(uniform(self.gradient_program, "sunrise"), 1, colors.sunrise.as_ptr());
+        gl::Uniform3fv(uniform(self.gradient_program, "sun"), 1, sun.as_ptr());
+        gl::BindVertexArray(self.gradient_vao);
+        gl::DrawArrays(gl::TRIANGLES, 0, 3);
+
+        // light only ever gets added to the sky, like vanilla does it
+        gl::Enable(gl::BLEND);
+        gl::BlendFunc(gl::ONE, gl::ONE);
+        gl::UseProgram(self.body_program);
+        let model = Rotation3::from_axis_angle(&Vector3::z_axis(), angle).to_homogeneous();
+        gl::UniformMatrix4fv(uniform(self.body_program, "model"), 1, gl::FALSE, model.as_ptr());
+        gl::UniformMatrix4fv(uniform(self.body_program, "view"), 1, gl::FALSE, rotation.as_ptr());
+        gl::UniformMatrix4fv(uniform(self.body_program, "projection"), 1, gl::FALSE, projection.as_ptr());
+        gl::Uniform1f(uniform(self.body_program, "moonPhase"), moon_phase as f32 / MOON_PHASES as f32 * PI * 2.0);
+        gl::BindVertexArray(self.vao);
+        let body_location = uniform(self.body_program, "body");
+        let brightness_location = uniform(self.body_program, "brightness");
+        gl::Uniform1f(brightness_location, colors.bodies);
+        gl::Uniform1i(body_location, SUN);
+        gl::DrawArrays(gl::TRIANGLES, 0, QUAD_VERTICES);
+        gl::Uniform1i(body_location, MOON);
+        gl::DrawArrays(gl::TRIANGLES, QUAD_VERTICES, QUAD_VERTICES);
+        if colors.stars * colors.bodies > 0.0 {
+            gl::Uniform1f(brightness_location, colors.stars * colors.bodies);
+            gl::Uniform1i(body_location, STARS);
+            gl::DrawArrays(gl::TRIANGLES, QUAD_VERTICES * 2, self.star_vertex_count);
+        }
+
+        gl::Disable(gl::BLEND);
+        gl::DepthMask(gl::TRUE);
+        gl::Enable(gl::DEPTH_TEST);
+    }
+}
+
+impl Drop for SkyRenderer {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteBuffers(1, &self.vbo);
+            gl::DeleteVertexArrays(1, &self.vao);
+            gl::DeleteVertexArrays(1, &self.gradient_vao);
+            gl::DeleteProgram(self.gradient_program);
+            gl::DeleteProgram(self.body_program);
+        }
+    }
+}
+
+unsafe fn uniform(program: GLuint, name: &str) -> GLint {
+    gl::GetUniformLocation(program, CString::new(name).unwrap().as_ptr())
+}
+
+/// A flat square `size` out from the middle each way, `y` above the camera
+fn push_quad(vertices: &mut Vec<f32>, y: f32, size: f32) {
+    for (u, v) in [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)] {
+        vertices.extend_from_slice(&[u * size, y, v * size, u, v]);
+    }
+}
+
+/// Vanilla's star field: random points on a sphere, each a little square facing the middle and
+/// spun by a random amount
+fn push_stars(vertices: &mut Vec<f32>) {
+    let mut random = JavaRandom::new(STAR_SEED);
+    for _ in 0..STAR_ATTEMPTS {
+        let mut direction = Vector3::new(random.next_float() * 2.0 - 1.0, random.next_float() * 2.0 - 1.0, random.next_float() * 2.0 - 1.0);
+        let size = 0.15 + random.next_float() * 0.1;
+        // only points inside the ball, so pushing them out to its surface spreads them evenly
+        let length_squared = direction.norm_squared();
+        if length_squared >= 1.0 || length_squared <= 0.01 {
+            continue;
+        }
+        direction /= length_squared.sqrt();
+
+        let center = direction * SKY_RADIUS;
+        let yaw = direction.x.atan2(direction.z);
+        let pitch = (direction.x * direction.x + direction.z * direction.z).sqrt().atan2(direction.y);
+        let spin = random.next_double() as f32 * PI * 2.0;
+        let corners: [Vector3<f32>; 4] = array::from_fn(|corner| {
+            let a = ((corner as i32 & 2) - 1) as f32 * size;
+            let b = (((corner as i32 + 1) & 2) - 1) as f32 * size;
+            let (a, b) = (a * spin.cos() - b * spin.sin(), b * spin.cos() + a * spin.sin());
+            let out = -a * pitch.cos();
+            center + Vector3::new(out * yaw.sin() - b * yaw.cos(), a * pitch.sin(), b * yaw.sin() + out * yaw.cos())
+        });
+        for i in [0, 1, 2, 0, 2, 3] {
+            vertices.extend_from_slice(&[corners[i].x, corners[i].y, corners[i].z, 0.0, 0.0]);
+        }
+    }
+}
+
+const GRADIENT_VERTEX_SHADER_SOURCE: &str = r#"
+    #version 330 core
+
+    out vec2 Clip;
+
+    void main() {
+        // one triangle big enough to cover the screen
+        Clip = vec2((gl_VertexID << 1) & 2, gl_VertexID & 2) * 2.0 - 1.0;
+        gl_Position = vec4(Clip, 0.0, 1.0);
+    }
+"#;
+
+const GRADIENT_FRAGMENT_SHADER_SOURCE: &str = r#"
+    #version 330 core
+
+    in vec2 Clip;
+
+    uniform mat4 inverseViewProjection;
+    uniform vec3 sky;
+    uniform vec3 fog;
+    uniform vec4 sunrise;
+    uniform vec3 sun;
+
+    out vec4 FragColor;
+
+    void main() {
+        vec4 far = inverseViewProjection * vec4(Clip, 1.0, 1.0);
+        vec3 direction = normalize(far.xyz / far.w);
+        vec3 color = mix(fog, sky, smoothstep(0.0, 0.4, direction.y));
+
+        // the sunrise glows along the horizon on the sun's side
+        vec2 across = normalize(direction.xz + vec2(1e-5));
+        float toward = max(dot(across, normalize(sun.xz + vec2(1e-5, 0.0))), 0.0);
+        float glow = sunrise.a * pow(toward, 4.0) * (1.0 - smoothstep(0.0, 0.5, abs(direction.y)));
+        FragColor = vec4(mix(color, sunrise.rgb, glow), 1.0);
+    }
+"#;
+
+const BODY_VERTEX_SHADER_SOURCE: &str = r#"
+    #version 330 core
+    layout (location = 0) in vec3 aPos;
+    layout (location = 1) in vec2 aCorner;
+
+    out vec2 Corner;
+
+    uniform mat4 model;
+    uniform mat4 view;
+    uniform mat4 projection;
+
+    void main() {
+        Corner = aCorner;
+        vec4 position = projection * view * model * vec4(aPos, 1.0);
+        // depth doesn't matter for the sky, this just keeps the far plane from cutting it off
+        gl_Position = vec4(position.xy, 0.0, position.w);
+    }
+"#;
+
+const BODY_FRAGMENT_SHADER_SOURCE: &str = r#"
+    #version 330 core
+
+    in vec2 Corner;
+
+    uniform int body;
+    uniform float brightness;
+    // the angle the moon is lit from, 0 lights the side facing us
+    uniform float moonPhase;
+
+    out vec4 FragColor;
+
+    void main() {
+        vec3 color = vec3(1.0);
+        if (body == 0) {
+            // a square sun with a soft glow around it
+            float core = 1.0 - smoothstep(0.3, 0.34, max(abs(Corner.x), abs(Corner.y)));
+            float glow = pow(max(1.0 - length(Corner), 0.0), 2.0) * 0.5;
+            color = vec3(1.0, 1.0, 0.8) * core + vec3(1.0, 0.75, 0.35) * glow;
+        } else if (body == 1) {
+            float radius = length(Corner) / 0.5;
+            if (radius > 1.0) {
+                discard;
+            }
+            // lit like a ball, the dark side still shows a little against the night
+            vec3 normal = vec3(Corner / 0.5, sqrt(1.0 - radius * radius));
+            float lit = smoothstep(-0.05, 0.05, dot(normal, vec3(sin(moonPhase), 0.0, cos(moonPhase))));
+            color = vec3(0.85, 0.88, 0.95) * mix(0.06, 1.0, lit);
+        }
+        FragColor = vec4(color * brightness, 1.0);
+    }
+"#;
@@ -0,0 +1,214 @@
+//! Chunks as 1.8.9 stores them in region files: a root compound with a `Level` compound holding
+//! the blocks in `Sections`, the biomes and a height map.
+
+use std::io;
+
+use serde::{Deserialize, Serialize};
+
+use crate::invalid;
+use crate::nbt::convert::byte_array;
+use crate::nbt::{self, from_tag, to_tag, Compound, List, Tag};
+use crate::world::{BlockState, Chunk, ChunkPos, ChunkSection, LightType, SectionLight, CHUNK_WIDTH, SECTION_COUNT, SECTION_HEIGHT, SECTION_VOLUME};
+
+/// Anvil's format version, vanilla won't load chunks without it
+const VERSION: i8 = 1;
+
+/// Everything is by `y << 8 | z << 4 | x` like `ChunkSection`. The nibble arrays put even
+/// indices in the low half of each byte. `Add` holds the top four bits of block ids past 255
+/// and is left out when there aren't any.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Section {
+    y: i8,
+    #[serde(with = "byte_array")]
+    blocks: Vec<u8>,
+    #[serde(with = "byte_array", default)]
+    add: Option<Vec<u8>>,
+    #[serde(with = "byte_array")]
+    data: Vec<u8>,
+    #[serde(with = "byte_array")]
+    block_light: Vec<u8>,
+    #[serde(with = "byte_array", default)]
+    sky_light: Vec<u8>,
+}
+
+/// The root tag to save `chunk` as. Passing in what's already saved for it keeps everything we
+/// don't simulate, like entities and tile entities, so saving over a vanilla world loses nothing.
+pub fn chunk_to_nbt(chunk: &Chunk, ticks: u64, existing: Option<Tag>) -> io::Result<Tag> {
+    let mut root = match existing {
+        Some(Tag::Compound(root)) => root,
+        _ => Compound::new(),
+    };
+    let mut level = match root.remove("Level") {
+        Some(Tag::Compound(level)) => level,
+        _ => Compound::new(),
+    };
+
+    let mut sections = List::of(nbt::COMPOUND);
+    for y in 0..SECTION_COUNT {
+        // empty sections are still saved when there's light in them that open air wouldn't have
+        let lit = LightType::ALL.iter().any(|&light| *chunk.section_light(light, y) != SectionLight::Uniform(light.default_level()));
+        let section = match chunk.section(y) {
+            Some(section) => section,
+            None if lit => &ChunkSection::new(),
+            None => continue,
+        };
+        let mut blocks = vec![0; SECTION_VOLUME];
+        let mut add = vec![0; SECTION_VOLUME / 2];
+        let mut data = vec![0; SECTION_VOLUME / 2];
+        for (i, state) in section.blocks().iter().enumerate() {
+            blocks[i] = state.id() as u8;
+            add[i / 2] |= ((state.id() >> 8) as u8 & 15) << (i % 2 * 4);
+            data[i / 2] |= (state.meta() & 15) << (i % 2 * 4);
+        }
+        let section = Section {
+            y: y as i8,
+            blocks,
+            add: add.iter().any(|&nibbles| nibbles != 0).then_some(add),
+            data,
+            block_light: chunk.section_light(LightType::Block, y).to_bytes(),
+            sky_light: chunk.section_light(LightType::Sky, y).to_bytes(),
+        };
+        sections.push(to_tag(&section)?).unwrap();
+    }
+
+    level.insert("xPos", chunk.pos.x);
+    level.insert("zPos", chunk.pos.z);
+    level.insert("LastUpdate", ticks as i64);
+    // without it vanilla lights the chunk itself
+    level.insert("LightPopulated", chunk.light_populated);
+    // decorations are already in, vanilla would add a second round of trees otherwise
+    level.insert("TerrainPopulated", true);
+    level.insert("V", VERSION);
+    if !level.contains("InhabitedTime") {
+        level.insert("InhabitedTime", 0i64);
+    }
+    level.insert("Biomes", chunk.biomes.iter().map(|&biome| biome as i8).collect::<Vec<i8>>());
+    level.insert("HeightMap", height_map(chunk));
+    level.insert("Sections", sections);
+    for name in ["Entities", "TileEntities"] {
+        if !level.contains(name) {
+            level.insert(name, List::of(nbt::COMPOUND));
+        }
+    }
+
+    root.insert("Level", level);
+    Ok(Tag::Compound(root))
+}
+
+pub fn chunk_from_nbt(tag: &Tag) -> io::Result<Chunk> {
+    let level = tag.as_compound().and_then(|root| root.get_compound("Level")).ok_or_else(|| invalid("chunk has no Level compound"))?;
+    let (Some(x), Some(z)) = (level.get_i32("xPos"), level.get_i32("zPos")) else {
+        return Err(invalid("chunk is missing its position"));
+    };
+    let mut chunk = Chunk::new(ChunkPos::new(x, z));
+
+    // vanilla leaves them out of chunks that never got far enough to have biomes
+    if let Some(biomes) = level.get_byte_array("Biomes").filter(|biomes| biomes.len() == chunk.biomes.len()) {
+        for (biome, &saved) in chunk.biomes.iter_mut().zip(biomes) {
+            *biome = saved as u8;
+        }
+    }
+
+    let mut sections: Vec<Section> = match level.get("Sections") {
+        Some(sections) => from_tag(sections)?,
+        None => Vec::new(),
+    };
+    // some tools save sections below and above the world, there's nowhere to put those
+    sections.retain(|section| (0..SECTION_COUNT as i32).contains(&(section.y as i32)));
+    // sections that aren't saved are open sky above the highest one that is and dark below,
+    // same as vanilla reads them
+    chunk.light_populated = level.get_bool("LightPopulated").unwrap_or(false);
+    if chunk.light_populated {
+        let top = sections.iter().map(|section| section.y as usize + 1).max().unwrap_or(0);
+        for y in 0..top {
+            *chunk.section_light_mut(LightType::Sky, y) = SectionLight::Uniform(0);
+        }
+    }
+    for section in sections {
+        let section_y = section.y as usize;
+        if section.blocks.len() != SECTION_VOLUME || section.data.len() != SECTION_VOLUME / 2 {
+            return Err(invalid(format!("section {} of chunk {x},{z} has the wrong size arrays", section.y)));
+        }
+        let add = section.add.filter(|add| add.len() == SECTION_VOLUME / 2);
+        for i in 0..SECTION_VOLUME {
+            let nibble = |array: &[u8]| array[i / 2] >> (i % 2 * 4) & 15;
+            let id = section.blocks[i] as u16 | add.as_deref().map_or(0, |add| (nibble(add) as u16) << 8);
+            let state = BlockState::new(id, nibble(&section.data));
+            if !state.is_air() {
+                chunk.set_block(i & 15, section_y * SECTION_HEIGHT + (i >> 8), i >> 4 & 15, state);
+            }
+        }
+        if chunk.light_populated {
+            // a section missing its light leaves the chunk needing to be lit again
+            let block_light = SectionLight::from_bytes(&section.block_light);
+            let sky_light = SectionLight::from_bytes(&section.sky_light);
+            match (block_light, sky_light) {
+                (Some(block_light), Some(sky_light)) => {
+                    *chunk.section_light_mut(LightType::Block, section_y) = block_light;
+                    *chunk.section_light_mut(LightType::Sky, section_y) = sky_light;
+                }
+                _ => chunk.light_populated = false,
+            }
+        }
+    }
+    // it's exactly what's saved, until something changes
+    chunk.modified = false;
+    Ok(chunk)
+}
+
+/// The y above the highest block in each column that dims sunlight
+fn height_map(chunk: &Chunk) -> Vec<i32> {
+    (0..CHUNK_WIDTH * CHUNK_WIDTH).map(|i| chunk.height(i & 15, i >> 4) as i32).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::{STONE, WATER};
+
+    #[test]
+    fn chunks_round_trip() {
+        let mut chunk = Chunk::new(ChunkPos::new(-3, 12));
+        chunk.set_block(0, 0, 0, STONE);
+        chunk.set_block(15, 255, 15, WATER);
+        // an id past 255 needs the Add array
+        chunk.set_block(5, 70, 9, BlockState::new(300, 7));
+        chunk.set_biome(4, 4, 21);
+        chunk.light_populated = true;
+        chunk.set_light(LightType::Block, 5, 71, 9, 14);
+        chunk.set_light(LightType::Sky, 5, 69, 9, 3);
+
+        let loaded = chunk_from_nbt(&chunk_to_nbt(&chunk, 100, None).unwrap()).unwrap();
+        assert_eq!(loaded.pos, chunk.pos);
+        for (x, y, z) in [(0, 0, 0), (15, 255, 15), (5, 70, 9), (1, 1, 1)] {
+            assert_eq!(loaded.get_block(x, y, z), chunk.get_block(x, y, z));
+        }
+        assert_eq!(loaded.biomes, chunk.biomes);
+        assert!(loaded.light_populated);
+        assert_eq!(loaded.light(LightType::Block, 5, 71, 9), 14);
+        assert_eq!(loaded.light(LightType::Sky, 5, 69, 9), 3);
+    }
+
+    #[test]
+    fn sections_outside_the_world_are_dropped() {
+        let mut chunk = Chunk::new(ChunkPos::new(0, 0));
+        chunk.set_block(1, 17, 1, STONE);
+        chunk.light_populated = true;
+        let mut tag = chunk_to_nbt(&chunk, 0, None).unwrap();
+        let level = tag.as_compound_mut().and_then(|root| root.get_compound_mut("Level")).unwrap();
+        let mut sections = level.get_list("Sections").unwrap().clone();
+        for y in [-1, 16, 127] {
+            let mut section = sections.get(0).unwrap().clone();
+            section.as_compound_mut().unwrap().insert("Y", y as i8);
+            sections.push(section).unwrap();
+        }
+        level.insert("Sections", sections);
+
+        let loaded = chunk_from_nbt(&tag).unwrap();
+        assert_eq!(loaded.sections().map(|(y, _)| y).collect::<Vec<_>>(), [1]);
+        assert_eq!(loaded.get_block(1, 17, 1), STONE);
+        // open sky above the highest real section
+        assert_eq!(loaded.light(LightType::Sky, 1, 40, 1), 15);
+    }
+}
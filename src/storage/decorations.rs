@@ -0,0 +1,71 @@
+//! decorations.dat: the bits of trees and such that reached into chunks which haven't been
+//! generated yet, so they still go in once those chunks are. Vanilla has nothing like it, its
+//! decorations never leave the chunks around the one being decorated.
+
+use std::io;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::invalid;
+use crate::nbt::{from_tag, to_tag, Compound};
+use crate::storage::level::{read_root, write_root};
+use crate::world::{BlockPos, BlockState};
+use crate::worldgen::decorator::{PendingDecorations, Placement, Replace};
+
+#[derive(Serialize, Deserialize)]
+struct SavedPlacement {
+    x: i32,
+    y: i32,
+    z: i32,
+    /// `id << 4 | meta`, like a chunk section's blocks
+    #[serde(rename = "Block")]
+    block: i16,
+    #[serde(rename = "Replace")]
+    replace: i8,
+}
+
+/// The whole list goes every time, it's only what's along the edge of the generated world
+pub fn save_decorations(decorations: &PendingDecorations, path: &Path) -> io::Result<()> {
+    let placements: Vec<SavedPlacement> = decorations.placements()
+        .map(|placement| SavedPlacement {
+            x: placement.pos.x,
+            y: placement.pos.y,
+            z: placement.pos.z,
+            block: placement.state.0 as i16,
+            replace: match placement.replace {
+                Replace::Air => 0,
+                Replace::Stone => 1,
+                Replace::Soft => 2,
+            },
+        })
+        .collect();
+    let mut root = Compound::new();
+    root.insert("Placements", to_tag(&placements)?);
+    write_root(path, root)
+}
+
+/// Nothing waiting if there's no file yet
+pub fn load_decorations(path: &Path) -> io::Result<PendingDecorations> {
+    let mut decorations = PendingDecorations::new();
+    let Some(root) = read_root(path)? else {
+        return Ok(decorations);
+    };
+    let Some(placements) = root.get("Placements") else {
+        return Ok(decorations);
+    };
+    for saved in from_tag::<Vec<SavedPlacement>>(placements)? {
+        let replace = match saved.replace {
+            0 => Replace::Air,
+            1 => Replace::Stone,
+            2 => Replace::Soft,
+            other => return Err(invalid(format!("{} has a placement replacing {other}", path.display()))),
+        };
+        decorations.defer(Placement {
+            pos: BlockPos::new(saved.x, saved.y, saved.z),
+            state: BlockState(saved.block as u16),
+            replace,
+        });
+    }
+    Ok(decorations)
+}
@@ -0,0 +1,272 @@
+//! level.dat: a gzipped compound with everything about the world in `Data`, including the
+//! single player's `Player` compound.
+
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, ErrorKind, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use nalgebra::Vector3;
+use serde::{Deserialize, Serialize};
+
+use crate::game::Game;
+use crate::invalid;
+use crate::nbt::{self, from_tag, to_tag, Compound, Compression, Tag};
+use crate::storage::ChunkStorage;
+use crate::worldgen::decorator::PendingDecorations;
+
+/// Anvil worlds, what 1.8.9 writes and expects
+const VERSION: i32 = 19133;
+/// The only terrain we generate is vanilla's default
+pub const GENERATOR_NAME: &str = "default";
+const GAME_TYPE_CREATIVE: i32 = 1;
+
+/// The parts of `Data` we read and write, anything else in the file is left as it was.
+/// Everything has a default since older saves are missing some of it.
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+struct LevelData {
+    #[serde(rename = "RandomSeed")]
+    seed: i64,
+    #[serde(rename = "SpawnX")]
+    spawn_x: i32,
+    #[serde(rename = "SpawnY")]
+    spawn_y: i32,
+    #[serde(rename = "SpawnZ")]
+    spawn_z: i32,
+    #[serde(rename = "Time")]
+    time: i64,
+    #[serde(rename = "DayTime")]
+    day_time: i64,
+    #[serde(rename = "generatorName")]
+    generator_name: String,
+    #[serde(rename = "GameRules")]
+    game_rules: BTreeMap<String, String>,
+    raining: bool,
+    #[serde(rename = "rainTime")]
+    rain_time: i32,
+    thundering: bool,
+    #[serde(rename = "thunderTime")]
+    thunder_time: i32,
+    #[serde(rename = "clearWeatherTime")]
+    clear_weather_time: i32,
+    #[serde(rename = "LevelName")]
+    level_name: String,
+    #[serde(rename = "LastPlayed")]
+    last_played: i64,
+    version: i32,
+    #[serde(rename = "GameType")]
+    game_type: i32,
+    initialized: bool,
+}
+
+impl Default for LevelData {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            spawn_x: 0,
+            spawn_y: 64,
+            spawn_z: 0,
+            time: 0,
+            day_time: 0,
+            generator_name: GENERATOR_NAME.to_string(),
+            game_rules: BTreeMap::new(),
+            raining: false,
+            rain_time: 0,
+            thundering: false,
+            thunder_time: 0,
+            clear_weather_time: 0,
+            level_name: "world".to_string(),
+            last_played: 0,
+            version: VERSION,
+            game_type: GAME_TYPE_CREATIVE,
+            initialized: true,
+        }
+    }
+}
+
+/// The parts of the `Player` compound we keep, the rest (inventory, health and so on) is kept
+/// as it was. Rotation is vanilla's: yaw 0 faces +z and pitch is positive looking down.
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+struct PlayerData {
+    #[serde(rename = "Pos")]
+    position: [f64; 3],
+    #[serde(rename = "Motion")]
+    motion: [f64; 3],
+    #[serde(rename = "Rotation")]
+    rotation: [f32; 2],
+    #[serde(rename = "OnGround")]
+    on_ground: bool,
+    #[serde(rename = "Dimension")]
+    dimension: i32,
+    abilities: Abilities,
+}
+
+impl Default for PlayerData {
+    fn default() -> Self {
+        Self { position: [0.0; 3], motion: [0.0; 3], rotation: [0.0; 2], on_ground: false, dimension: 0, abilities: Abilities::default() }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+struct Abilities {
+    flying: bool,
+    #[serde(rename = "mayfly")]
+    may_fly: bool,
+    #[serde(rename = "instabuild")]
+    insta_build: bool,
+    invulnerable: bool,
+    #[serde(rename = "mayBuild")]
+    may_build: bool,
+    #[serde(rename = "flySpeed")]
+    fly_speed: f32,
+    #[serde(rename = "walkSpeed")]
+    walk_speed: f32,
+}
+
+impl Default for Abilities {
+    fn default() -> Self {
+        // creative, which is what double tapping jump to fly amounts to
+        Self { flying: false, may_fly: true, insta_build: true, invulnerable: true, may_build: true, fly_speed: 0.05, walk_speed: 0.1 }
+    }
+}
+
+/// Writes `game` over whatever level.dat is at `path`, keeping the fields we don't know about.
+/// The `Player` compound is only touched `with_player`. Goes through a temporary file so a
+/// crash halfway never leaves a broken level.dat behind.
+pub fn save_level(game: &Game, path: &Path, with_player: bool) -> io::Result<()> {
+    let mut root = match read_root(path) {
+        Ok(Some(root)) => root,
+        // a broken level.dat is about to be replaced anyway
+        Ok(None) | Err(_) => Compound::new(),
+    };
+    let mut data = match root.remove("Data") {
+        Some(Tag::Compound(data)) => data,
+        _ => Compound::new(),
+    };
+    let existing: LevelData = from_tag(&Tag::Compound(data.clone())).unwrap_or_default();
+
+    let level = LevelData {
+        seed: game.seed(),
+        spawn_x: game.spawn.x.floor() as i32,
+        spawn_y: game.spawn.y.floor() as i32,
+        spawn_z: game.spawn.z.floor() as i32,
+        time: game.ticks as i64,
+        day_time: game.day_time as i64,
+        generator_name: GENERATOR_NAME.to_string(),
+        game_rules: game.rules.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect(),
+        raining: game.weather.raining,
+        rain_time: game.weather.rain_time,
+        thundering: game.weather.thundering,
+        thunder_time: game.weather.thunder_time,
+        clear_weather_time: game.weather.clear_weather_time,
+        level_name: existing.level_name,
+        last_played: SystemTime::now().duration_since(UNIX_EPOCH).map(|time| time.as_millis() as i64).unwrap_or(0),
+        version: VERSION,
+        game_type: existing.game_type,
+        initialized: true,
+    };
+    merge(&mut data, to_tag(&level)?);
+
+    if with_player {
+        let player = &game.player;
+        let mut player_tag = match data.remove("Player") {
+            Some(Tag::Compound(player)) => player,
+            _ => Compound::new(),
+        };
+        let player_data = PlayerData {
+            position: [player.position.x as f64, player.position.y as f64, player.position.z as f64],
+            motion: [player.velocity.x as f64, player.velocity.y as f64, player.velocity.z as f64],
+            // our yaw is the camera's, 0 facing +x
+            rotation: [player.yaw - 90.0, -player.pitch],
+            on_ground: player.on_ground,
+            dimension: 0,
+            abilities: Abilities { flying: player.flying, ..Abilities::default() },
+        };
+        merge(&mut player_tag, to_tag(&player_data)?);
+        data.insert("Player", player_tag);
+    }
+    root.insert("Data", data);
+
+    write_root(path, root)
+}
+
+/// Writes a gzipped compound the way vanilla does, next to the file first and then over it so a
+/// crash halfway leaves the old one
+pub(super) fn write_root(path: &Path, root: Compound) -> io::Result<()> {
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push("_new");
+    let mut out = BufWriter::new(File::create(&temporary)?);
+    nbt::write_compressed(&mut out, "", &Tag::Compound(root), Compression::Gzip)?;
+    out.flush()?;
+    out.get_ref().sync_all()?;
+    drop(out);
+    fs::rename(temporary, path)
+}
+
+/// Picks a saved game back up with its chunks coming out of `storage` and the decorations that
+/// were still waiting on chunks, `None` if there's no level.dat
+pub fn load_level(path: &Path, storage: ChunkStorage, decorations: PendingDecorations) -> io::Result<Option<Game>> {
+    let Some(root) = read_root(path)? else {
+        return Ok(None);
+    };
+    let data = root.get("Data").ok_or_else(|| invalid(format!("{} has no Data compound", path.display())))?;
+    let level: LevelData = from_tag(data)?;
+    if level.generator_name != GENERATOR_NAME {
+        eprintln!("{} uses the {:?} generator, new chunks will get default terrain", path.display(), level.generator_name);
+    }
+
+    let mut game = Game::with_storage(level.seed, Some(storage), decorations);
+    game.spawn = Vector3::new(level.spawn_x as f32 + 0.5, level.spawn_y as f32, level.spawn_z as f32 + 0.5);
+    game.ticks = level.time.max(0) as u64;
+    game.day_time = level.day_time.max(0) as u64;
+    for (name, value) in &level.game_rules {
+        game.rules.set(name, value);
+    }
+    game.weather.raining = level.raining;
+    game.weather.rain_time = level.rain_time;
+    game.weather.thundering = level.thundering;
+    game.weather.thunder_time = level.thunder_time;
+    game.weather.clear_weather_time = level.clear_weather_time;
+
+    let player = &mut game.player;
+    match data.as_compound().and_then(|data| data.get("Player")) {
+        Some(tag) => {
+            let saved: PlayerData = from_tag(tag)?;
+            player.position = Vector3::new(saved.position[0] as f32, saved.position[1] as f32, saved.position[2] as f32);
+            player.velocity = Vector3::new(saved.motion[0] as f32, saved.motion[1] as f32, saved.motion[2] as f32);
+            player.yaw = saved.rotation[0] + 90.0;
+            player.pitch = -saved.rotation[1];
+            player.on_ground = saved.on_ground;
+            player.flying = saved.abilities.flying;
+        }
+        // multiplayer worlds keep players in their own files, so start at spawn like a new player would
+        None => player.position = game.spawn,
+    }
+    player.previous_position = player.position;
+    Ok(Some(game))
+}
+
+pub(super) fn read_root(path: &Path) -> io::Result<Option<Compound>> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err),
+    };
+    match nbt::read_compressed(BufReader::new(file))? {
+        (_, Tag::Compound(root), _) => Ok(Some(root)),
+        _ => Err(invalid(format!("{} isn't a compound", path.display()))),
+    }
+}
+
+/// Puts every tag of `update` into `into`, replacing ones with the same name
+fn merge(into: &mut Compound, update: Tag) {
+    if let Tag::Compound(update) = update {
+        for (name, tag) in update.iter() {
+            into.insert(name, tag.clone());
+        }
+    }
+}
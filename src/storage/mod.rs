@@ -0,0 +1,150 @@
+//! Saves laid out like vanilla 1.8.9's, so vanilla worlds open here and ours open in vanilla:
+//! chunks go into Anvil region files under `region/` and the rest of the world and the player
+//! into `level.dat`. Decorations still waiting on chunks go in a `decorations.dat` of our own.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::game::{Game, LOAD_RADIUS};
+use crate::invalid;
+use crate::storage::anvil::{chunk_from_nbt, chunk_to_nbt};
+use crate::storage::decorations::{load_decorations, save_decorations};
+use crate::storage::level::{load_level, save_level};
+use crate::storage::region::{region_file_name, RegionFile};
+use crate::world::{BlockPos, Chunk, ChunkPos};
+
+pub mod anvil;
+pub mod decorations;
+pub mod level;
+pub mod region;
+
+const LEVEL_FILE: &str = "level.dat";
+const DECORATIONS_FILE: &str = "decorations.dat";
+const REGION_DIR: &str = "region";
+
+/// The region files of a save, opened as the chunks in them are needed. Loading a chunk that
+/// was never saved leaves its region alone, saving one creates the region if it has to.
+pub struct ChunkStorage {
+    region_dir: PathBuf,
+    regions: HashMap<(i32, i32), RegionFile>,
+}
+
+impl ChunkStorage {
+    pub fn new(dir: &Path) -> Self {
+        Self { region_dir: dir.join(REGION_DIR), regions: HashMap::new() }
+    }
+
+    /// `None` if the chunk was never saved
+    pub fn load_chunk(&mut self, pos: ChunkPos) -> io::Result<Option<Chunk>> {
+        let Some(region) = self.region(pos, false)? else {
+            return Ok(None);
+        };
+        let Some(tag) = region.read(pos)? else {
+            return Ok(None);
+        };
+        let chunk = chunk_from_nbt(&tag)?;
+        if chunk.pos != pos {
+            return Err(invalid(format!("chunk {},{} is saved as {},{}", pos.x, pos.z, chunk.pos.x, chunk.pos.z)));
+        }
+        Ok(Some(chunk))
+    }
+
+    pub fn save_chunk(&mut self, chunk: &Chunk, ticks: u64) -> io::Result<()> {
+        let region = self.region(chunk.pos, true)?.unwrap();
+        // a chunk we can't read back is one we'd rather overwrite than keep failing on
+        let existing = region.read(chunk.pos).unwrap_or(None);
+        region.write(chunk.pos, &chunk_to_nbt(chunk, ticks, existing)?)
+    }
+
+    /// Makes sure everything saved so far is on disk
+    pub fn sync(&self) -> io::Result<()> {
+        self.regions.values().try_for_each(RegionFile::sync)
+    }
+
+    fn region(&mut self, pos: ChunkPos, create: bool) -> io::Result<Option<&mut RegionFile>> {
+        let key = (pos.x >> 5, pos.z >> 5);
+        if !self.regions.contains_key(&key) {
+            let path = self.region_dir.join(region_file_name(pos));
+            if !create && !path.is_file() {
+                return Ok(None);
+            }
+            fs::create_dir_all(&self.region_dir)?;
+            self.regions.insert(key, RegionFile::open(&path)?);
+        }
+        Ok(self.regions.get_mut(&key))
+    }
+}
+
+/// Saves the chunks that changed since they were loaded from or last saved to `dir`, and
+/// level.dat. From then on chunks that aren't loaded come from `dir`. Returns how many
+/// chunks were written.
+pub fn save_game(game: &mut Game, dir: &Path) -> io::Result<usize> {
+    save(game, dir, true)
+}
+
+/// `save_game` without the single player, for the server where it's nobody. Whatever player
+/// level.dat already had stays as it was.
+pub fn save_world(game: &mut Game, dir: &Path) -> io::Result<usize> {
+    save(game, dir, false)
+}
+
+fn save(game: &mut Game, dir: &Path, with_player: bool) -> io::Result<usize> {
+    let storage = game.storage.get_or_insert_with(|| ChunkStorage::new(dir));
+    let mut saved = 0;
+    for chunk in game.world.chunks_mut().filter(|chunk| chunk.modified) {
+        storage.save_chunk(chunk, game.ticks)?;
+        chunk.modified = false;
+        saved += 1;
+    }
+    storage.sync()?;
+    save_decorations(&game.decorations, &dir.join(DECORATIONS_FILE))?;
+    save_level(game, &dir.join(LEVEL_FILE), with_player)?;
+    Ok(saved)
+}
+
+/// `None` if there's no world saved in `dir` yet. Only the chunks around the spawn and the
+/// player get loaded, the rest come in as they're needed.
+pub fn load_game(dir: &Path) -> io::Result<Option<Game>> {
+    // before any chunks are generated, some of them are what these were waiting on
+    let decorations = load_decorations(&dir.join(DECORATIONS_FILE))?;
+    let Some(mut game) = load_level(&dir.join(LEVEL_FILE), ChunkStorage::new(dir), decorations)? else {
+        return Ok(None);
+    };
+    // the player might be nowhere near the origin, where the game loaded around
+    let around = BlockPos::new(game.player.position.x.floor() as i32, 0, game.player.position.z.floor() as i32).chunk();
+    game.generate_around(around, LOAD_RADIUS);
+    Ok(Some(game))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::GLASS;
+
+    #[test]
+    fn only_changed_chunks_get_saved_and_the_rest_load_back() {
+        let dir = std::env::temp_dir().join(format!("nanocraft-save-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        let mut game = Game::new(7);
+        let generated = game.world.chunks().count();
+        assert_eq!(save_game(&mut game, &dir).unwrap(), generated);
+        assert_eq!(save_game(&mut game, &dir).unwrap(), 0);
+
+        let pos = BlockPos::new(-20, 100, 37);
+        assert!(game.set_block(pos, GLASS));
+        assert_eq!(save_game(&mut game, &dir).unwrap(), 1);
+
+        let mut loaded = load_game(&dir).unwrap().unwrap();
+        assert_eq!(loaded.world.get_block(pos), GLASS);
+        // nothing came out of the save changed, so there's nothing to write back
+        assert_eq!(save_game(&mut loaded, &dir).unwrap(), 0);
+        // and what was never saved gets generated like it would have been
+        let far = ChunkPos::new(30, 30);
+        loaded.generate_around(far, 0);
+        assert_eq!(loaded.world.chunk(far).unwrap().get_block(3, 60, 3), game.generator.generate_chunk(far).get_block(3, 60, 3));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
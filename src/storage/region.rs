@@ -0,0 +1,293 @@
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use flate2::read::{GzDecoder, ZlibDecoder};
+use flate2::write::ZlibEncoder;
+
+use crate::invalid;
+use crate::nbt::{self, Tag};
+use crate::world::ChunkPos;
+
+/// Chunks along each side of a region
+pub const REGION_WIDTH: i32 = 32;
+const SECTOR_SIZE: usize = 4096;
+const CHUNK_COUNT: usize = (REGION_WIDTH * REGION_WIDTH) as usize;
+/// The offset table and the timestamp table, a sector each
+const HEADER_SECTORS: usize = 2;
+/// A chunk's sector count has to fit in a byte
+const MAX_CHUNK_SECTORS: usize = 255;
+
+const GZIP: u8 = 1;
+const ZLIB: u8 = 2;
+
+/// One `r.X.Z.mca` file, 32x32 chunks stored as compressed NBT in 4 KiB sectors. The first
+/// sector says where each chunk starts and how many sectors it takes, the second when each
+/// was last saved. Chunks that grow past their sectors move to the first gap big enough to
+/// hold them, so space freed by moves and shrinks gets used again.
+pub struct RegionFile {
+    file: File,
+    /// Sector offset << 8 | sector count for every chunk, 0 when it isn't there
+    locations: [u32; CHUNK_COUNT],
+    timestamps: [u32; CHUNK_COUNT],
+    /// Which sectors of the file are taken, headers included
+    used: Vec<bool>,
+}
+
+impl RegionFile {
+    /// Opens a region, creating it if there isn't one yet
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let mut file = OpenOptions::new().read(true).write(true).create(true).truncate(false).open(path)?;
+        let length = file.metadata()?.len() as usize;
+        if length < HEADER_SECTORS * SECTOR_SIZE {
+            // new, or cut short before its header was written
+            file.set_len((HEADER_SECTORS * SECTOR_SIZE) as u64)?;
+        } else if !length.is_multiple_of(SECTOR_SIZE) {
+            // vanilla pads every chunk out to a full sector, but be forgiving of files that weren't
+            file.set_len(length.next_multiple_of(SECTOR_SIZE) as u64)?;
+        }
+
+        let mut header = vec![0; HEADER_SECTORS * SECTOR_SIZE];
+        file.seek(SeekFrom::Start(0))?;
+        file.read_exact(&mut header)?;
+        let table = |i: usize| u32::from_be_bytes(header[i * 4..i * 4 + 4].try_into().unwrap());
+        let locations = std::array::from_fn(table);
+        let timestamps = std::array::from_fn(|i| table(CHUNK_COUNT + i));
+
+        let sectors = file.metadata()?.len() as usize / SECTOR_SIZE;
+        let mut used = vec![false; sectors];
+        used[..HEADER_SECTORS].fill(true);
+        for location in locations {
+            let (offset, count) = split_location(location);
+            // chunks pointing into the header or off the end are treated as missing when read
+            if offset >= HEADER_SECTORS && offset + count <= sectors {
+                used[offset..offset + count].fill(true);
+            }
+        }
+
+        Ok(Self { file, locations, timestamps, used })
+    }
+
+    pub fn has_chunk(&self, pos: ChunkPos) -> bool {
+        self.locations[index(pos)] != 0
+    }
+
+    /// Unix seconds of the chunk's last save, 0 if it was never saved
+    pub fn timestamp(&self, pos: ChunkPos) -> u32 {
+        self.timestamps[index(pos)]
+    }
+
+    /// Every chunk with data in this region, given the region's own coordinates
+    pub fn chunks(&self, region_x: i32, region_z: i32) -> Vec<ChunkPos> {
+        (0..CHUNK_COUNT)
+            .filter(|&i| self.locations[i] != 0)
+            .map(|i| ChunkPos::new(region_x * REGION_WIDTH + i as i32 % REGION_WIDTH, region_z * REGION_WIDTH + i as i32 / REGION_WIDTH))
+            .collect()
+    }
+
+    /// The chunk's root tag, `None` if it was never saved
+    pub fn read(&mut self, pos: ChunkPos) -> io::Result<Option<Tag>> {
+        let (offset, count) = split_location(self.locations[index(pos)]);
+        if count == 0 {
+            return Ok(None);
+        }
+        if offset < HEADER_SECTORS || offset + count > self.used.len() {
+            return Err(invalid(format!("chunk {},{} points outside the region file", pos.x, pos.z)));
+        }
+
+        let mut data = vec![0; count * SECTOR_SIZE];
+        self.file.seek(SeekFrom::Start((offset * SECTOR_SIZE) as u64))?;
+        self.file.read_exact(&mut data)?;
+        // the length counts the compression byte but not itself
+        let length = u32::from_be_bytes(data[..4].try_into().unwrap()) as usize;
+        if length == 0 || length + 4 > data.len() {
+            return Err(invalid(format!("chunk {},{} claims {length} bytes in {count} sectors", pos.x, pos.z)));
+        }
+        let compressed = &data[5..length + 4];
+        let (_, tag) = match data[4] {
+            GZIP => nbt::read(&mut GzDecoder::new(compressed))?,
+            ZLIB => nbt::read(&mut ZlibDecoder::new(compressed))?,
+            other => return Err(invalid(format!("chunk {},{} has unknown compression {other}", pos.x, pos.z))),
+        };
+        Ok(Some(tag))
+    }
+
+    /// Saves the chunk zlib compressed, the way vanilla does
+    pub fn write(&mut self, pos: ChunkPos, tag: &Tag) -> io::Result<()> {
+        let mut encoder = ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+        nbt::write(&mut encoder, "", tag)?;
+        let compressed = encoder.finish()?;
+
+        let mut data = Vec::with_capacity(compressed.len() + 5);
+        data.extend_from_slice(&(compressed.len() as u32 + 1).to_be_bytes());
+        data.push(ZLIB);
+        data.extend_from_slice(&compressed);
+        let count = data.len().div_ceil(SECTOR_SIZE);
+        if count > MAX_CHUNK_SECTORS {
+            return Err(invalid(format!("chunk {},{} is {} bytes compressed, too big for a region file", pos.x, pos.z, data.len())));
+        }
+        data.resize(count * SECTOR_SIZE, 0);
+
+        let i = index(pos);
+        let (old_offset, mut old_count) = split_location(self.locations[i]);
+        if old_offset < HEADER_SECTORS || old_offset + old_count > self.used.len() {
+            // a broken location owns nothing, so there's nothing to reuse or free
+            old_count = 0;
+        }
+        let offset = if old_count != 0 && count <= old_count {
+            // still fits where it was, hand back what it doesn't need anymore
+            self.used[old_offset + count..old_offset + old_count].fill(false);
+            old_offset
+        } else {
+            if old_count != 0 {
+                self.used[old_offset..old_offset + old_count].fill(false);
+            }
+            self.allocate(count)
+        };
+
+        self.file.seek(SeekFrom::Start((offset * SECTOR_SIZE) as u64))?;
+        self.file.write_all(&data)?;
+
+        self.locations[i] = (offset as u32) << 8 | count as u32;
+        self.timestamps[i] = SystemTime::now().duration_since(UNIX_EPOCH).map(|time| time.as_secs() as u32).unwrap_or(0);
+        self.file.seek(SeekFrom::Start((i * 4) as u64))?;
+        self.file.write_all(&self.locations[i].to_be_bytes())?;
+        self.file.seek(SeekFrom::Start((SECTOR_SIZE + i * 4) as u64))?;
+        self.file.write_all(&self.timestamps[i].to_be_bytes())
+    }
+
+    /// First free run of `count` sectors, growing the file when there isn't one
+    fn allocate(&mut self, count: usize) -> usize {
+        let mut run = 0;
+        for sector in HEADER_SECTORS..self.used.len() {
+            run = if self.used[sector] { 0 } else { run + 1 };
+            if run == count {
+                let start = sector + 1 - count;
+                self.used[start..=sector].fill(true);
+                return start;
+            }
+        }
+        // a free run at the very end gets extended rather than skipped
+        let start = self.used.len() - run;
+        self.used.resize(start + count, true);
+        self.used[start..].fill(true);
+        start
+    }
+
+    pub fn sync(&self) -> io::Result<()> {
+        self.file.sync_data()
+    }
+}
+
+/// `r.X.Z.mca` for the region holding `pos`
+pub fn region_file_name(pos: ChunkPos) -> String {
+    format!("r.{}.{}.mca", pos.x >> 5, pos.z >> 5)
+}
+
+pub fn parse_region_file_name(name: &str) -> Option<(i32, i32)> {
+    let (x, z) = name.strip_prefix("r.")?.strip_suffix(".mca")?.split_once('.')?;
+    Some((x.parse().ok()?, z.parse().ok()?))
+}
+
+fn index(pos: ChunkPos) -> usize {
+    ((pos.x & 31) + (pos.z & 31) * REGION_WIDTH) as usize
+}
+
+fn split_location(location: u32) -> (usize, usize) {
+    ((location >> 8) as usize, (location & 0xFF) as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use super::*;
+
+    /// A fresh directory for one test's region files
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("nanocraft-region-{}-{name}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// A chunk tag around `bytes` long once compressed, filled with noise that won't compress
+    fn tag_of_size(bytes: usize, seed: u32) -> Tag {
+        let mut state = seed | 1;
+        let noise = (0..bytes)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                state as i8
+            })
+            .collect::<Vec<i8>>();
+        Tag::Compound([("data", Tag::ByteArray(noise))].into_iter().collect())
+    }
+
+    fn sectors(region: &RegionFile, pos: ChunkPos) -> (usize, usize) {
+        split_location(region.locations[index(pos)])
+    }
+
+    #[test]
+    fn chunks_round_trip_through_the_file() {
+        let dir = scratch_dir("round-trip");
+        let path = dir.join(region_file_name(ChunkPos::new(-1, 64)));
+        let chunks = [(ChunkPos::new(-1, 64), 100), (ChunkPos::new(-32, 95), 5000), (ChunkPos::new(-20, 70), 20000)];
+        {
+            let mut region = RegionFile::open(&path).unwrap();
+            for (i, &(pos, size)) in chunks.iter().enumerate() {
+                region.write(pos, &tag_of_size(size, i as u32)).unwrap();
+            }
+            region.sync().unwrap();
+        }
+
+        let mut region = RegionFile::open(&path).unwrap();
+        let mut saved = region.chunks(-1, 2);
+        saved.sort_by_key(|pos| (pos.x, pos.z));
+        assert_eq!(saved, [ChunkPos::new(-32, 95), ChunkPos::new(-20, 70), ChunkPos::new(-1, 64)]);
+        for (i, &(pos, size)) in chunks.iter().enumerate() {
+            assert!(region.has_chunk(pos) && region.timestamp(pos) > 0);
+            assert_eq!(region.read(pos).unwrap(), Some(tag_of_size(size, i as u32)));
+        }
+        assert_eq!(region.read(ChunkPos::new(-2, 65)).unwrap(), None);
+        assert_eq!(parse_region_file_name(path.file_name().unwrap().to_str().unwrap()), Some((-1, 2)));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn freed_sectors_get_used_again() {
+        let dir = scratch_dir("free-sectors");
+        let mut region = RegionFile::open(&dir.join("r.0.0.mca")).unwrap();
+        let (a, b, c) = (ChunkPos::new(0, 0), ChunkPos::new(1, 0), ChunkPos::new(2, 0));
+
+        region.write(a, &tag_of_size(3 * SECTOR_SIZE, 1)).unwrap();
+        region.write(b, &tag_of_size(100, 2)).unwrap();
+        assert_eq!(sectors(&region, a), (HEADER_SECTORS, 4));
+        assert_eq!(sectors(&region, b), (HEADER_SECTORS + 4, 1));
+        let length = region.used.len();
+
+        // shrinking in place hands back its tail, which the next small chunk fills
+        region.write(a, &tag_of_size(100, 3)).unwrap();
+        assert_eq!(sectors(&region, a), (HEADER_SECTORS, 1));
+        region.write(c, &tag_of_size(2 * SECTOR_SIZE, 4)).unwrap();
+        assert_eq!(sectors(&region, c), (HEADER_SECTORS + 1, 3));
+        assert_eq!(region.used.len(), length);
+
+        // growing past its neighbours moves it to the end, and its old sector is free again
+        region.write(a, &tag_of_size(2 * SECTOR_SIZE, 5)).unwrap();
+        assert_eq!(sectors(&region, a), (length, 3));
+        region.write(ChunkPos::new(3, 0), &tag_of_size(100, 6)).unwrap();
+        assert_eq!(sectors(&region, ChunkPos::new(3, 0)), (HEADER_SECTORS, 1));
+
+        // and all of that is what the file says after opening it again
+        drop(region);
+        let mut region = RegionFile::open(&dir.join("r.0.0.mca")).unwrap();
+        assert_eq!(region.used.iter().filter(|&&used| !used).count(), 0);
+        assert_eq!(region.read(a).unwrap(), Some(tag_of_size(2 * SECTOR_SIZE, 5)));
+        assert_eq!(region.read(c).unwrap(), Some(tag_of_size(2 * SECTOR_SIZE, 4)));
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,440 @@
+use std::collections::{HashMap, HashSet};
+
+pub const CHUNK_WIDTH: usize = 16;
+pub const CHUNK_HEIGHT: usize = 256;
+pub const SECTION_HEIGHT: usize = 16;
+pub const SECTION_COUNT: usize = CHUNK_HEIGHT / SECTION_HEIGHT;
+pub const SECTION_VOLUME: usize = CHUNK_WIDTH * SECTION_HEIGHT * CHUNK_WIDTH;
+/// Plains, what columns are until a generator says otherwise
+pub const DEFAULT_BIOME: u8 = 1;
+
+/// A block id and its metadata packed as `id << 4 | meta`, the same layout 1.8.9 keeps in its chunk arrays.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct BlockState(pub u16);
+
+impl BlockState {
+    pub const AIR: BlockState = BlockState(0);
+
+    pub const fn new(id: u16, meta: u8) -> Self {
+        Self(id << 4 | (meta & 0xF) as u16)
+    }
+
+    pub fn id(self) -> u16 {
+        self.0 >> 4
+    }
+
+    pub fn meta(self) -> u8 {
+        (self.0 & 0xF) as u8
+    }
+
+    pub fn is_air(self) -> bool {
+        self.id() == 0
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct BlockPos {
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+}
+
+impl BlockPos {
+    pub fn new(x: i32, y: i32, z: i32) -> Self {
+        Self { x, y, z }
+    }
+
+    pub fn chunk(self) -> ChunkPos {
+        ChunkPos::new(self.x >> 4, self.z >> 4)
+    }
+
+    /// Position inside its chunk, x and z are 0..16 and y is left as is
+    pub fn local(self) -> (usize, usize, usize) {
+        ((self.x & 15) as usize, self.y as usize, (self.z & 15) as usize)
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct ChunkPos {
+    pub x: i32,
+    pub z: i32,
+}
+
+impl ChunkPos {
+    pub fn new(x: i32, z: i32) -> Self {
+        Self { x, z }
+    }
+
+    /// World position of the chunk's lowest corner
+    pub fn origin(self) -> BlockPos {
+        BlockPos::new(self.x * CHUNK_WIDTH as i32, 0, self.z * CHUNK_WIDTH as i32)
+    }
+}
+
+/// A 16x16x16 slice of a chunk, stored in vanilla `y << 8 | z << 4 | x` order.
+#[derive(Clone)]
+pub struct ChunkSection {
+    blocks: Box<[BlockState; SECTION_VOLUME]>,
+    non_air: u16,
+}
+
+impl ChunkSection {
+    pub fn new() -> Self {
+        Self {
+            blocks: Box::new([BlockState::AIR; SECTION_VOLUME]),
+            non_air: 0,
+        }
+    }
+
+    pub fn index(x: usize, y: usize, z: usize) -> usize {
+        y << 8 | z << 4 | x
+    }
+
+    pub fn get(&self, x: usize, y: usize, z: usize) -> BlockState {
+        self.blocks[Self::index(x, y, z)]
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, z: usize, state: BlockState) {
+        let slot = &mut self.blocks[Self::index(x, y, z)];
+        match (slot.is_air(), state.is_air()) {
+            (true, false) => self.non_air += 1,
+            (false, true) => self.non_air -= 1,
+            _ => {}
+        }
+        *slot = state;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.non_air == 0
+    }
+
+    pub fn blocks(&self) -> &[BlockState; SECTION_VOLUME] {
+        &self.blocks
+    }
+}
+
+impl Default for ChunkSection {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Light from the sky comes down every column until something stops it, block light spreads out
+/// from torches, lava and the like. Both go from 0 to 15.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum LightType {
+    Sky,
+    Block,
+}
+
+impl LightType {
+    pub const ALL: [LightType; 2] = [LightType::Sky, LightType::Block];
+
+    /// What open air far from anything reads as, also used outside the world and loaded chunks
+    pub fn default_level(self) -> u8 {
+        match self {
+            LightType::Sky => 15,
+            LightType::Block => 0,
+        }
+    }
+}
+
+/// One light type for a 16x16x16 section, a nibble per block in `ChunkSection` order with even
+/// indices in the low half of each byte, like saves and chunk packets have it. Sections lit the
+/// same all over, open sky or deep underground, don't need the array.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SectionLight {
+    Uniform(u8),
+    Nibbles(Box<[u8; SECTION_VOLUME / 2]>),
+}
+
+impl SectionLight {
+    /// Takes vanilla's 2048 byte layout, anything else is `None`
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let nibbles: [u8; SECTION_VOLUME / 2] = bytes.try_into().ok()?;
+        let first = nibbles[0];
+        Some(if first & 15 == first >> 4 && nibbles.iter().all(|&byte| byte == first) {
+            SectionLight::Uniform(first & 15)
+        } else {
+            SectionLight::Nibbles(Box::new(nibbles))
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            SectionLight::Uniform(level) => vec![level << 4 | level; SECTION_VOLUME / 2],
+            SectionLight::Nibbles(nibbles) => nibbles.to_vec(),
+        }
+    }
+
+    pub fn get(&self, x: usize, y: usize, z: usize) -> u8 {
+        match self {
+            SectionLight::Uniform(level) => *level,
+            SectionLight::Nibbles(nibbles) => {
+                let i = ChunkSection::index(x, y, z);
+                nibbles[i / 2] >> (i % 2 * 4) & 15
+            }
+        }
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, z: usize, level: u8) {
+        if let SectionLight::Uniform(uniform) = *self {
+            if uniform == level {
+                return;
+            }
+            *self = SectionLight::Nibbles(Box::new([uniform << 4 | uniform; SECTION_VOLUME / 2]));
+        }
+        if let SectionLight::Nibbles(nibbles) = self {
+            let i = ChunkSection::index(x, y, z);
+            let shift = i % 2 * 4;
+            nibbles[i / 2] = nibbles[i / 2] & !(15 << shift) | (level & 15) << shift;
+        }
+    }
+}
+
+/// A 16x256x16 column of blocks, sections that are all air are not allocated.
+#[derive(Clone)]
+pub struct Chunk {
+    pub pos: ChunkPos,
+    sections: [Option<Box<ChunkSection>>; SECTION_COUNT],
+    sky_light: [SectionLight; SECTION_COUNT],
+    block_light: [SectionLight; SECTION_COUNT],
+    /// The y above the highest block in each column that sunlight loses anything going through, `z << 4 | x`
+    heights: [u16; CHUNK_WIDTH * CHUNK_WIDTH],
+    /// Biome id of every column, `z << 4 | x` like the `Biomes` array in saves and chunk packets
+    pub biomes: [u8; CHUNK_WIDTH * CHUNK_WIDTH],
+    /// Whether the light has been worked out, until then the whole chunk reads as open sky
+    pub light_populated: bool,
+    /// Blocks or light changed since it was loaded or last saved, new chunks start out that way
+    pub modified: bool,
+}
+
+impl Chunk {
+    pub fn new(pos: ChunkPos) -> Self {
+        Self {
+            pos,
+            sections: Default::default(),
+            sky_light: std::array::from_fn(|_| SectionLight::Uniform(LightType::Sky.default_level())),
+            block_light: std::array::from_fn(|_| SectionLight::Uniform(LightType::Block.default_level())),
+            heights: [0; CHUNK_WIDTH * CHUNK_WIDTH],
+            biomes: [DEFAULT_BIOME; CHUNK_WIDTH * CHUNK_WIDTH],
+            light_populated: false,
+            modified: true,
+        }
+    }
+
+    pub fn biome(&self, x: usize, z: usize) -> u8 {
+        self.biomes[z << 4 | x]
+    }
+
+    pub fn set_biome(&mut self, x: usize, z: usize, biome: u8) {
+        self.modified = true;
+        self.biomes[z << 4 | x] = biome;
+    }
+
+    pub fn get_block(&self, x: usize, y: usize, z: usize) -> BlockState {
+        if y >= CHUNK_HEIGHT {
+            return BlockState::AIR;
+        }
+        match &self.sections[y / SECTION_HEIGHT] {
+            Some(section) => section.get(x, y % SECTION_HEIGHT, z),
+            None => BlockState::AIR,
+        }
+    }
+
+    pub fn set_block(&mut self, x: usize, y: usize, z: usize, state: BlockState) {
+        if y >= CHUNK_HEIGHT {
+            return;
+        }
+        self.modified = true;
+        let slot = &mut self.sections[y / SECTION_HEIGHT];
+        if slot.is_none() {
+            if state.is_air() {
+                return;
+            }
+            *slot = Some(Box::default());
+        }
+        let section = slot.as_mut().unwrap();
+        section.set(x, y % SECTION_HEIGHT, z, state);
+        if section.is_empty() {
+            *slot = None;
+        }
+    }
+
+    pub fn section(&self, index: usize) -> Option<&ChunkSection> {
+        self.sections.get(index)?.as_deref()
+    }
+
+    pub fn sections(&self) -> impl Iterator<Item = (usize, &ChunkSection)> {
+        self.sections.iter().enumerate().filter_map(|(i, s)| Some((i, s.as_deref()?)))
+    }
+
+    pub fn light(&self, light: LightType, x: usize, y: usize, z: usize) -> u8 {
+        if y >= CHUNK_HEIGHT {
+            return light.default_level();
+        }
+        self.section_light(light, y / SECTION_HEIGHT).get(x, y % SECTION_HEIGHT, z)
+    }
+
+    pub fn set_light(&mut self, light: LightType, x: usize, y: usize, z: usize, level: u8) {
+        if y < CHUNK_HEIGHT {
+            self.section_light_mut(light, y / SECTION_HEIGHT).set(x, y % SECTION_HEIGHT, z, level);
+        }
+    }
+
+    pub fn section_light(&self, light: LightType, index: usize) -> &SectionLight {
+        match light {
+            LightType::Sky => &self.sky_light[index],
+            LightType::Block => &self.block_light[index],
+        }
+    }
+
+    pub fn section_light_mut(&mut self, light: LightType, index: usize) -> &mut SectionLight {
+        self.modified = true;
+        match light {
+            LightType::Sky => &mut self.sky_light[index],
+            LightType::Block => &mut self.block_light[index],
+        }
+    }
+
+    pub fn height(&self, x: usize, z: usize) -> usize {
+        self.heights[z << 4 | x] as usize
+    }
+
+    pub fn set_height(&mut self, x: usize, z: usize, height: usize) {
+        self.heights[z << 4 | x] = height.min(CHUNK_HEIGHT) as u16;
+    }
+}
+
+/// All the chunks currently loaded, anything outside of them reads as air.
+#[derive(Default)]
+pub struct World {
+    chunks: HashMap<ChunkPos, Chunk>,
+    /// Whether to keep `light_changes` at all, only something that meshes wants them
+    pub track_light_changes: bool,
+    /// Sections with light changes in or right next to them since `take_light_changes` was last called
+    light_changes: HashSet<(ChunkPos, usize)>,
+}
+
+impl World {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn load_chunk(&mut self, chunk: Chunk) {
+        self.chunks.insert(chunk.pos, chunk);
+    }
+
+    pub fn unload_chunk(&mut self, pos: ChunkPos) -> Option<Chunk> {
+        self.chunks.remove(&pos)
+    }
+
+    pub fn chunk(&self, pos: ChunkPos) -> Option<&Chunk> {
+        self.chunks.get(&pos)
+    }
+
+    pub fn chunk_mut(&mut self, pos: ChunkPos) -> Option<&mut Chunk> {
+        self.chunks.get_mut(&pos)
+    }
+
+    pub fn chunks(&self) -> impl Iterator<Item = &Chunk> {
+        self.chunks.values()
+    }
+
+    pub fn chunks_mut(&mut self) -> impl Iterator<Item = &mut Chunk> {
+        self.chunks.values_mut()
+    }
+
+    pub fn is_loaded(&self, pos: ChunkPos) -> bool {
+        self.chunks.contains_key(&pos)
+    }
+
+    pub fn get_block(&self, pos: BlockPos) -> BlockState {
+        if pos.y < 0 || pos.y >= CHUNK_HEIGHT as i32 {
+            return BlockState::AIR;
+        }
+        match self.chunks.get(&pos.chunk()) {
+            Some(chunk) => {
+                let (x, y, z) = pos.local();
+                chunk.get_block(x, y, z)
+            }
+            None => BlockState::AIR,
+        }
+    }
+
+    pub fn get_biome(&self, x: i32, z: i32) -> u8 {
+        match self.chunks.get(&ChunkPos::new(x >> 4, z >> 4)) {
+            Some(chunk) => chunk.biome((x & 15) as usize, (z & 15) as usize),
+            None => DEFAULT_BIOME,
+        }
+    }
+
+    /// Returns false if the position isn't in a loaded chunk or is outside the build height
+    pub fn set_block(&mut self, pos: BlockPos, state: BlockState) -> bool {
+        if pos.y < 0 || pos.y >= CHUNK_HEIGHT as i32 {
+            return false;
+        }
+        match self.chunks.get_mut(&pos.chunk()) {
+            Some(chunk) => {
+                let (x, y, z) = pos.local();
+                chunk.set_block(x, y, z, state);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Out of the world and in unloaded chunks it's whatever open air would have
+    pub fn get_light(&self, light: LightType, pos: BlockPos) -> u8 {
+        if pos.y < 0 {
+            return 0;
+        }
+        match self.chunks.get(&pos.chunk()) {
+            Some(chunk) => {
+                let (x, y, z) = pos.local();
+                chunk.light(light, x, y, z)
+            }
+            None => light.default_level(),
+        }
+    }
+
+    /// Returns false if the position isn't in a loaded chunk or is outside the build height
+    pub fn set_light(&mut self, light: LightType, pos: BlockPos, level: u8) -> bool {
+        if pos.y < 0 || pos.y >= CHUNK_HEIGHT as i32 {
+            return false;
+        }
+        match self.chunks.get_mut(&pos.chunk()) {
+            Some(chunk) => {
+                let (x, y, z) = pos.local();
+                chunk.set_light(light, x, y, z, level);
+                if !self.track_light_changes {
+                    return true;
+                }
+                // blocks touching it, corners included, light their faces with it
+                let around = |local: usize, last: usize| match local {
+                    0 => -1..=0,
+                    _ if local == last => 0..=1,
+                    _ => 0..=0,
+                };
+                for dx in around(x, CHUNK_WIDTH - 1) {
+                    for dz in around(z, CHUNK_WIDTH - 1) {
+                        for dy in around(y % SECTION_HEIGHT, SECTION_HEIGHT - 1) {
+                            let section_y = (y / SECTION_HEIGHT) as i32 + dy;
+                            if (0..SECTION_COUNT as i32).contains(&section_y) {
+                                let chunk = ChunkPos::new(pos.chunk().x + dx, pos.chunk().z + dz);
+                                self.light_changes.insert((chunk, section_y as usize));
+                            }
+                        }
+                    }
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// The sections that need meshing again for new light, as `(chunk, section y)`. Some may not be loaded.
+    pub fn take_light_changes(&mut self) -> HashSet<(ChunkPos, usize)> {
+        std::mem::take(&mut self.light_changes)
+    }
+}
@@ -0,0 +1,177 @@
+use crate::block::{DIRT, GRASS, SAND, STONE};
+use crate::world::BlockState;
+use crate::worldgen::noise::OctaveNoise;
+use crate::worldgen::random::JavaRandom;
+
+/// The biomes we generate, with the ids 1.8.9 stores in a chunk's `Biomes` array.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Biome {
+    Ocean = 0,
+    Plains = 1,
+    Desert = 2,
+    ExtremeHills = 3,
+    Forest = 4,
+    Taiga = 5,
+    Swampland = 6,
+}
+
+impl Biome {
+    pub fn id(self) -> u8 {
+        self as u8
+    }
+
+    pub fn from_id(id: u8) -> Option<Biome> {
+        Some(match id {
+            0 => Biome::Ocean,
+            1 => Biome::Plains,
+            2 => Biome::Desert,
+            3 => Biome::ExtremeHills,
+            4 => Biome::Forest,
+            5 => Biome::Taiga,
+            6 => Biome::Swampland,
+            _ => return None,
+        })
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Biome::Ocean => "Ocean",
+            Biome::Plains => "Plains",
+            Biome::Desert => "Desert",
+            Biome::ExtremeHills => "Extreme Hills",
+            Biome::Forest => "Forest",
+            Biome::Taiga => "Taiga",
+            Biome::Swampland => "Swampland",
+        }
+    }
+
+    pub fn temperature(self) -> f32 {
+        match self {
+            Biome::Ocean => 0.5,
+            Biome::Plains => 0.8,
+            Biome::Desert => 2.0,
+            Biome::ExtremeHills => 0.2,
+            Biome::Forest => 0.7,
+            Biome::Taiga => 0.25,
+            Biome::Swampland => 0.8,
+        }
+    }
+
+    pub fn rainfall(self) -> f32 {
+        match self {
+            Biome::Ocean => 0.5,
+            Biome::Plains => 0.4,
+            Biome::Desert => 0.0,
+            Biome::ExtremeHills => 0.3,
+            Biome::Forest => 0.8,
+            Biome::Taiga => 0.8,
+            Biome::Swampland => 0.9,
+        }
+    }
+
+    /// Vanilla's `minHeight` and `maxHeight`, how high the ground sits and how much it varies
+    pub fn height(self) -> (f32, f32) {
+        match self {
+            Biome::Ocean => (-1.0, 0.1),
+            Biome::Plains => (0.125, 0.05),
+            Biome::Desert => (0.125, 0.05),
+            Biome::ExtremeHills => (1.0, 0.5),
+            Biome::Forest => (0.1, 0.2),
+            Biome::Taiga => (0.2, 0.2),
+            Biome::Swampland => (-0.2, 0.1),
+        }
+    }
+
+    pub fn top_block(self) -> BlockState {
+        match self {
+            Biome::Desert => SAND,
+            _ => GRASS,
+        }
+    }
+
+    pub fn filler_block(self) -> BlockState {
+        match self {
+            Biome::Desert => SAND,
+            Biome::ExtremeHills => STONE,
+            _ => DIRT,
+        }
+    }
+
+    /// RGB the grey grass texture gets multiplied by, picked from vanilla's grass colormap
+    pub fn grass_color(self) -> u32 {
+        match self {
+            Biome::Ocean => 0x8EB971,
+            Biome::Plains => 0x91BD59,
+            Biome::Desert => 0xBFB755,
+            Biome::ExtremeHills => 0x8AB689,
+            Biome::Forest => 0x79C05A,
+            Biome::Taiga => 0x86B783,
+            Biome::Swampland => 0x6A7039,
+        }
+    }
+
+    pub fn foliage_color(self) -> u32 {
+        match self {
+            Biome::Ocean => 0x71A74D,
+            Biome::Plains => 0x77AB2F,
+            Biome::Desert => 0xAEA42A,
+            Biome::ExtremeHills => 0x6DA36B,
+            Biome::Forest => 0x59AE30,
+            Biome::Taiga => 0x68A464,
+            Biome::Swampland => 0x6A7039,
+        }
+    }
+}
+
+/// Picks biomes from temperature and rainfall noise. Oceans are the one exception and come from
+/// their own low frequency noise, otherwise they'd end up as small patches between the climates.
+pub struct BiomeProvider {
+    temperature: OctaveNoise,
+    rainfall: OctaveNoise,
+    ocean: OctaveNoise,
+    variety: OctaveNoise,
+}
+
+impl BiomeProvider {
+    pub fn new(seed: i64) -> Self {
+        // separate stream from the terrain noise so tweaking one doesn't reshuffle the other
+        let mut random = JavaRandom::new(seed.wrapping_mul(9871).wrapping_add(3));
+        Self {
+            temperature: OctaveNoise::new(&mut random, 4),
+            rainfall: OctaveNoise::new(&mut random, 4),
+            ocean: OctaveNoise::new(&mut random, 3),
+            variety: OctaveNoise::new(&mut random, 2),
+        }
+    }
+
+    /// Both in 0..1
+    pub fn climate_at(&self, x: i32, z: i32) -> (f32, f32) {
+        let (x, z) = (x as f64, z as f64);
+        let temperature = self.temperature.sample_2d(x / 600.0, z / 600.0) * 0.6 + 0.5;
+        let rainfall = self.rainfall.sample_2d(x / 500.0, z / 500.0) * 0.6 + 0.5;
+        (temperature.clamp(0.0, 1.0) as f32, rainfall.clamp(0.0, 1.0) as f32)
+    }
+
+    pub fn biome_at(&self, x: i32, z: i32) -> Biome {
+        if self.ocean.sample_2d(x as f64 / 900.0, z as f64 / 900.0) < -0.3 {
+            return Biome::Ocean;
+        }
+        let (temperature, rainfall) = self.climate_at(x, z);
+        // mountains cut through cold and mild climates, wherever this noise peaks
+        let rugged = self.variety.sample_2d(x as f64 / 300.0, z as f64 / 300.0) > 0.35;
+
+        if temperature < 0.35 {
+            if rugged || rainfall < 0.35 { Biome::ExtremeHills } else { Biome::Taiga }
+        } else if temperature > 0.7 && rainfall < 0.35 {
+            Biome::Desert
+        } else if rugged && temperature < 0.55 {
+            Biome::ExtremeHills
+        } else if rainfall > 0.75 && temperature > 0.55 {
+            Biome::Swampland
+        } else if rainfall > 0.5 {
+            Biome::Forest
+        } else {
+            Biome::Plains
+        }
+    }
+}
@@ -0,0 +1,294 @@
+use std::f32::consts::PI;
+
+use crate::block::{DIRT, GRASS, GRAVEL, LAVA, SAND, SANDSTONE, STONE, WATER};
+use crate::world::{BlockState, Chunk, ChunkPos, CHUNK_WIDTH};
+use crate::worldgen::biome::Biome;
+use crate::worldgen::random::JavaRandom;
+
+/// How many chunks away a tunnel can start and still reach the chunk being carved
+const RANGE: i32 = 8;
+/// Anything carved out below this fills with lava instead of air
+const LAVA_LEVEL: i32 = 10;
+
+/// Tunnels that get cut out of the terrain after the height pass. A tunnel is worked out from the
+/// chunk it starts in and each chunk only carves its own part of it, which is why tunnels line up
+/// across chunk borders no matter which order chunks are generated in.
+pub trait Carver {
+    /// Start whatever tunnels `source` has, carving only the parts that fall inside `chunk`
+    fn carve_from(&self, random: &mut JavaRandom, source: ChunkPos, chunk: &mut Chunk);
+}
+
+pub fn carve(carver: &impl Carver, seed: i64, chunk: &mut Chunk) {
+    let mut random = JavaRandom::new(seed);
+    let x_factor = random.next_long();
+    let z_factor = random.next_long();
+    let center = chunk.pos;
+
+    for x in center.x - RANGE..=center.x + RANGE {
+        for z in center.z - RANGE..=center.z + RANGE {
+            random.set_seed((x as i64).wrapping_mul(x_factor) ^ (z as i64).wrapping_mul(z_factor) ^ seed);
+            carver.carve_from(&mut random, ChunkPos::new(x, z), chunk);
+        }
+    }
+}
+
+/// Where a tunnel is and where it's heading
+#[derive(Copy, Clone)]
+struct Tunnel {
+    x: f64,
+    y: f64,
+    z: f64,
+    width: f32,
+    yaw: f32,
+    pitch: f32,
+}
+
+/// Carves an ellipsoid centred on the tunnel into the chunk. `shape` gets the offsets from the
+/// centre, scaled to the radii, and says whether that spot is inside.
+fn carve_blob(chunk: &mut Chunk, center: (f64, f64, f64), radius: f64, height: f64, shape: impl Fn(f64, f64, f64, i32) -> bool) {
+    let origin = chunk.pos.origin();
+    let (x, y, z) = center;
+    let min_x = ((x - radius).floor() as i32 - origin.x - 1).max(0);
+    let max_x = ((x + radius).floor() as i32 - origin.x + 1).min(CHUNK_WIDTH as i32);
+    let min_y = ((y - height).floor() as i32 - 1).max(1);
+    let max_y = ((y + height).floor() as i32 + 1).min(248);
+    let min_z = ((z - radius).floor() as i32 - origin.z - 1).max(0);
+    let max_z = ((z + radius).floor() as i32 - origin.z + 1).min(CHUNK_WIDTH as i32);
+
+    // breaking into the sea or a lake would flood everything, so leave this spot alone
+    for bx in min_x..max_x {
+        for bz in min_z..max_z {
+            for by in min_y - 1..=max_y {
+                if chunk.get_block(bx as usize, by as usize, bz as usize) == WATER {
+                    return;
+                }
+            }
+        }
+    }
+
+    for bx in min_x..max_x {
+        let dx = ((bx + origin.x) as f64 + 0.5 - x) / radius;
+        for bz in min_z..max_z {
+            let dz = ((bz + origin.z) as f64 + 0.5 - z) / radius;
+            if dx * dx + dz * dz >= 1.0 {
+                continue;
+            }
+            let mut found_grass = false;
+            for by in (min_y..max_y).rev() {
+                let dy = (by as f64 + 0.5 - y) / height;
+                if !shape(dx, dy, dz, by) {
+                    continue;
+                }
+                let (ux, uy, uz) = (bx as usize, by as usize, bz as usize);
+                let block = chunk.get_block(ux, uy, uz);
+                if block == GRASS {
+                    found_grass = true;
+                }
+                if !can_carve(block, chunk.get_block(ux, uy + 1, uz)) {
+                    continue;
+                }
+                if by < LAVA_LEVEL {
+                    chunk.set_block(ux, uy, uz, LAVA);
+                } else {
+                    chunk.set_block(ux, uy, uz, BlockState::AIR);
+                    // dirt that's now the surface should look like the rest of the surface
+                    if found_grass && chunk.get_block(ux, uy - 1, uz) == DIRT {
+                        let biome = Biome::from_id(chunk.biome(ux, uz)).unwrap_or(Biome::Plains);
+                        chunk.set_block(ux, uy - 1, uz, biome.top_block());
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn can_carve(block: BlockState, above: BlockState) -> bool {
+    match block {
+        STONE | DIRT | GRASS | SANDSTONE => true,
+        SAND | GRAVEL => above != WATER,
+        _ => false,
+    }
+}
+
+/// Whether the tunnel has wandered too far to ever reach the chunk again
+fn out_of_reach(tunnel: &Tunnel, chunk: ChunkPos, step: i32, steps: i32) -> bool {
+    let center_x = (chunk.x * 16 + 8) as f64;
+    let center_z = (chunk.z * 16 + 8) as f64;
+    let (dx, dz) = (tunnel.x - center_x, tunnel.z - center_z);
+    let remaining = (steps - step) as f64;
+    let reach = tunnel.width as f64 + 2.0 + 16.0;
+    dx * dx + dz * dz - remaining * remaining > reach * reach
+}
+
+fn near_chunk(tunnel: &Tunnel, chunk: ChunkPos, radius: f64) -> bool {
+    let center_x = (chunk.x * 16 + 8) as f64;
+    let center_z = (chunk.z * 16 + 8) as f64;
+    let margin = 16.0 + radius * 2.0;
+    (center_x - margin..=center_x + margin).contains(&tunnel.x) && (center_z - margin..=center_z + margin).contains(&tunnel.z)
+}
+
+/// Vanilla's winding worm caves, with the odd round room where several of them meet.
+pub struct Caves;
+
+impl Caves {
+    /// `start` of -1 makes a room instead of a tunnel
+    fn tunnel(&self, seed: i64, chunk: &mut Chunk, mut tunnel: Tunnel, mut start: i32, mut steps: i32, height_scale: f64) {
+        let mut random = JavaRandom::new(seed);
+        let (mut yaw_change, mut pitch_change) = (0.0f32, 0.0f32);
+        if steps <= 0 {
+            let max = RANGE * 16 - 16;
+            steps = max - random.next_int_bounded(max / 4);
+        }
+        let room = start == -1;
+        if room {
+            start = steps / 2;
+        }
+        let branch_at = random.next_int_bounded(steps / 2) + steps / 4;
+        let steep = random.next_int_bounded(6) == 0;
+
+        for step in start..steps {
+            let radius = 1.5 + ((step as f32 * PI / steps as f32).sin() * tunnel.width) as f64;
+            let height = radius * height_scale;
+            tunnel.x += (tunnel.yaw.cos() * tunnel.pitch.cos()) as f64;
+            tunnel.y += tunnel.pitch.sin() as f64;
+            tunnel.z += (tunnel.yaw.sin() * tunnel.pitch.cos()) as f64;
+            tunnel.pitch *= if steep { 0.92 } else { 0.7 };
+            tunnel.pitch += pitch_change * 0.1;
+            tunnel.yaw += yaw_change * 0.1;
+            pitch_change *= 0.9;
+            yaw_change *= 0.75;
+            pitch_change += (random.next_float() - random.next_float()) * random.next_float() * 2.0;
+            yaw_change += (random.next_float() - random.next_float()) * random.next_float() * 4.0;
+
+            if !room && step == branch_at && tunnel.width > 1.0 {
+                for turn in [-PI / 2.0, PI / 2.0] {
+                    let seed = random.next_long();
+                    let branch = Tunnel {
+                        width: random.next_float() * 0.5 + 0.5,
+                        yaw: tunnel.yaw + turn,
+                        pitch: tunnel.pitch / 3.0,
+                        ..tunnel
+                    };
+                    self.tunnel(seed, chunk, branch, step, steps, 1.0);
+                }
+                return;
+            }
+
+            if room || random.next_int_bounded(4) != 0 {
+                if out_of_reach(&tunnel, chunk.pos, step, steps) {
+                    return;
+                }
+                if near_chunk(&tunnel, chunk.pos, radius) {
+                    carve_blob(chunk, (tunnel.x, tunnel.y, tunnel.z), radius, height, |dx, dy, dz, _| {
+                        dy > -0.7 && dx * dx + dy * dy + dz * dz < 1.0
+                    });
+                    if room {
+                        break;
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Carver for Caves {
+    fn carve_from(&self, random: &mut JavaRandom, source: ChunkPos, chunk: &mut Chunk) {
+        let bound = random.next_int_bounded(15) + 1;
+        let bound = random.next_int_bounded(bound) + 1;
+        let mut count = random.next_int_bounded(bound);
+        if random.next_int_bounded(7) != 0 {
+            count = 0;
+        }
+
+        for _ in 0..count {
+            let x = (source.x * 16 + random.next_int_bounded(16)) as f64;
+            let bound = random.next_int_bounded(120) + 8;
+            let y = random.next_int_bounded(bound) as f64;
+            let z = (source.z * 16 + random.next_int_bounded(16)) as f64;
+            let mut tunnels = 1;
+
+            if random.next_int_bounded(4) == 0 {
+                let room = Tunnel { x, y, z, width: 1.0 + random.next_float() * 6.0, yaw: 0.0, pitch: 0.0 };
+                self.tunnel(random.next_long(), chunk, room, -1, -1, 0.5);
+                tunnels += random.next_int_bounded(4);
+            }
+
+            for _ in 0..tunnels {
+                let yaw = random.next_float() * PI * 2.0;
+                let pitch = (random.next_float() - 0.5) * 2.0 / 8.0;
+                let mut width = random.next_float() * 2.0 + random.next_float();
+                if random.next_int_bounded(10) == 0 {
+                    width *= random.next_float() * random.next_float() * 3.0 + 1.0;
+                }
+                self.tunnel(random.next_long(), chunk, Tunnel { x, y, z, width, yaw, pitch }, 0, 0, 1.0);
+            }
+        }
+    }
+}
+
+/// Rare, long and very tall cracks in the ground.
+pub struct Ravines;
+
+impl Ravines {
+    fn tunnel(&self, seed: i64, chunk: &mut Chunk, mut tunnel: Tunnel, height_scale: f64) {
+        let mut random = JavaRandom::new(seed);
+        let (mut yaw_change, mut pitch_change) = (0.0f32, 0.0f32);
+        let max = RANGE * 16 - 16;
+        let steps = max - random.next_int_bounded(max / 4);
+
+        // makes the walls bulge in and out as they go down instead of being perfectly smooth
+        let mut wall_scale = [0.0f64; 256];
+        let mut scale = 1.0f32;
+        for (y, slot) in wall_scale.iter_mut().enumerate() {
+            if y == 0 || random.next_int_bounded(3) == 0 {
+                scale = 1.0 + random.next_float() * random.next_float();
+            }
+            *slot = (scale * scale) as f64;
+        }
+
+        for step in 0..steps {
+            let mut radius = 1.5 + ((step as f32 * PI / steps as f32).sin() * tunnel.width) as f64;
+            let mut height = radius * height_scale;
+            radius *= (random.next_float() * 0.25 + 0.75) as f64;
+            height *= (random.next_float() * 0.25 + 0.75) as f64;
+            tunnel.x += (tunnel.yaw.cos() * tunnel.pitch.cos()) as f64;
+            tunnel.y += tunnel.pitch.sin() as f64;
+            tunnel.z += (tunnel.yaw.sin() * tunnel.pitch.cos()) as f64;
+            tunnel.pitch *= 0.7;
+            tunnel.pitch += pitch_change * 0.05;
+            tunnel.yaw += yaw_change * 0.05;
+            pitch_change *= 0.8;
+            yaw_change *= 0.5;
+            pitch_change += (random.next_float() - random.next_float()) * random.next_float() * 2.0;
+            yaw_change += (random.next_float() - random.next_float()) * random.next_float() * 4.0;
+
+            if random.next_int_bounded(4) == 0 {
+                continue;
+            }
+            if out_of_reach(&tunnel, chunk.pos, step, steps) {
+                return;
+            }
+            if near_chunk(&tunnel, chunk.pos, radius) {
+                carve_blob(chunk, (tunnel.x, tunnel.y, tunnel.z), radius, height, |dx, dy, dz, y| {
+                    (dx * dx + dz * dz) * wall_scale[(y - 1).max(0) as usize] + dy * dy / 6.0 < 1.0
+                });
+            }
+        }
+    }
+}
+
+impl Carver for Ravines {
+    fn carve_from(&self, random: &mut JavaRandom, source: ChunkPos, chunk: &mut Chunk) {
+        if random.next_int_bounded(50) != 0 {
+            return;
+        }
+        let x = (source.x * 16 + random.next_int_bounded(16)) as f64;
+        let bound = random.next_int_bounded(40) + 8;
+        let y = (random.next_int_bounded(bound) + 20) as f64;
+        let z = (source.z * 16 + random.next_int_bounded(16)) as f64;
+        let yaw = random.next_float() * PI * 2.0;
+        let pitch = (random.next_float() - 0.5) * 2.0 / 8.0;
+        let width = (random.next_float() * 2.0 + random.next_float()) * 2.0;
+        self.tunnel(random.next_long(), chunk, Tunnel { x, y, z, width, yaw, pitch }, 3.0);
+    }
+}
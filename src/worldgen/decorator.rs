@@ -0,0 +1,387 @@
+use std::collections::HashMap;
+use std::f64::consts::PI;
+
+use crate::block::{
+    BIRCH_LEAVES, BIRCH_LOG, CACTUS, COAL_ORE, DANDELION, DEAD_BUSH, DIAMOND_ORE, DIRT, FERN, GOLD_ORE, GRASS, IRON_ORE,
+    LAPIS_ORE, OAK_LEAVES, OAK_LOG, POPPY, REDSTONE_ORE, SAND, SPRUCE_LEAVES, SPRUCE_LOG, STONE, TALL_GRASS,
+};
+use crate::world::{BlockPos, BlockState, Chunk, ChunkPos, World, CHUNK_HEIGHT};
+use crate::worldgen::biome::Biome;
+use crate::worldgen::random::JavaRandom;
+
+/// What a placement is allowed to overwrite
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Replace {
+    Air,
+    Stone,
+    /// Air, leaves and plants, so trunks can push through a neighbouring tree's canopy
+    Soft,
+}
+
+impl Replace {
+    pub fn allows(self, block: BlockState) -> bool {
+        match self {
+            Replace::Air => block.is_air(),
+            Replace::Stone => block == STONE,
+            // by id, so every kind of leaves and grass counts whatever its meta
+            Replace::Soft => block.is_air() || [OAK_LEAVES, TALL_GRASS, DEAD_BUSH, DANDELION, POPPY].iter().any(|soft| soft.id() == block.id()),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Placement {
+    pub pos: BlockPos,
+    pub state: BlockState,
+    pub replace: Replace,
+}
+
+impl Placement {
+    /// Places the block if whatever is there now may be replaced, returns whether it did
+    pub fn apply(&self, chunk: &mut Chunk) -> bool {
+        let (x, y, z) = self.pos.local();
+        if !self.replace.allows(chunk.get_block(x, y, z)) {
+            return false;
+        }
+        chunk.set_block(x, y, z, self.state);
+        true
+    }
+}
+
+/// (veins per chunk, blocks per vein, lowest y, highest y)
+const ORES: [(BlockState, i32, i32, i32, i32); 5] = [
+    (COAL_ORE, 20, 17, 0, 128),
+    (IRON_ORE, 20, 9, 0, 64),
+    (GOLD_ORE, 2, 9, 0, 32),
+    (REDSTONE_ORE, 8, 8, 0, 16),
+    (DIAMOND_ORE, 1, 8, 0, 16),
+];
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum Tree {
+    Oak,
+    Birch,
+    Spruce,
+}
+
+/// How much of each decoration a biome gets per chunk, roughly what its BiomeDecorator has in vanilla
+struct BiomeDecorations {
+    trees: i32,
+    grass: i32,
+    flowers: i32,
+    dead_bushes: i32,
+    cacti: i32,
+}
+
+fn decorations(biome: Biome) -> BiomeDecorations {
+    let (trees, grass, flowers, dead_bushes, cacti) = match biome {
+        Biome::Ocean => (0, 1, 0, 0, 0),
+        Biome::Plains => (0, 10, 4, 0, 0),
+        Biome::Desert => (0, 0, 0, 2, 10),
+        Biome::ExtremeHills => (0, 1, 2, 0, 0),
+        Biome::Forest => (10, 2, 2, 0, 0),
+        Biome::Taiga => (10, 10, 0, 0, 0),
+        Biome::Swampland => (2, 5, 1, 1, 0),
+    };
+    BiomeDecorations { trees, grass, flowers, dead_bushes, cacti }
+}
+
+fn tree_for(biome: Biome, random: &mut JavaRandom) -> Tree {
+    match biome {
+        Biome::Taiga => Tree::Spruce,
+        Biome::ExtremeHills if random.next_int_bounded(3) == 0 => Tree::Spruce,
+        Biome::Forest if random.next_int_bounded(5) == 0 => Tree::Birch,
+        _ => Tree::Oak,
+    }
+}
+
+/// Highest non-air y in a column of the chunk
+fn top_block(chunk: &Chunk, x: usize, z: usize) -> Option<i32> {
+    (0..CHUNK_HEIGHT).rev().find(|&y| !chunk.get_block(x, y, z).is_air()).map(|y| y as i32)
+}
+
+/// Works out everything the decoration pass wants to put down for a chunk: ore veins, trees
+/// and plants. The chunk's own terrain is all it looks at, so the result only depends on the
+/// seed and what the terrain generator made. Placements can land outside the chunk.
+pub fn decorate(seed: i64, chunk: &Chunk) -> Vec<Placement> {
+    let mut out = Vec::new();
+    let pos = chunk.pos;
+    let origin = pos.origin();
+    // the same per chunk seeding vanilla's populate uses
+    let mut random = JavaRandom::new(seed);
+    let x_factor = random.next_long() / 2 * 2 + 1;
+    let z_factor = random.next_long() / 2 * 2 + 1;
+    random.set_seed((pos.x as i64).wrapping_mul(x_factor).wrapping_add((pos.z as i64).wrapping_mul(z_factor)) ^ seed);
+
+    for (ore, count, size, min_y, max_y) in ORES {
+        for _ in 0..count {
+            let x = origin.x + random.next_int_bounded(16);
+            let y = random.next_int_bounded(max_y - min_y) + min_y;
+            let z = origin.z + random.next_int_bounded(16);
+            vein(&mut out, &mut random, ore, size, BlockPos::new(x, y, z));
+        }
+    }
+    // lapis is the odd one out, bunched up around y 16 instead of spread evenly
+    let x = origin.x + random.next_int_bounded(16);
+    let y = random.next_int_bounded(16) + random.next_int_bounded(16);
+    let z = origin.z + random.next_int_bounded(16);
+    vein(&mut out, &mut random, LAPIS_ORE, 7, BlockPos::new(x, y, z));
+
+    let biome = Biome::from_id(chunk.biome(8, 8)).unwrap_or(Biome::Plains);
+    let settings = decorations(biome);
+
+    let mut trees = settings.trees;
+    if random.next_int_bounded(10) == 0 {
+        trees += 1;
+    }
+    for _ in 0..trees {
+        let (x, z) = (random.next_int_bounded(16) as usize, random.next_int_bounded(16) as usize);
+        let kind = tree_for(biome, &mut random);
+        let Some(top) = top_block(chunk, x, z) else { continue };
+        let ground = chunk.get_block(x, top as usize, z);
+        if ground != GRASS && ground != DIRT {
+            continue;
+        }
+        let base = BlockPos::new(origin.x + x as i32, top + 1, origin.z + z as i32);
+        match kind {
+            Tree::Oak => blob_tree(&mut out, &mut random, base, OAK_LOG, OAK_LEAVES, 4),
+            Tree::Birch => blob_tree(&mut out, &mut random, base, BIRCH_LOG, BIRCH_LEAVES, 5),
+            Tree::Spruce => spruce_tree(&mut out, &mut random, base),
+        }
+    }
+
+    let mut plant = |random: &mut JavaRandom, count: i32, ground: BlockState, block: &dyn Fn(&mut JavaRandom) -> BlockState| {
+        for _ in 0..count {
+            let (x, z) = (random.next_int_bounded(16) as usize, random.next_int_bounded(16) as usize);
+            let state = block(random);
+            if let Some(top) = top_block(chunk, x, z) {
+                if chunk.get_block(x, top as usize, z) == ground && top + 1 < CHUNK_HEIGHT as i32 {
+                    out.push(Placement { pos: BlockPos::new(origin.x + x as i32, top + 1, origin.z + z as i32), state, replace: Replace::Air });
+                }
+            }
+        }
+    };
+    let taiga = biome == Biome::Taiga;
+    plant(&mut random, settings.grass, GRASS, &|random| if taiga && random.next_int_bounded(4) != 0 { FERN } else { TALL_GRASS });
+    plant(&mut random, settings.flowers, GRASS, &|random| if random.next_int_bounded(3) == 0 { POPPY } else { DANDELION });
+    plant(&mut random, settings.dead_bushes, SAND, &|_| DEAD_BUSH);
+
+    for _ in 0..settings.cacti {
+        let (x, z) = (random.next_int_bounded(16) as usize, random.next_int_bounded(16) as usize);
+        let bound = random.next_int_bounded(3) + 1;
+        let height = 1 + random.next_int_bounded(bound);
+        let Some(top) = top_block(chunk, x, z) else { continue };
+        // cacti need air on every side, at least as far as this chunk can tell
+        let clear = |dx: i32, dz: i32| {
+            let (nx, nz) = (x as i32 + dx, z as i32 + dz);
+            !(0..16).contains(&nx) || !(0..16).contains(&nz) || chunk.get_block(nx as usize, top as usize + 1, nz as usize).is_air()
+        };
+        if chunk.get_block(x, top as usize, z) != SAND || !(clear(1, 0) && clear(-1, 0) && clear(0, 1) && clear(0, -1)) {
+            continue;
+        }
+        for y in 1..=height {
+            out.push(Placement { pos: BlockPos::new(origin.x + x as i32, top + y, origin.z + z as i32), state: CACTUS, replace: Replace::Air });
+        }
+    }
+
+    out
+}
+
+/// Vanilla's WorldGenMinable, a line of overlapping blobs that thicken towards the middle
+fn vein(out: &mut Vec<Placement>, random: &mut JavaRandom, ore: BlockState, size: i32, center: BlockPos) {
+    let angle = random.next_float() as f64 * PI;
+    let spread = size as f64 / 8.0;
+    let (x0, x1) = (center.x as f64 + angle.sin() * spread, center.x as f64 - angle.sin() * spread);
+    let (z0, z1) = (center.z as f64 + angle.cos() * spread, center.z as f64 - angle.cos() * spread);
+    let y0 = (center.y + random.next_int_bounded(3) - 2) as f64;
+    let y1 = (center.y + random.next_int_bounded(3) - 2) as f64;
+
+    for i in 0..size {
+        let t = i as f64 / size as f64;
+        let (cx, cy, cz) = (x0 + (x1 - x0) * t, y0 + (y1 - y0) * t, z0 + (z1 - z0) * t);
+        let scale = random.next_double() * size as f64 / 16.0;
+        let radius = ((PI * t).sin() + 1.0) * scale + 1.0;
+        let half = radius / 2.0;
+
+        for x in (cx - half).floor() as i32..=(cx + half).floor() as i32 {
+            let dx = (x as f64 + 0.5 - cx) / half;
+            for y in (cy - half).floor() as i32..=(cy + half).floor() as i32 {
+                let dy = (y as f64 + 0.5 - cy) / half;
+                for z in (cz - half).floor() as i32..=(cz + half).floor() as i32 {
+                    let dz = (z as f64 + 0.5 - cz) / half;
+                    if dx * dx + dy * dy + dz * dz < 1.0 && (0..CHUNK_HEIGHT as i32).contains(&y) {
+                        out.push(Placement { pos: BlockPos::new(x, y, z), state: ore, replace: Replace::Stone });
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Oak and birch, a straight trunk under a rounded box of leaves
+fn blob_tree(out: &mut Vec<Placement>, random: &mut JavaRandom, base: BlockPos, log: BlockState, leaves: BlockState, min_height: i32) {
+    let height = min_height + random.next_int_bounded(3);
+    if base.y + height + 1 >= CHUNK_HEIGHT as i32 {
+        return;
+    }
+    let top = base.y + height;
+    for y in top - 3..=top {
+        let layer = y - top;
+        let radius = 1 - layer / 2;
+        for dx in -radius..=radius {
+            for dz in -radius..=radius {
+                // knock the corners off, always on the top layer and sometimes below it
+                if dx.abs() == radius && dz.abs() == radius && (random.next_int_bounded(2) == 0 || layer == 0) {
+                    continue;
+                }
+                out.push(Placement { pos: BlockPos::new(base.x + dx, y, base.z + dz), state: leaves, replace: Replace::Air });
+            }
+        }
+    }
+    for y in 0..height {
+        out.push(Placement { pos: BlockPos::new(base.x, base.y + y, base.z), state: log, replace: Replace::Soft });
+    }
+}
+
+/// Vanilla's WorldGenTaiga2, rings of leaves that grow and shrink on the way down
+fn spruce_tree(out: &mut Vec<Placement>, random: &mut JavaRandom, base: BlockPos) {
+    let height = random.next_int_bounded(4) + 6;
+    let bare = 1 + random.next_int_bounded(2);
+    let max_radius = 2 + random.next_int_bounded(2);
+    if base.y + height + 1 >= CHUNK_HEIGHT as i32 {
+        return;
+    }
+
+    let mut radius = random.next_int_bounded(2);
+    let mut grow_to = 1;
+    let mut restart = 0;
+    for layer in 0..=height - bare {
+        let y = base.y + height - layer;
+        for dx in -radius..=radius {
+            for dz in -radius..=radius {
+                if dx.abs() != radius || dz.abs() != radius || radius <= 0 {
+                    out.push(Placement { pos: BlockPos::new(base.x + dx, y, base.z + dz), state: SPRUCE_LEAVES, replace: Replace::Air });
+                }
+            }
+        }
+        if radius >= grow_to {
+            radius = restart;
+            restart = 1;
+            grow_to = (grow_to + 1).min(max_radius);
+        } else {
+            radius += 1;
+        }
+    }
+
+    let trunk = height - random.next_int_bounded(3);
+    for y in 0..trunk {
+        out.push(Placement { pos: BlockPos::new(base.x, base.y + y, base.z), state: SPRUCE_LOG, replace: Replace::Soft });
+    }
+}
+
+/// Decorations that reached into chunks which didn't exist yet, waiting for those chunks to be generated.
+#[derive(Default)]
+pub struct PendingDecorations {
+    pending: HashMap<ChunkPos, Vec<Placement>>,
+}
+
+impl PendingDecorations {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.pending.values().map(Vec::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Holds on to a placement until its chunk comes along
+    pub fn defer(&mut self, placement: Placement) {
+        self.pending.entry(placement.pos.chunk()).or_default().push(placement);
+    }
+
+    /// Everything still waiting, for saving
+    pub fn placements(&self) -> impl Iterator<Item = &Placement> {
+        self.pending.values().flatten()
+    }
+
+    /// Decorates a freshly generated chunk and puts it in the world. Anything waiting on this chunk
+    /// goes in after its own decorations. Placements for loaded neighbours go straight in, the
+    /// rest wait here. Returns the blocks that were placed in already loaded chunks.
+    pub fn add_chunk(&mut self, world: &mut World, seed: i64, mut chunk: Chunk) -> Vec<BlockPos> {
+        let placements = decorate(seed, &chunk);
+        let mut spilled = Vec::new();
+        for placement in &placements {
+            let target = placement.pos.chunk();
+            if target == chunk.pos {
+                placement.apply(&mut chunk);
+            } else if let Some(neighbour) = world.chunk_mut(target) {
+                if placement.apply(neighbour) {
+                    spilled.push(placement.pos);
+                }
+            } else {
+                self.defer(*placement);
+            }
+        }
+        for placement in self.pending.remove(&chunk.pos).unwrap_or_default() {
+            placement.apply(&mut chunk);
+        }
+        world.load_chunk(chunk);
+        spilled
+    }
+
+    /// Puts a chunk from a save in the world along with anything that was waiting on it. Returns
+    /// whether any of that went in, which leaves the light it was saved with out of date.
+    pub fn add_saved_chunk(&mut self, world: &mut World, mut chunk: Chunk) -> bool {
+        let mut placed = false;
+        for placement in self.pending.remove(&chunk.pos).unwrap_or_default() {
+            placed |= placement.apply(&mut chunk);
+        }
+        world.load_chunk(chunk);
+        placed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Game;
+    use crate::storage::{load_game, save_world};
+
+    #[test]
+    fn trees_across_the_border_survive_a_save() {
+        let dir = std::env::temp_dir().join(format!("nanocraft-decorations-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        // a forest chunk well away from everything else that's generated, with trees by its edges
+        let pos = ChunkPos::new(44, 44);
+        let mut game = Game::new(5);
+        game.generate_around(pos, 0);
+        let waiting: Vec<Placement> = game.decorations.placements()
+            .filter(|placement| {
+                let target = placement.pos.chunk();
+                (target.x - pos.x).abs() <= 1 && (target.z - pos.z).abs() <= 1
+            })
+            .copied()
+            .collect();
+        // trunks always stand in the chunk that grew them, it's the leaves that reach over
+        assert!(waiting.iter().any(|placement| placement.state == OAK_LEAVES));
+        assert!(waiting.iter().all(|placement| placement.pos.chunk() != pos));
+
+        save_world(&mut game, &dir).unwrap();
+        let mut loaded = load_game(&dir).unwrap().unwrap();
+        // the same neighbours generated without ever saving are what they should look like
+        for placement in &waiting {
+            game.generate_around(placement.pos.chunk(), 0);
+            loaded.generate_around(placement.pos.chunk(), 0);
+        }
+        for placement in &waiting {
+            assert_eq!(loaded.world.get_block(placement.pos), game.world.get_block(placement.pos), "{:?}", placement.pos);
+        }
+        let leaves = waiting.iter().filter(|placement| placement.state == OAK_LEAVES);
+        assert!(leaves.filter(|placement| loaded.world.get_block(placement.pos) == OAK_LEAVES).count() > 10);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
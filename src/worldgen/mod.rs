@@ -0,0 +1,202 @@
+use crate::block::{BEDROCK, GRAVEL, SAND, STONE, WATER};
+use crate::world::{BlockState, Chunk, ChunkPos, CHUNK_HEIGHT, CHUNK_WIDTH};
+use crate::worldgen::biome::{Biome, BiomeProvider};
+use crate::worldgen::carver::{carve, Caves, Ravines};
+use crate::worldgen::noise::OctaveNoise;
+use crate::worldgen::random::JavaRandom;
+
+pub mod biome;
+pub mod carver;
+pub mod decorator;
+pub mod noise;
+pub mod random;
+
+pub const SEA_LEVEL: i32 = 63;
+
+/// Biome heights get blended on a grid this many blocks apart and interpolated in between
+const BLEND_SPACING: i32 = 4;
+/// How many grid points out in each direction go into a blend
+const BLEND_RADIUS: i32 = 2;
+
+/// Fills chunks from a world seed. Everything it does only depends on the seed and the chunk
+/// position, so generating the same chunk twice always gives the same blocks.
+pub struct TerrainGenerator {
+    pub seed: i64,
+    pub biomes: BiomeProvider,
+    height_noise: OctaveNoise,
+    detail_noise: OctaveNoise,
+    surface_noise: OctaveNoise,
+}
+
+impl TerrainGenerator {
+    pub fn new(seed: i64) -> Self {
+        let mut random = JavaRandom::new(seed);
+        Self {
+            seed,
+            biomes: BiomeProvider::new(seed),
+            height_noise: OctaveNoise::new(&mut random, 6),
+            detail_noise: OctaveNoise::new(&mut random, 4),
+            surface_noise: OctaveNoise::new(&mut random, 4),
+        }
+    }
+
+    /// Average of the biome heights around a blend grid point
+    fn blended_height(&self, biome_at: impl Fn(i32, i32) -> Biome, x: i32, z: i32) -> (f32, f32) {
+        let (mut base, mut variation) = (0.0, 0.0);
+        for dz in -BLEND_RADIUS..=BLEND_RADIUS {
+            for dx in -BLEND_RADIUS..=BLEND_RADIUS {
+                let (b, v) = biome_at(x + dx * BLEND_SPACING, z + dz * BLEND_SPACING).height();
+                base += b;
+                variation += v;
+            }
+        }
+        let count = ((BLEND_RADIUS * 2 + 1) * (BLEND_RADIUS * 2 + 1)) as f32;
+        (base / count, variation / count)
+    }
+
+    /// `corners` are the blended heights at the grid points around the column, in -x-z, +x-z, -x+z, +x+z order
+    fn column_height(&self, x: i32, z: i32, corners: [(f32, f32); 4]) -> i32 {
+        let tx = x.rem_euclid(BLEND_SPACING) as f32 / BLEND_SPACING as f32;
+        let tz = z.rem_euclid(BLEND_SPACING) as f32 / BLEND_SPACING as f32;
+        let lerp = |a: f32, b: f32, t: f32| a + (b - a) * t;
+        let base = lerp(lerp(corners[0].0, corners[1].0, tx), lerp(corners[2].0, corners[3].0, tx), tz);
+        let variation = lerp(lerp(corners[0].1, corners[1].1, tx), lerp(corners[2].1, corners[3].1, tx), tz);
+
+        let (x, z) = (x as f64, z as f64);
+        let hills = self.height_noise.sample_2d(x / 256.0, z / 256.0);
+        let detail = self.detail_noise.sample_2d(x / 48.0, z / 48.0) * 0.25;
+        let height = SEA_LEVEL as f64 + 1.0 + base as f64 * 17.0 + (hills + detail) * (variation as f64 * 40.0 + 3.0);
+        height.round().clamp(1.0, CHUNK_HEIGHT as f64 - 2.0) as i32
+    }
+
+    /// Y of the topmost terrain block in a column, before anything gets carved out of it
+    pub fn height_at(&self, x: i32, z: i32) -> i32 {
+        let (gx, gz) = (x.div_euclid(BLEND_SPACING) * BLEND_SPACING, z.div_euclid(BLEND_SPACING) * BLEND_SPACING);
+        let biome_at = |x, z| self.biomes.biome_at(x, z);
+        let corners = [
+            self.blended_height(biome_at, gx, gz),
+            self.blended_height(biome_at, gx + BLEND_SPACING, gz),
+            self.blended_height(biome_at, gx, gz + BLEND_SPACING),
+            self.blended_height(biome_at, gx + BLEND_SPACING, gz + BLEND_SPACING),
+        ];
+        self.column_height(x, z, corners)
+    }
+
+    pub fn generate_chunk(&self, pos: ChunkPos) -> Chunk {
+        let mut chunk = Chunk::new(pos);
+        // per chunk random for the bedrock floor, seeded the same way vanilla seeds its chunk randoms
+        let mut random = JavaRandom::new((pos.x as i64).wrapping_mul(341873128712).wrapping_add((pos.z as i64).wrapping_mul(132897987541)) ^ self.seed);
+        let origin = pos.origin();
+
+        // look every grid biome up once rather than once per blend that needs it
+        const GRID: i32 = CHUNK_WIDTH as i32 / BLEND_SPACING + 1 + BLEND_RADIUS * 2;
+        let grid: Vec<Biome> = (0..GRID * GRID)
+            .map(|i| self.biomes.biome_at(origin.x + (i % GRID - BLEND_RADIUS) * BLEND_SPACING, origin.z + (i / GRID - BLEND_RADIUS) * BLEND_SPACING))
+            .collect();
+        let grid_biome = |x: i32, z: i32| {
+            let (gx, gz) = ((x - origin.x) / BLEND_SPACING + BLEND_RADIUS, (z - origin.z) / BLEND_SPACING + BLEND_RADIUS);
+            grid[(gz * GRID + gx) as usize]
+        };
+        const CORNERS: i32 = CHUNK_WIDTH as i32 / BLEND_SPACING + 1;
+        let blended: Vec<(f32, f32)> = (0..CORNERS * CORNERS)
+            .map(|i| self.blended_height(grid_biome, origin.x + i % CORNERS * BLEND_SPACING, origin.z + i / CORNERS * BLEND_SPACING))
+            .collect();
+
+        for z in 0..CHUNK_WIDTH {
+            for x in 0..CHUNK_WIDTH {
+                let (world_x, world_z) = (origin.x + x as i32, origin.z + z as i32);
+                let biome = self.biomes.biome_at(world_x, world_z);
+                chunk.set_biome(x, z, biome.id());
+
+                let (cx, cz) = (x as i32 / BLEND_SPACING, z as i32 / BLEND_SPACING);
+                let corner = |dx: i32, dz: i32| blended[((cz + dz) * CORNERS + cx + dx) as usize];
+                let height = self.column_height(world_x, world_z, [corner(0, 0), corner(1, 0), corner(0, 1), corner(1, 1)]);
+
+                let depth = 3 + (self.surface_noise.sample_2d(world_x as f64 / 16.0, world_z as f64 / 16.0) * 1.5).round() as i32;
+                let (top, filler) = surface_blocks(biome, height);
+
+                for y in 0..=height.max(SEA_LEVEL) {
+                    let block = if y < 5 && y <= random.next_int_bounded(5) {
+                        BEDROCK
+                    } else if y > height {
+                        WATER
+                    } else if y == height {
+                        top
+                    } else if y > height - depth {
+                        filler
+                    } else {
+                        STONE
+                    };
+                    chunk.set_block(x, y as usize, z, block);
+                }
+            }
+        }
+
+        carve(&Caves, self.seed, &mut chunk);
+        carve(&Ravines, self.seed, &mut chunk);
+
+        chunk
+    }
+}
+
+/// What the top block and the few below it are made of, beaches and sea floors get sand and gravel
+fn surface_blocks(biome: Biome, height: i32) -> (BlockState, BlockState) {
+    if height < SEA_LEVEL - 1 {
+        (GRAVEL, biome.filler_block())
+    } else if height <= SEA_LEVEL + 1 && biome != Biome::Swampland {
+        (SAND, SAND)
+    } else {
+        (biome.top_block(), biome.filler_block())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// FNV-1a over every block and biome, enough to notice any change to the terrain
+    fn checksum(chunk: &Chunk) -> u64 {
+        let mut hash = 0xcbf29ce484222325u64;
+        let mut add = |byte: u8| hash = (hash ^ byte as u64).wrapping_mul(0x100000001b3);
+        for y in 0..CHUNK_HEIGHT {
+            for z in 0..CHUNK_WIDTH {
+                for x in 0..CHUNK_WIDTH {
+                    let state = chunk.get_block(x, y, z);
+                    add(state.id() as u8);
+                    add(state.meta());
+                }
+            }
+        }
+        for &biome in &chunk.biomes {
+            add(biome);
+        }
+        hash
+    }
+
+    #[test]
+    fn same_chunk_twice_is_identical() {
+        let generator = TerrainGenerator::new(1234);
+        for pos in [ChunkPos::new(0, 0), ChunkPos::new(-3, 7)] {
+            assert_eq!(checksum(&generator.generate_chunk(pos)), checksum(&generator.generate_chunk(pos)));
+        }
+    }
+
+    #[test]
+    fn generators_with_one_seed_agree() {
+        let (a, b) = (TerrainGenerator::new(-98765), TerrainGenerator::new(-98765));
+        for pos in [ChunkPos::new(0, 0), ChunkPos::new(12, -5), ChunkPos::new(-40, -40)] {
+            assert_eq!(checksum(&a.generate_chunk(pos)), checksum(&b.generate_chunk(pos)));
+            assert_eq!(a.height_at(pos.x * 16, pos.z * 16), b.height_at(pos.x * 16, pos.z * 16));
+        }
+        let other = TerrainGenerator::new(-98764);
+        assert_ne!(checksum(&a.generate_chunk(ChunkPos::new(0, 0))), checksum(&other.generate_chunk(ChunkPos::new(0, 0))));
+    }
+
+    #[test]
+    fn terrain_matches_pinned_checksums() {
+        // update these only on purpose, a change here changes every world generated from now on
+        let generator = TerrainGenerator::new(42);
+        let sums: Vec<u64> = [ChunkPos::new(0, 0), ChunkPos::new(5, -9)].iter().map(|&pos| checksum(&generator.generate_chunk(pos))).collect();
+        assert_eq!(sums, [2050906085155357061, 18392621326704071241]);
+    }
+}
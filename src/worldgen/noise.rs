@@ -0,0 +1,93 @@
+use crate::worldgen::random::JavaRandom;
+
+/// Ken Perlin's improved noise, with the permutation table shuffled from a seeded random
+/// the way vanilla's NoiseGeneratorImproved does it.
+#[derive(Clone)]
+pub struct PerlinNoise {
+    permutation: [u8; 512],
+    offset: [f64; 3],
+}
+
+impl PerlinNoise {
+    pub fn new(random: &mut JavaRandom) -> Self {
+        let offset = [random.next_double() * 256.0, random.next_double() * 256.0, random.next_double() * 256.0];
+        let mut table: [u8; 256] = std::array::from_fn(|i| i as u8);
+        for i in 0..256 {
+            let j = random.next_int_bounded(256 - i as i32) as usize + i;
+            table.swap(i, j);
+        }
+        let mut permutation = [0; 512];
+        for (i, value) in permutation.iter_mut().enumerate() {
+            *value = table[i & 255];
+        }
+        Self { permutation, offset }
+    }
+
+    /// Roughly in -1..1
+    pub fn sample(&self, x: f64, y: f64, z: f64) -> f64 {
+        let (x, y, z) = (x + self.offset[0], y + self.offset[1], z + self.offset[2]);
+        let (fx, fy, fz) = (x.floor(), y.floor(), z.floor());
+        let (xi, yi, zi) = ((fx as i64 & 255) as usize, (fy as i64 & 255) as usize, (fz as i64 & 255) as usize);
+        let (x, y, z) = (x - fx, y - fy, z - fz);
+        let (u, v, w) = (fade(x), fade(y), fade(z));
+
+        let p = &self.permutation;
+        let a = p[xi] as usize + yi;
+        let aa = p[a] as usize + zi;
+        let ab = p[a + 1] as usize + zi;
+        let b = p[xi + 1] as usize + yi;
+        let ba = p[b] as usize + zi;
+        let bb = p[b + 1] as usize + zi;
+
+        lerp(w,
+            lerp(v,
+                lerp(u, grad(p[aa], x, y, z), grad(p[ba], x - 1.0, y, z)),
+                lerp(u, grad(p[ab], x, y - 1.0, z), grad(p[bb], x - 1.0, y - 1.0, z))),
+            lerp(v,
+                lerp(u, grad(p[aa + 1], x, y, z - 1.0), grad(p[ba + 1], x - 1.0, y, z - 1.0)),
+                lerp(u, grad(p[ab + 1], x, y - 1.0, z - 1.0), grad(p[bb + 1], x - 1.0, y - 1.0, z - 1.0))))
+    }
+}
+
+fn fade(t: f64) -> f64 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn lerp(t: f64, a: f64, b: f64) -> f64 {
+    a + t * (b - a)
+}
+
+fn grad(hash: u8, x: f64, y: f64, z: f64) -> f64 {
+    let h = hash & 15;
+    let u = if h < 8 { x } else { y };
+    let v = if h < 4 { y } else if h == 12 || h == 14 { x } else { z };
+    (if h & 1 == 0 { u } else { -u }) + (if h & 2 == 0 { v } else { -v })
+}
+
+/// Several layers of Perlin noise, each at double the frequency and half the amplitude of the last.
+#[derive(Clone)]
+pub struct OctaveNoise {
+    octaves: Vec<PerlinNoise>,
+}
+
+impl OctaveNoise {
+    pub fn new(random: &mut JavaRandom, octaves: usize) -> Self {
+        Self { octaves: (0..octaves).map(|_| PerlinNoise::new(random)).collect() }
+    }
+
+    pub fn sample(&self, x: f64, y: f64, z: f64) -> f64 {
+        let mut total = 0.0;
+        let mut frequency = 1.0;
+        let mut amplitude = 1.0;
+        for octave in &self.octaves {
+            total += octave.sample(x * frequency, y * frequency, z * frequency) * amplitude;
+            frequency *= 2.0;
+            amplitude /= 2.0;
+        }
+        total
+    }
+
+    pub fn sample_2d(&self, x: f64, z: f64) -> f64 {
+        self.sample(x, 0.0, z)
+    }
+}
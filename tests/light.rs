@@ -0,0 +1,235 @@
+use nanocraft::block::{BlockRegistry, GLOWSTONE, OAK_LEAVES, STONE, TORCH};
+use nanocraft::light::{block_changed, light_chunk};
+use nanocraft::world::{BlockPos, BlockState, Chunk, ChunkPos, LightType, World};
+
+const ROOF_Y: i32 = 70;
+
+/// Empty chunks from -1,-1 to 1,1, lit one after another
+fn empty_world(registry: &BlockRegistry) -> World {
+    let mut world = World::new();
+    for x in -1..=1 {
+        for z in -1..=1 {
+            world.load_chunk(Chunk::new(ChunkPos::new(x, z)));
+            light_chunk(&mut world, registry, ChunkPos::new(x, z));
+        }
+    }
+    world
+}
+
+/// `empty_world` with the middle chunk covered by a stone roof
+fn roofed_world(registry: &BlockRegistry) -> World {
+    let mut world = World::new();
+    let mut roofed = Chunk::new(ChunkPos::new(0, 0));
+    for x in 0..16 {
+        for z in 0..16 {
+            roofed.set_block(x, ROOF_Y as usize, z, STONE);
+        }
+    }
+    world.load_chunk(roofed);
+    light_chunk(&mut world, registry, ChunkPos::new(0, 0));
+    for x in -1..=1 {
+        for z in -1..=1 {
+            if (x, z) != (0, 0) {
+                world.load_chunk(Chunk::new(ChunkPos::new(x, z)));
+                light_chunk(&mut world, registry, ChunkPos::new(x, z));
+            }
+        }
+    }
+    world
+}
+
+fn set_block(world: &mut World, registry: &BlockRegistry, pos: BlockPos, state: BlockState) {
+    assert!(world.set_block(pos, state));
+    block_changed(world, registry, pos);
+}
+
+/// Light along x from `from` for `length` blocks
+fn row(world: &World, light: LightType, from: BlockPos, length: i32) -> Vec<u8> {
+    (0..length).map(|i| world.get_light(light, BlockPos::new(from.x + i, from.y, from.z))).collect()
+}
+
+/// Light of a `size` by `size` square on the xz plane, one row per z
+fn grid(world: &World, light: LightType, corner: BlockPos, size: i32) -> Vec<Vec<u8>> {
+    (0..size).map(|z| row(world, light, BlockPos::new(corner.x, corner.y, corner.z + z), size)).collect()
+}
+
+/// Under the roof, light comes in sideways from the open sky around it
+const UNDER_ROOF: [u8; 16] = [14, 13, 12, 11, 10, 9, 8, 7, 7, 8, 9, 10, 11, 12, 13, 14];
+
+#[test]
+fn open_sky_is_full_light_all_the_way_down() {
+    let registry = BlockRegistry::vanilla();
+    let world = empty_world(&registry);
+    for y in [0, 64, 255] {
+        assert_eq!(row(&world, LightType::Sky, BlockPos::new(-16, y, 5), 48), vec![15; 48]);
+        assert_eq!(row(&world, LightType::Block, BlockPos::new(-16, y, 5), 48), vec![0; 48]);
+    }
+}
+
+#[test]
+fn sky_light_spreads_under_a_roof_from_the_edges() {
+    let registry = BlockRegistry::vanilla();
+    let world = roofed_world(&registry);
+    assert_eq!(row(&world, LightType::Sky, BlockPos::new(0, ROOF_Y - 1, 8), 16), UNDER_ROOF);
+    assert_eq!(row(&world, LightType::Sky, BlockPos::new(0, ROOF_Y, 8), 16), vec![0; 16]);
+    assert_eq!(row(&world, LightType::Sky, BlockPos::new(0, ROOF_Y + 1, 8), 16), vec![15; 16]);
+    // the roof's edge against the chunks around it
+    assert_eq!(row(&world, LightType::Sky, BlockPos::new(-2, ROOF_Y - 1, 8), 4), [15, 15, 14, 13]);
+}
+
+#[test]
+fn breaking_a_roof_block_lets_the_sky_in_and_placing_it_back_takes_it_away() {
+    let registry = BlockRegistry::vanilla();
+    let mut world = roofed_world(&registry);
+    let hole = BlockPos::new(8, ROOF_Y, 8);
+
+    set_block(&mut world, &registry, hole, BlockState::AIR);
+    #[rustfmt::skip]
+    let expected = vec![
+        vec![11, 12, 13, 12, 11],
+        vec![12, 13, 14, 13, 12],
+        vec![13, 14, 15, 14, 13],
+        vec![12, 13, 14, 13, 12],
+        vec![11, 12, 13, 12, 11],
+    ];
+    assert_eq!(grid(&world, LightType::Sky, BlockPos::new(6, ROOF_Y - 1, 6), 5), expected);
+    // straight down the hole it's open sky all the way
+    for y in 0..ROOF_Y {
+        assert_eq!(world.get_light(LightType::Sky, BlockPos::new(8, y, 8)), 15, "at y {y}");
+    }
+
+    set_block(&mut world, &registry, hole, STONE);
+    assert_eq!(world.get_light(LightType::Sky, hole), 0);
+    assert_eq!(row(&world, LightType::Sky, BlockPos::new(0, ROOF_Y - 1, 8), 16), UNDER_ROOF);
+    assert_eq!(row(&world, LightType::Sky, BlockPos::new(0, 10, 8), 16), UNDER_ROOF);
+    // nothing left over anywhere from the hole
+    let untouched = roofed_world(&registry);
+    for y in [0, 30, ROOF_Y - 1, ROOF_Y, ROOF_Y + 1] {
+        let corner = BlockPos::new(-4, y, -4);
+        assert_eq!(grid(&world, LightType::Sky, corner, 24), grid(&untouched, LightType::Sky, corner, 24), "at y {y}");
+    }
+}
+
+#[test]
+fn torch_light_falls_off_by_one_per_block() {
+    let registry = BlockRegistry::vanilla();
+    let mut world = empty_world(&registry);
+    let torch = BlockPos::new(8, 64, 8);
+    set_block(&mut world, &registry, torch, TORCH);
+
+    #[rustfmt::skip]
+    let expected = vec![
+        vec![10, 11, 12, 11, 10],
+        vec![11, 12, 13, 12, 11],
+        vec![12, 13, 14, 13, 12],
+        vec![11, 12, 13, 12, 11],
+        vec![10, 11, 12, 11, 10],
+    ];
+    assert_eq!(grid(&world, LightType::Block, BlockPos::new(6, 64, 6), 5), expected);
+    assert_eq!(world.get_light(LightType::Block, BlockPos::new(8, 77, 8)), 1);
+    assert_eq!(world.get_light(LightType::Block, BlockPos::new(8, 78, 8)), 0);
+    // sky light doesn't care, a torch lets it straight through
+    assert_eq!(world.get_light(LightType::Sky, torch), 15);
+}
+
+#[test]
+fn breaking_a_torch_takes_all_its_light_with_it() {
+    let registry = BlockRegistry::vanilla();
+    let mut world = empty_world(&registry);
+    let torch = BlockPos::new(8, 64, 8);
+    set_block(&mut world, &registry, torch, TORCH);
+    set_block(&mut world, &registry, torch, BlockState::AIR);
+    for y in 50..=78 {
+        assert_eq!(row(&world, LightType::Block, BlockPos::new(-16, y, 8), 48), vec![0; 48], "at y {y}");
+    }
+}
+
+#[test]
+fn removing_one_of_two_lights_keeps_the_other() {
+    let registry = BlockRegistry::vanilla();
+    let mut world = empty_world(&registry);
+    let left = BlockPos::new(2, 64, 8);
+    let right = BlockPos::new(10, 64, 8);
+    set_block(&mut world, &registry, left, GLOWSTONE);
+    set_block(&mut world, &registry, right, TORCH);
+    assert_eq!(row(&world, LightType::Block, BlockPos::new(0, 64, 8), 14), [13, 14, 15, 14, 13, 12, 11, 11, 12, 13, 14, 13, 12, 11]);
+
+    set_block(&mut world, &registry, left, BlockState::AIR);
+    assert_eq!(row(&world, LightType::Block, BlockPos::new(0, 64, 8), 14), [4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 13, 12, 11]);
+}
+
+#[test]
+fn walls_block_light_and_light_goes_around_them() {
+    let registry = BlockRegistry::vanilla();
+    let mut world = empty_world(&registry);
+    // a wall along z at x = 9, three high and five wide, with a torch right next to it
+    for y in 63..=65 {
+        for z in 6..=10 {
+            set_block(&mut world, &registry, BlockPos::new(9, y, z), STONE);
+        }
+    }
+    set_block(&mut world, &registry, BlockPos::new(8, 64, 8), TORCH);
+
+    assert_eq!(world.get_light(LightType::Block, BlockPos::new(9, 64, 8)), 0);
+    // behind the wall light comes over the top, going round the sides is further
+    assert_eq!(world.get_light(LightType::Block, BlockPos::new(10, 64, 8)), 14 - 6);
+    assert_eq!(row(&world, LightType::Block, BlockPos::new(6, 64, 8), 7), [12, 13, 14, 0, 8, 7, 6]);
+}
+
+#[test]
+fn light_crosses_chunk_borders() {
+    let registry = BlockRegistry::vanilla();
+    let mut world = empty_world(&registry);
+    set_block(&mut world, &registry, BlockPos::new(15, 64, 0), TORCH);
+    // along x into chunk 1,0 and along z back into chunk 0,-1
+    assert_eq!(row(&world, LightType::Block, BlockPos::new(14, 64, 0), 5), [13, 14, 13, 12, 11]);
+    assert_eq!(world.get_light(LightType::Block, BlockPos::new(15, 64, -1)), 13);
+    assert_eq!(world.get_light(LightType::Block, BlockPos::new(16, 64, -1)), 12);
+
+    set_block(&mut world, &registry, BlockPos::new(15, 64, 0), BlockState::AIR);
+    assert_eq!(row(&world, LightType::Block, BlockPos::new(14, 64, 0), 5), [0; 5]);
+    assert_eq!(world.get_light(LightType::Block, BlockPos::new(16, 64, -1)), 0);
+}
+
+#[test]
+fn light_flows_into_a_chunk_loaded_next_to_it() {
+    let registry = BlockRegistry::vanilla();
+    let mut world = World::new();
+    let mut lit = Chunk::new(ChunkPos::new(0, 0));
+    lit.set_block(15, 64, 4, GLOWSTONE);
+    world.load_chunk(lit);
+    light_chunk(&mut world, &registry, ChunkPos::new(0, 0));
+    assert_eq!(world.get_light(LightType::Block, BlockPos::new(16, 64, 4)), 0);
+
+    // and out of one loaded next to it, a roofed chunk gets sky light from its open neighbour
+    let mut roofed = Chunk::new(ChunkPos::new(1, 0));
+    for x in 0..16 {
+        for z in 0..16 {
+            roofed.set_block(x, ROOF_Y as usize, z, STONE);
+        }
+    }
+    world.load_chunk(roofed);
+    light_chunk(&mut world, &registry, ChunkPos::new(1, 0));
+    assert_eq!(row(&world, LightType::Block, BlockPos::new(15, 64, 4), 4), [15, 14, 13, 12]);
+    assert_eq!(row(&world, LightType::Sky, BlockPos::new(14, ROOF_Y - 1, 4), 5), [15, 15, 14, 13, 12]);
+}
+
+#[test]
+fn leaves_dim_sky_light_by_one_more() {
+    let registry = BlockRegistry::vanilla();
+    let mut world = empty_world(&registry);
+    let leaves = BlockPos::new(8, 80, 8);
+    set_block(&mut world, &registry, leaves, OAK_LEAVES);
+    let column: Vec<u8> = (76..=81).rev().map(|y| world.get_light(LightType::Sky, BlockPos::new(8, y, 8))).collect();
+    // straight below only loses what spreading sideways from the open columns around it would
+    assert_eq!(column, [15, 14, 14, 14, 14, 14]);
+
+    // boxed in on the sides, the light under the leaves only comes through them
+    for (dx, dz) in [(1, 0), (-1, 0), (0, 1), (0, -1)] {
+        for y in 70..80 {
+            set_block(&mut world, &registry, BlockPos::new(8 + dx, y, 8 + dz), STONE);
+        }
+    }
+    let column: Vec<u8> = (76..=80).rev().map(|y| world.get_light(LightType::Sky, BlockPos::new(8, y, 8))).collect();
+    assert_eq!(column, [14, 13, 12, 11, 10]);
+}